@@ -0,0 +1,40 @@
+// Embeds every migration under `migrations/`, both directions, so that `conduit migrate`
+// works without the source tree. Generates the `MIGRATIONS` list used by `src/cli/migrate.rs`.
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+    let root = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("migrations");
+    let mut dirs = fs::read_dir(&root)
+        .expect("cannot read migrations/")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.join("up.sql").is_file())
+        .collect::<Vec<_>>();
+    dirs.sort();
+
+    let mut out = String::from("pub const MIGRATIONS: &[EmbeddedMigration] = &[\n");
+    for dir in dirs {
+        println!("cargo:rerun-if-changed={}", dir.display());
+        let name = dir.file_name().unwrap().to_str().unwrap();
+        // Same versions as the diesel CLI records, so either can be used on one database.
+        let version = name.split('_').next().unwrap().replace('-', "");
+        writeln!(
+            out,
+            "    EmbeddedMigration {{ version: {:?}, name: {:?}, up: include_str!({:?}), down: include_str!({:?}) }},",
+            version,
+            name,
+            dir.join("up.sql"),
+            dir.join("down.sql"),
+        )
+        .unwrap();
+    }
+    out.push_str("];\n");
+    fs::write(
+        Path::new(&env::var("OUT_DIR").unwrap()).join("migrations.rs"),
+        out,
+    )
+    .unwrap();
+}
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX tags_name_idx;
+DROP TABLE tag_follows;
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE tag_follows (
+  user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  tag_name TEXT NOT NULL,
+  PRIMARY KEY (user_id, tag_name),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX tag_follows_tag_name_idx ON tag_follows (tag_name);
+CREATE INDEX tags_name_idx ON tags (name);
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE slug_history (
+  slug TEXT PRIMARY KEY,
+  article_id UUID NOT NULL REFERENCES articles (id) ON DELETE CASCADE,
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX slug_history_article_id_idx ON slug_history (article_id);
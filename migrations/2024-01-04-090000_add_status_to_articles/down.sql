@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX articles_status_published_at_idx;
+ALTER TABLE articles
+  DROP CONSTRAINT scheduled_articles_need_published_at,
+  DROP CONSTRAINT articles_status_is_valid,
+  DROP COLUMN published_at,
+  DROP COLUMN status;
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+ALTER TABLE articles
+  ADD COLUMN status TEXT NOT NULL DEFAULT 'published',
+  ADD COLUMN published_at TIMESTAMP;
+
+UPDATE articles SET published_at = created_at;
+
+ALTER TABLE articles
+  ADD CONSTRAINT articles_status_is_valid
+  CHECK (status IN ('draft', 'published', 'scheduled'));
+
+ALTER TABLE articles
+  ADD CONSTRAINT scheduled_articles_need_published_at
+  CHECK (status <> 'scheduled' OR published_at IS NOT NULL);
+
+CREATE INDEX articles_status_published_at_idx ON articles (status, published_at);
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+CREATE TABLE article_revisions (
+  id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+  article_id UUID NOT NULL REFERENCES articles (id) ON DELETE CASCADE,
+  author_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  title TEXT NOT NULL,
+  description TEXT NOT NULL,
+  body TEXT NOT NULL,
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX article_revisions_article_id_created_at_idx ON article_revisions (article_id, created_at);
+
+-- Existing articles start their history with their current content.
+INSERT INTO article_revisions (article_id, author_id, title, description, body, created_at, updated_at)
+SELECT id, author_id, title, description, body, updated_at, updated_at FROM articles;
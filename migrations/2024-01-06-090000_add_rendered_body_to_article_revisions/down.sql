@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE article_revisions
+  DROP COLUMN toc,
+  DROP COLUMN body_html;
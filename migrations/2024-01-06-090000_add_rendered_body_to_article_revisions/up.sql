@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- Rendered Markdown is cached per revision and filled in lazily on first read.
+ALTER TABLE article_revisions
+  ADD COLUMN body_html TEXT,
+  ADD COLUMN toc JSONB;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX articles_search_vector_idx;
+DROP TRIGGER articles_search_vector_trigger ON articles;
+DROP FUNCTION articles_search_vector_update();
+ALTER TABLE articles DROP COLUMN search_vector;
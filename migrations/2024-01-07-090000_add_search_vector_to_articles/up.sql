@@ -0,0 +1,20 @@
+-- Your SQL goes here
+ALTER TABLE articles ADD COLUMN search_vector TSVECTOR NOT NULL DEFAULT ''::tsvector;
+
+CREATE OR REPLACE FUNCTION articles_search_vector_update() RETURNS TRIGGER AS $$
+BEGIN
+  NEW.search_vector :=
+    setweight(to_tsvector('english', coalesce(NEW.title, '')), 'A') ||
+    setweight(to_tsvector('english', coalesce(NEW.description, '')), 'B') ||
+    setweight(to_tsvector('english', coalesce(NEW.body, '')), 'C');
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER articles_search_vector_trigger
+  BEFORE INSERT OR UPDATE OF title, description, body ON articles
+  FOR EACH ROW EXECUTE PROCEDURE articles_search_vector_update();
+
+UPDATE articles SET title = title;
+
+CREATE INDEX articles_search_vector_idx ON articles USING GIN (search_vector);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX tags_article_id_name_idx;
+CREATE INDEX tags_article_id_idx ON tags (article_id);
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+DELETE FROM tags a
+  USING tags b
+  WHERE a.article_id = b.article_id
+    AND a.name = b.name
+    AND a.id > b.id;
+
+DROP INDEX tags_article_id_idx;
+CREATE UNIQUE INDEX tags_article_id_name_idx ON tags (article_id, name);
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX comments_root_id_idx;
+DROP INDEX comments_parent_id_idx;
+ALTER TABLE comments
+  DROP CONSTRAINT replies_need_parent_and_root,
+  DROP COLUMN deleted_at,
+  DROP COLUMN depth,
+  DROP COLUMN root_id,
+  DROP COLUMN parent_id;
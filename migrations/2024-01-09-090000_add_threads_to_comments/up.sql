@@ -0,0 +1,13 @@
+-- Your SQL goes here
+ALTER TABLE comments
+  ADD COLUMN parent_id UUID REFERENCES comments (id) ON DELETE CASCADE,
+  ADD COLUMN root_id UUID REFERENCES comments (id) ON DELETE CASCADE,
+  ADD COLUMN depth INTEGER NOT NULL DEFAULT 0,
+  ADD COLUMN deleted_at TIMESTAMP;
+
+ALTER TABLE comments
+  ADD CONSTRAINT replies_need_parent_and_root
+  CHECK ((parent_id IS NULL) = (root_id IS NULL) AND (parent_id IS NULL) = (depth = 0));
+
+CREATE INDEX comments_parent_id_idx ON comments (parent_id);
+CREATE INDEX comments_root_id_idx ON comments (root_id);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE comment_revisions;
+ALTER TABLE comments DROP COLUMN edited_at;
+ALTER TABLE comments RENAME COLUMN created_at TO create_at;
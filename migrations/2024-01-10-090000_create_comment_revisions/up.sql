@@ -0,0 +1,13 @@
+-- Your SQL goes here
+ALTER TABLE comments RENAME COLUMN create_at TO created_at;
+ALTER TABLE comments ADD COLUMN edited_at TIMESTAMP;
+
+-- Every body a comment had before an edit.
+CREATE TABLE comment_revisions (
+  id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+  comment_id UUID NOT NULL REFERENCES comments (id) ON DELETE CASCADE,
+  body TEXT NOT NULL,
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX comment_revisions_comment_id_created_at_idx ON comment_revisions (comment_id, created_at);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE comments DROP COLUMN hidden_at;
+
+ALTER TABLE articles
+  DROP COLUMN comments_followers_only,
+  DROP COLUMN comments_locked;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE articles
+  ADD COLUMN comments_locked BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN comments_followers_only BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE comments ADD COLUMN hidden_at TIMESTAMP;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- One row per user mentioned in an article body (comment_id IS NULL) or a comment.
+CREATE TABLE mentions (
+  id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+  user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  author_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  article_id UUID NOT NULL REFERENCES articles (id) ON DELETE CASCADE,
+  comment_id UUID REFERENCES comments (id) ON DELETE CASCADE,
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX mentions_user_id_created_at_idx ON mentions (user_id, created_at);
+CREATE INDEX mentions_article_id_idx ON mentions (article_id);
+CREATE INDEX mentions_comment_id_idx ON mentions (comment_id);
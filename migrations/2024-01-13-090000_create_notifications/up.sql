@@ -0,0 +1,20 @@
+-- Your SQL goes here
+CREATE TABLE notifications (
+  id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+  user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  actor_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  kind TEXT NOT NULL,
+  article_id UUID REFERENCES articles (id) ON DELETE CASCADE,
+  comment_id UUID REFERENCES comments (id) ON DELETE CASCADE,
+  -- Unread notifications sharing a group key are shown as one, e.g. "5 people favorited X".
+  group_key TEXT NOT NULL,
+  read_at TIMESTAMP,
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+ALTER TABLE notifications
+  ADD CONSTRAINT notifications_kind_is_valid
+  CHECK (kind IN ('follow', 'favorite', 'comment', 'mention'));
+
+CREATE INDEX notifications_user_id_created_at_idx ON notifications (user_id, created_at);
+CREATE INDEX notifications_user_id_group_key_idx ON notifications (user_id, group_key) WHERE read_at IS NULL;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE digest_articles;
+DROP TABLE digest_subscriptions;
@@ -0,0 +1,20 @@
+-- Your SQL goes here
+CREATE TABLE digest_subscriptions (
+  user_id UUID PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+  frequency TEXT NOT NULL,
+  last_sent_at TIMESTAMP,
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+ALTER TABLE digest_subscriptions
+  ADD CONSTRAINT digest_subscriptions_frequency_is_valid
+  CHECK (frequency IN ('daily', 'weekly'));
+
+-- Every article that went out in someone's digest, so it never goes out to them again.
+CREATE TABLE digest_articles (
+  user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  article_id UUID NOT NULL REFERENCES articles (id) ON DELETE CASCADE,
+  sent_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  PRIMARY KEY (user_id, article_id)
+);
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+ALTER TABLE users
+  ADD COLUMN role TEXT NOT NULL DEFAULT 'user',
+  ADD COLUMN disabled_at TIMESTAMP;
+
+ALTER TABLE users
+  ADD CONSTRAINT users_role_is_valid
+  CHECK (role IN ('user', 'admin'));
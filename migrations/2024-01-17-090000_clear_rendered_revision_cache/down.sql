@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+-- Nothing to undo: the cleared renderings are rebuilt by `conduit reindex`.
+SELECT 1;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- Renderings cached before heading and footnote ids were prefixed are stale. `conduit reindex`
+-- renders them again; until then they are rendered on the fly.
+UPDATE article_revisions SET body_html = NULL, toc = NULL;
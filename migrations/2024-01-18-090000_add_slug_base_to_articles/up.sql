@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- The slug an article's title maps to, before any suffix added to make it unique. Existing
+-- articles can't tell a generated suffix from their title, so their whole slug is the base.
+ALTER TABLE articles ADD COLUMN slug_base TEXT;
+UPDATE articles SET slug_base = slug;
+ALTER TABLE articles ALTER COLUMN slug_base SET NOT NULL;
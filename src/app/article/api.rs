@@ -1,46 +1,180 @@
-use super::model::Article;
+use super::model::{Article, SlugHistory};
 use super::service;
 use super::{
-    request, 
-    response::{MultipleArticlesResponse, SingleArticleResponse},
+    request,
+    response::{ArticleContent, MultipleArticlesResponse, SingleArticleResponse},
 };
+use crate::app::mention;
 use crate::error::AppError;
 use crate::middleware::auth;
 use crate::middleware::state::AppState;
-use actix_web::{web, HttpRequest, HttpResponse};
+use crate::utils::cursor::Cursor;
+use crate::utils::markdown::Rendered;
+use crate::utils::mention::MentionRange;
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use diesel::pg::PgConnection;
 use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
 
 type ArticleTitleSlug = String;
 
+// `?render=html` opts into server-rendered Markdown (`bodyHtml` and `toc`).
+const RENDER_HTML: &str = "html";
+
+fn fetch_rendered_bodies_in_order(
+    conn: &PgConnection,
+    render: &Option<String>,
+    articles_list: Vec<&Article>,
+) -> Result<Vec<Option<Rendered>>, AppError> {
+    if render.as_deref() != Some(RENDER_HTML) {
+        return Ok(vec![]);
+    }
+    let mut rendered_bodies = service::fetch_rendered_bodies(conn, &articles_list)?;
+    let rendered_bodies = articles_list
+        .iter()
+        .map(|article| rendered_bodies.remove(&article.id))
+        .collect();
+    Ok(rendered_bodies)
+}
+
+fn attach_rendered_bodies(
+    res: &mut MultipleArticlesResponse,
+    rendered_bodies: Vec<Option<Rendered>>,
+) {
+    for (content, rendered) in res.articles.iter_mut().zip(rendered_bodies) {
+        if let Some(rendered) = rendered {
+            content.attach_rendered(rendered);
+        }
+    }
+}
+
+fn fetch_mention_ranges_in_order(
+    conn: &PgConnection,
+    articles_list: Vec<&Article>,
+) -> Result<Vec<Vec<MentionRange>>, AppError> {
+    let mut ranges = mention::service::fetch_article_mention_ranges(conn, &articles_list)?;
+    let ranges = articles_list
+        .iter()
+        .map(|article| ranges.remove(&article.id).unwrap_or_default())
+        .collect();
+    Ok(ranges)
+}
+
+fn attach_mention_ranges(contents: &mut [ArticleContent], ranges: Vec<Vec<MentionRange>>) {
+    for (content, ranges) in contents.iter_mut().zip(ranges) {
+        content.attach_mentions(ranges);
+    }
+}
+
+fn parse_page_cursor(
+    after: &Option<String>,
+    before: &Option<String>,
+) -> Result<Option<service::PageCursor>, AppError> {
+    match (after, before) {
+        (Some(_), Some(_)) => Err(AppError::UnprocessableEntity(json!({
+            "error": "after and before cannot be combined"
+        }))),
+        (Some(after), None) => Ok(Some(service::PageCursor::After(Cursor::decode(after)?))),
+        (None, Some(before)) => Ok(Some(service::PageCursor::Before(Cursor::decode(before)?))),
+        (None, None) => Ok(None),
+    }
+}
+
+fn attach_page_cursors(res: &mut MultipleArticlesResponse, cursors: service::PageCursors) {
+    res.next_cursor = cursors.next.map(|cursor| cursor.encode());
+    res.prev_cursor = cursors.prev.map(|cursor| cursor.encode());
+}
+
 #[derive(Deserialize)]
 pub struct ArticlesListQueryParameter {
+    q: Option<String>,
     tag: Option<String>,
     author: Option<String>,
     favorited: Option<String>,
+    sort: Option<String>,
     limit: Option<i64>,
     offset: Option<i64>,
+    after: Option<String>,
+    before: Option<String>,
+    render: Option<String>,
 }
 
 pub async fn index(
     state: web::Data<AppState>,
+    req: HttpRequest,
     params: web::Query<ArticlesListQueryParameter>,
 ) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req).ok();
     let conn = state.get_conn()?;
-    let offset = std::cmp::min(params.offset.to_owned().unwrap_or(0), 100);
-    let limit = params.limit.unwrap_or(20);
+    let offset = params.offset.unwrap_or(0).max(0);
+    let limit = state.config.pagination.limit(params.limit);
+    let cursor = parse_page_cursor(&params.after, &params.before)?;
+    let q = params
+        .q
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(str::to_owned);
 
-    let (articles_list, articles_count) = service::fetch_articles_list(
+    let (articles_list, articles_count, cursors) = service::fetch_articles_list(
         &conn,
         service::FetchArticlesList {
+            me: auth_user,
+            q: q.clone(),
             tag: params.tag.clone(),
             author: params.author.clone(),
             favorited: params.favorited.clone(),
+            sort: params
+                .sort
+                .as_deref()
+                .map(service::ArticleSort::parse)
+                .transpose()?,
             offset,
             limit,
+            cursor,
         },
     )?;
 
-    let res = MultipleArticlesResponse::from((articles_list, articles_count));
+    let rendered_bodies = fetch_rendered_bodies_in_order(
+        &conn,
+        &params.render,
+        articles_list
+            .iter()
+            .map(|((article, _, _), _)| article)
+            .collect(),
+    )?;
+    let mut highlights = match &q {
+        Some(q) => service::fetch_search_highlights(
+            &conn,
+            q,
+            &articles_list
+                .iter()
+                .map(|((article, _, _), _)| article)
+                .collect::<Vec<_>>(),
+        )?,
+        None => HashMap::new(),
+    };
+    let article_ids = articles_list
+        .iter()
+        .map(|((article, _, _), _)| article.id)
+        .collect::<Vec<_>>();
+    let mention_ranges = fetch_mention_ranges_in_order(
+        &conn,
+        articles_list
+            .iter()
+            .map(|((article, _, _), _)| article)
+            .collect(),
+    )?;
+    let mut res = MultipleArticlesResponse::from((articles_list, articles_count));
+    attach_page_cursors(&mut res, cursors);
+    attach_rendered_bodies(&mut res, rendered_bodies);
+    attach_mention_ranges(&mut res.articles, mention_ranges);
+    for (content, article_id) in res.articles.iter_mut().zip(article_ids) {
+        if let Some(highlight) = highlights.remove(&article_id) {
+            content.attach_highlight(highlight);
+        }
+    }
     Ok(HttpResponse::Ok().json(res))
 }
 
@@ -48,6 +182,9 @@ pub async fn index(
 pub struct FeedQueryParameter {
     limit: Option<i64>,
     offset: Option<i64>,
+    after: Option<String>,
+    before: Option<String>,
+    render: Option<String>,
 }
 
 pub async fn feed(
@@ -57,31 +194,81 @@ pub async fn feed(
 ) -> Result<HttpResponse, AppError> {
     let auth_user = auth::access_auth_user(&req)?;
     let conn = state.get_conn()?;
-    let offset = std::cmp::min(params.offset.to_owned().unwrap_or(0), 100);
-    let limit = params.limit.unwrap_or(20);
-    let (articles_list, articles_count) = service::fetch_following_articles(
+    let offset = params.offset.unwrap_or(0).max(0);
+    let limit = state.config.pagination.limit(params.limit);
+    let cursor = parse_page_cursor(&params.after, &params.before)?;
+    let (articles_list, articles_count, cursors) = service::fetch_following_articles(
         &conn,
         &service::FetchFollowedArticlesSerivce {
             me: auth_user,
             offset,
             limit,
+            cursor,
         },
     )?;
 
-    let res = MultipleArticlesResponse::from((articles_list, articles_count));
+    let rendered_bodies = fetch_rendered_bodies_in_order(
+        &conn,
+        &params.render,
+        articles_list
+            .iter()
+            .map(|((article, _, _), _)| article)
+            .collect(),
+    )?;
+    let mention_ranges = fetch_mention_ranges_in_order(
+        &conn,
+        articles_list
+            .iter()
+            .map(|((article, _, _), _)| article)
+            .collect(),
+    )?;
+    let mut res = MultipleArticlesResponse::from((articles_list, articles_count));
+    attach_page_cursors(&mut res, cursors);
+    attach_rendered_bodies(&mut res, rendered_bodies);
+    attach_mention_ranges(&mut res.articles, mention_ranges);
     Ok(HttpResponse::Ok().json(res))
 }
 
+#[derive(Deserialize)]
+pub struct ShowQueryParameter {
+    render: Option<String>,
+}
+
 pub async fn show(
     state: web::Data<AppState>,
-    _req: HttpRequest,
+    req: HttpRequest,
     path: web::Path<ArticleTitleSlug>,
+    params: web::Query<ShowQueryParameter>,
 ) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req).ok();
     let conn = state.get_conn()?;
     let article_title_slug = path.into_inner();
-    let (article, profile, favorite_info, tags_list) =
-        service::fetch_article_by_slug(&conn, &service::FetchArticleBySlug { article_title_slug })?;
-    let res = SingleArticleResponse::from((article, profile, favorite_info, tags_list));
+    let fetched = service::fetch_article_by_slug(
+        &conn,
+        &service::FetchArticleBySlug {
+            article_title_slug: article_title_slug.clone(),
+            me: auth_user,
+        },
+    );
+    let (article, profile, favorite_info, tags_list) = match fetched {
+        Err(AppError::NotFound(_)) => {
+            if let Some(current_slug) = SlugHistory::fetch_current_slug(&conn, &article_title_slug)?
+            {
+                return Ok(HttpResponse::MovedPermanently()
+                    .insert_header((header::LOCATION, format!("/api/articles/{}", current_slug)))
+                    .finish());
+            }
+            fetched?
+        }
+        _ => fetched?,
+    };
+    let rendered = fetch_rendered_bodies_in_order(&conn, &params.render, vec![&article])?;
+    let mention_ranges = fetch_mention_ranges_in_order(&conn, vec![&article])?;
+    let mut res = SingleArticleResponse::from((article, profile, favorite_info, tags_list));
+    attach_mention_ranges(std::slice::from_mut(&mut res.article), mention_ranges);
+    if let Some(Some(rendered)) = rendered.into_iter().next() {
+        res.article.attach_rendered(rendered);
+    }
     Ok(HttpResponse::Ok().json(res))
 }
 
@@ -96,14 +283,20 @@ pub async fn create(
         &conn,
         &service::CreateArticleService {
             title: form.article.title.clone(),
-            slug: Article::convert_title_to_slug(&form.article.title),
             description: form.article.description.clone(),
             body: form.article.body.clone(),
             tag_list: form.article.tag_list.to_owned(),
+            status: form.article.status.to_owned(),
+            publish_at: form
+                .article
+                .publish_at
+                .map(|publish_at| publish_at.naive_utc()),
             me: auth_user,
         },
     )?;
-    let res = SingleArticleResponse::from((article, profile, favorite_info, tag_list));
+    let mention_ranges = fetch_mention_ranges_in_order(&conn, vec![&article])?;
+    let mut res = SingleArticleResponse::from((article, profile, favorite_info, tag_list));
+    attach_mention_ranges(std::slice::from_mut(&mut res.article), mention_ranges);
     Ok(HttpResponse::Ok().json(res))
 }
 
@@ -116,25 +309,28 @@ pub async fn update(
     let auth_user = auth::access_auth_user(&req)?;
     let conn = state.get_conn()?;
     let article_title_slug = path.into_inner();
-    let article_slug = &form
-        .article
-        .title
-        .as_ref()
-        .map(|_title| Article::convert_title_to_slug(_title));
-
     let (article, profile, favorite_info, tag_list) = service::update_article(
         &conn,
         &service::UpdateArticleService {
             me: auth_user,
             article_title_slug,
-            slug: article_slug.to_owned(),
             title: form.article.title.clone(),
             description: form.article.description.clone(),
             body: form.article.body.clone(),
+            status: form.article.status.to_owned(),
+            publish_at: form
+                .article
+                .publish_at
+                .map(|publish_at| publish_at.naive_utc()),
+            tag_list: form.article.tag_list.to_owned(),
+            add_tags: form.article.add_tags.to_owned(),
+            remove_tags: form.article.remove_tags.to_owned(),
         },
     )?;
 
-    let res = SingleArticleResponse::from((article, profile, favorite_info, tag_list));
+    let mention_ranges = fetch_mention_ranges_in_order(&conn, vec![&article])?;
+    let mut res = SingleArticleResponse::from((article, profile, favorite_info, tag_list));
+    attach_mention_ranges(std::slice::from_mut(&mut res.article), mention_ranges);
     Ok(HttpResponse::Ok().json(res))
 }
 
@@ -146,7 +342,7 @@ pub async fn delete(
     let auth_user = auth::access_auth_user(&req)?;
     let conn = state.get_conn()?;
     let article_title_slug = path.into_inner();
-    let _ = service::delete_article(
+    service::delete_article(
         &conn,
         &service::DeleteArticle {
             slug: article_title_slug,
@@ -155,3 +351,36 @@ pub async fn delete(
     )?;
     Ok(HttpResponse::Ok().json(()))
 }
+
+#[derive(Deserialize)]
+pub struct DraftsQueryParameter {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+pub async fn drafts(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    params: web::Query<DraftsQueryParameter>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let (articles_list, articles_count) = service::fetch_drafts(
+        &conn,
+        &service::FetchDrafts {
+            me: auth_user,
+            offset: params.offset.unwrap_or(0).max(0),
+            limit: state.config.pagination.limit(params.limit),
+        },
+    )?;
+    let mention_ranges = fetch_mention_ranges_in_order(
+        &conn,
+        articles_list
+            .iter()
+            .map(|((article, _, _), _)| article)
+            .collect(),
+    )?;
+    let mut res = MultipleArticlesResponse::from((articles_list, articles_count));
+    attach_mention_ranges(&mut res.articles, mention_ranges);
+    Ok(HttpResponse::Ok().json(res))
+}
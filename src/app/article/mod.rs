@@ -2,4 +2,5 @@ pub mod api;
 pub mod model;
 pub mod request;
 pub mod response;
+pub mod scheduler;
 pub mod service;
\ No newline at end of file
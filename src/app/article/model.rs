@@ -1,15 +1,23 @@
 use crate::app::user::model::User;
 use crate::error::AppError;
-use crate::schema::articles;
 use crate::schema::articles::dsl::*;
+use crate::schema::{articles, slug_history};
 use crate::utils::converter;
+use crate::utils::markdown;
 use chrono::NaiveDateTime;
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
 use diesel::Insertable;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use uuid::Uuid;
 
+const SLUG_SUFFIX_LEN: usize = 6;
+
+// How often a write is retried when a concurrent one took its slug first.
+const SLUG_ATTEMPTS: usize = 5;
+
 #[derive(Identifiable, Queryable, Debug, Serialize, Deserialize, Associations, Clone)]
 #[belongs_to(User, foreign_key = "author_id")]
 #[table_name = "articles"]
@@ -22,15 +30,26 @@ pub struct Article {
     pub body: String,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub status: String,
+    pub published_at: Option<NaiveDateTime>,
+    pub comments_locked: bool,
+    pub comments_followers_only: bool,
+    pub slug_base: String,
+}
+
+// A slug for a title, and the base it was made from before any suffix.
+#[derive(Debug, Clone)]
+pub struct UniqueSlug {
+    pub slug: String,
+    pub base: String,
 }
 
 impl Article {
-    pub fn create(conn: &PgConnection, record: &CreateArticle) -> Result<Self, AppError> {
-        let article = diesel::insert_into(articles::table)
+    // Returns the raw diesel result so that `write_with_unique_slug` can spot slug conflicts.
+    pub fn create(conn: &PgConnection, record: &CreateArticle) -> QueryResult<Self> {
+        diesel::insert_into(articles::table)
             .values(record)
-            .get_result::<Article>(conn)?;
-
-        Ok(article)
+            .get_result::<Article>(conn)
     }
 
     pub fn update(
@@ -38,19 +57,162 @@ impl Article {
         article_title_slug: &str,
         _author_id: &Uuid,
         record: &UpdateArticle,
-    ) -> Result<Self, AppError> {
-        let article = diesel::update(
+    ) -> QueryResult<Self> {
+        diesel::update(
             articles
                 .filter(articles::slug.eq(article_title_slug))
                 .filter(articles::author_id.eq_all(_author_id)),
         )
-        .set(record)
-        .get_result::<Article>(conn)?;
-        Ok(article)
+        .set((record, articles::updated_at.eq(diesel::dsl::now)))
+        .get_result::<Article>(conn)
+    }
+
+    // Runs `write` with a slug from `generate_unique_slug`. Another article can take the slug
+    // between picking and writing it; the write is then retried under a fresh suffix rather
+    // than failing on the unique constraint.
+    pub fn write_with_unique_slug<T>(
+        conn: &PgConnection,
+        _title: &str,
+        current: Option<&Article>,
+        write: impl Fn(UniqueSlug) -> QueryResult<T>,
+    ) -> Result<T, AppError> {
+        for _ in 0..SLUG_ATTEMPTS {
+            let unique = Self::generate_unique_slug(conn, _title, current)?;
+            // A savepoint, so that a conflict doesn't abort the enclosing transaction.
+            match conn.transaction(|| write(unique)) {
+                Err(err) if Self::is_slug_conflict(&err) => continue,
+                result => return Ok(result?),
+            }
+        }
+        Err(AppError::UnprocessableEntity(json!({
+            "error": "could not find a free slug for the title, please try again"
+        })))
+    }
+
+    fn is_slug_conflict(err: &DieselError) -> bool {
+        match err {
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+                info.constraint_name() == Some("articles_slug_key")
+            }
+            _ => false,
+        }
     }
 
     pub fn convert_title_to_slug(_title: &str) -> String {
-        converter::to_kebab(_title)
+        let _slug = converter::to_slug(_title);
+        if _slug.is_empty() {
+            "article".to_string()
+        } else {
+            _slug
+        }
+    }
+
+    // Returns a slug for `_title` that no other article uses, either as its current slug or
+    // in its slug history. On collision a short random suffix is appended.
+    // `current` is the article being retitled, so that it can keep (or take back) its own slugs.
+    // It keeps its slug, suffix included, as long as the title still maps to the same base.
+    pub fn generate_unique_slug(
+        conn: &PgConnection,
+        _title: &str,
+        current: Option<&Article>,
+    ) -> Result<UniqueSlug, AppError> {
+        let base = Self::convert_title_to_slug(_title);
+        if let Some(current) = current {
+            if current.slug_base == base {
+                return Ok(UniqueSlug {
+                    slug: current.slug.to_owned(),
+                    base,
+                });
+            }
+        }
+        let current_id = current.map(|article| article.id);
+        let mut candidate = base.clone();
+        while Self::is_slug_taken(conn, &candidate, current_id)? {
+            let short_id = Uuid::new_v4().to_simple().to_string();
+            candidate = format!("{}-{}", base, &short_id[..SLUG_SUFFIX_LEN]);
+        }
+        Ok(UniqueSlug {
+            slug: candidate,
+            base,
+        })
+    }
+
+    fn is_slug_taken(
+        conn: &PgConnection,
+        _slug: &str,
+        except_article_id: Option<Uuid>,
+    ) -> Result<bool, AppError> {
+        let except_article_id = except_article_id.unwrap_or_else(Uuid::nil);
+        let taken_by_article = diesel::select(diesel::dsl::exists(
+            articles
+                .filter(slug.eq(_slug))
+                .filter(id.ne(except_article_id)),
+        ))
+        .get_result::<bool>(conn)?;
+        if taken_by_article {
+            return Ok(true);
+        }
+        let taken_by_history = diesel::select(diesel::dsl::exists(
+            slug_history::table
+                .filter(slug_history::slug.eq(_slug))
+                .filter(slug_history::article_id.ne(except_article_id)),
+        ))
+        .get_result::<bool>(conn)?;
+        Ok(taken_by_history)
+    }
+
+    pub fn is_published(&self) -> bool {
+        self.status == ArticleStatus::Published.as_str()
+    }
+
+    // Flips every scheduled article whose time has come to published, and returns them.
+    pub fn publish_due(conn: &PgConnection, now: NaiveDateTime) -> Result<Vec<Self>, AppError> {
+        let list = diesel::update(
+            articles
+                .filter(status.eq(ArticleStatus::Scheduled.as_str()))
+                .filter(published_at.le(now)),
+        )
+        .set(status.eq(ArticleStatus::Published.as_str()))
+        .get_results::<Self>(conn)?;
+        Ok(list)
+    }
+
+    pub fn fetch_next_scheduled_at(conn: &PgConnection) -> Result<Option<NaiveDateTime>, AppError> {
+        let next = articles
+            .filter(status.eq(ArticleStatus::Scheduled.as_str()))
+            .select(diesel::dsl::min(published_at))
+            .first::<Option<NaiveDateTime>>(conn)?;
+        Ok(next)
+    }
+
+    pub fn fetch_by_slug(conn: &PgConnection, _slug: &str) -> Result<Self, AppError> {
+        let item = articles.filter(slug.eq(_slug)).first::<Self>(conn)?;
+        Ok(item)
+    }
+
+    // Drafts and scheduled articles are reported as missing.
+    pub fn fetch_published_by_slug(conn: &PgConnection, _slug: &str) -> Result<Self, AppError> {
+        let item = Self::fetch_by_slug(conn, _slug)?;
+        if !item.is_published() {
+            return Err(AppError::NotFound(
+                json!({ "error": "requested record was not found" }),
+            ));
+        }
+        Ok(item)
+    }
+
+    pub fn update_comment_settings(
+        conn: &PgConnection,
+        _id: Uuid,
+        settings: &CommentSettings,
+    ) -> Result<Self, AppError> {
+        if settings.comments_locked.is_none() && settings.comments_followers_only.is_none() {
+            return Ok(articles.find(_id).first::<Self>(conn)?);
+        }
+        let item = diesel::update(articles.find(_id))
+            .set(settings)
+            .get_result::<Self>(conn)?;
+        Ok(item)
     }
 
     pub fn fetch_by_slug_and_author_id(
@@ -71,21 +233,322 @@ impl Article {
 pub struct CreateArticle {
     pub author_id: Uuid,
     pub slug: String,
+    pub slug_base: String,
     pub title: String,
     pub description: String,
     pub body: String,
+    pub status: String,
+    pub published_at: Option<NaiveDateTime>,
 }
 
 #[derive(AsChangeset)]
 #[table_name = "articles"]
 pub struct UpdateArticle {
     pub slug: Option<String>,
+    pub slug_base: Option<String>,
     pub title: Option<String>,
     pub description: Option<String>,
     pub body: Option<String>,
+    pub status: Option<String>,
+    pub published_at: Option<Option<NaiveDateTime>>,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "articles"]
+pub struct CommentSettings {
+    pub comments_locked: Option<bool>,
+    pub comments_followers_only: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArticleStatus {
+    Draft,
+    Published,
+    Scheduled,
+}
+
+impl ArticleStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArticleStatus::Draft => "draft",
+            ArticleStatus::Published => "published",
+            ArticleStatus::Scheduled => "scheduled",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, AppError> {
+        match text {
+            "draft" => Ok(ArticleStatus::Draft),
+            "published" => Ok(ArticleStatus::Published),
+            "scheduled" => Ok(ArticleStatus::Scheduled),
+            _ => Err(AppError::UnprocessableEntity(json!({
+                "error": "status must be one of draft, published or scheduled"
+            }))),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Publication {
+    pub status: ArticleStatus,
+    pub published_at: Option<NaiveDateTime>,
+}
+
+impl Publication {
+    // Works out the status and publication time for a create (`current` is None) or an
+    // update request. Without an explicit status new articles are published right away
+    // and existing ones keep their state.
+    pub fn resolve(
+        requested: Option<&str>,
+        publish_at: Option<NaiveDateTime>,
+        current: Option<&Article>,
+        now: NaiveDateTime,
+    ) -> Result<Self, AppError> {
+        let requested = requested.map(ArticleStatus::parse).transpose()?;
+        let requested = match (requested, current) {
+            (Some(requested), _) => requested,
+            (None, Some(current)) => ArticleStatus::parse(&current.status)?,
+            (None, None) => ArticleStatus::Published,
+        };
+        match requested {
+            ArticleStatus::Draft => Ok(Publication {
+                status: ArticleStatus::Draft,
+                published_at: None,
+            }),
+            ArticleStatus::Published => {
+                let first_published_at = current
+                    .filter(|current| current.is_published())
+                    .and_then(|current| current.published_at)
+                    .unwrap_or(now);
+                Ok(Publication {
+                    status: ArticleStatus::Published,
+                    published_at: Some(first_published_at),
+                })
+            }
+            ArticleStatus::Scheduled => {
+                let publish_at = publish_at.or_else(|| {
+                    current
+                        .filter(|current| current.status == ArticleStatus::Scheduled.as_str())
+                        .and_then(|current| current.published_at)
+                });
+                match publish_at {
+                    Some(publish_at) if publish_at > now => Ok(Publication {
+                        status: ArticleStatus::Scheduled,
+                        published_at: Some(publish_at),
+                    }),
+                    _ => Err(AppError::UnprocessableEntity(json!({
+                        "error": "scheduled articles need a publishAt in the future"
+                    }))),
+                }
+            }
+        }
+    }
 }
 
 pub struct FetchBySlugAndAuthorId {
     pub slug: String,
     pub author_id: Uuid,
 }
+
+#[derive(Identifiable, Queryable, Associations, Debug, Clone)]
+#[belongs_to(Article, foreign_key = "article_id")]
+#[primary_key(slug)]
+#[table_name = "slug_history"]
+pub struct SlugHistory {
+    pub slug: String,
+    pub article_id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl SlugHistory {
+    // Remembers `old_slug` for the article and forgets `new_slug` if the article is taking
+    // back one of its previous slugs.
+    pub fn record(
+        conn: &PgConnection,
+        _article_id: Uuid,
+        old_slug: &str,
+        new_slug: &str,
+    ) -> Result<(), AppError> {
+        let _ = diesel::delete(
+            slug_history::table
+                .filter(slug_history::slug.eq(new_slug))
+                .filter(slug_history::article_id.eq(_article_id)),
+        )
+        .execute(conn)?;
+        let _ = diesel::insert_into(slug_history::table)
+            .values(&NewSlugHistory {
+                slug: old_slug.to_owned(),
+                article_id: _article_id,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn fetch_current_slug(
+        conn: &PgConnection,
+        old_slug: &str,
+    ) -> Result<Option<String>, AppError> {
+        let current_slug = slug_history::table
+            .inner_join(articles::table)
+            .filter(slug_history::slug.eq(old_slug))
+            .select(articles::slug)
+            .first::<String>(conn)
+            .optional()?;
+        Ok(current_slug)
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "slug_history"]
+pub struct NewSlugHistory {
+    pub slug: String,
+    pub article_id: Uuid,
+}
+
+// NOTE: `articles.search_vector` is not part of schema.rs because diesel 1.x has no tsvector
+// type. It is kept up to date by the `articles_search_vector_trigger` and only referenced
+// through raw SQL, always parsed with `websearch_to_tsquery` so user input is never raw
+// tsquery syntax.
+#[derive(QueryableByName, Debug, Clone)]
+pub struct SearchHighlight {
+    #[sql_type = "diesel::sql_types::Uuid"]
+    pub id: Uuid,
+    #[sql_type = "diesel::sql_types::Text"]
+    pub title: String,
+    #[sql_type = "diesel::sql_types::Text"]
+    pub snippet: String,
+}
+
+const SEARCH_HIGHLIGHTS_QUERY: &str = r#"
+SELECT
+    a.id,
+    ts_headline('english', a.title, q.query,
+        'HighlightAll=true, StartSel=<mark>, StopSel=</mark>') AS title,
+    ts_headline('english', a.description || ' ' || a.body, q.query,
+        'MaxFragments=2, MaxWords=30, MinWords=10, FragmentDelimiter=" … ", StartSel=<mark>, StopSel=</mark>') AS snippet
+FROM articles a, websearch_to_tsquery('english', $1) AS q(query)
+WHERE a.id = ANY($2)
+"#;
+
+impl SearchHighlight {
+    pub fn fetch_list(
+        conn: &PgConnection,
+        q: &str,
+        article_ids: &[Uuid],
+    ) -> Result<Vec<Self>, AppError> {
+        use diesel::sql_types::{Array, Text};
+        let list = diesel::sql_query(SEARCH_HIGHLIGHTS_QUERY)
+            .bind::<Text, _>(q)
+            .bind::<Array<diesel::sql_types::Uuid>, _>(article_ids)
+            .load::<Self>(conn)?;
+        let list = list
+            .into_iter()
+            .map(|highlight| Self {
+                title: markdown::sanitize_highlight(&highlight.title),
+                snippet: markdown::sanitize_highlight(&highlight.snippet),
+                ..highlight
+            })
+            .collect();
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::db::testing;
+    use std::cell::Cell;
+
+    fn article(_status: ArticleStatus, _published_at: Option<NaiveDateTime>) -> Article {
+        let now = chrono::Utc::now().naive_utc();
+        Article {
+            id: Uuid::new_v4(),
+            author_id: Uuid::new_v4(),
+            slug: "slug".to_string(),
+            title: "title".to_string(),
+            description: "description".to_string(),
+            body: "body".to_string(),
+            created_at: now,
+            updated_at: now,
+            status: _status.as_str().to_string(),
+            published_at: _published_at,
+            comments_locked: false,
+            comments_followers_only: false,
+            slug_base: "slug".to_string(),
+        }
+    }
+
+    #[test]
+    fn resolve_publication_for_new_article() {
+        let now = chrono::Utc::now().naive_utc();
+        let later = now + chrono::Duration::hours(1);
+
+        let published = Publication::resolve(None, None, None, now).unwrap();
+        assert_eq!(ArticleStatus::Published, published.status);
+        assert_eq!(Some(now), published.published_at);
+
+        let draft = Publication::resolve(Some("draft"), None, None, now).unwrap();
+        assert_eq!(None, draft.published_at);
+
+        let scheduled = Publication::resolve(Some("scheduled"), Some(later), None, now).unwrap();
+        assert_eq!(Some(later), scheduled.published_at);
+
+        assert!(Publication::resolve(Some("scheduled"), None, None, now).is_err());
+        assert!(Publication::resolve(Some("scheduled"), Some(now), None, now).is_err());
+        assert!(Publication::resolve(Some("unknown"), None, None, now).is_err());
+    }
+
+    #[test]
+    fn resolve_publication_for_existing_article() {
+        let now = chrono::Utc::now().naive_utc();
+        let earlier = now - chrono::Duration::days(1);
+
+        let current = article(ArticleStatus::Published, Some(earlier));
+        let kept = Publication::resolve(None, None, Some(&current), now).unwrap();
+        assert_eq!(Some(earlier), kept.published_at);
+
+        let current = article(ArticleStatus::Draft, None);
+        let kept = Publication::resolve(None, None, Some(&current), now).unwrap();
+        assert_eq!(ArticleStatus::Draft, kept.status);
+        let published = Publication::resolve(Some("published"), None, Some(&current), now).unwrap();
+        assert_eq!(Some(now), published.published_at);
+    }
+
+    #[test]
+    fn slug_conflicts_from_concurrent_writes_are_retried() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let me = testing::insert_user(&conn, "slug_racer");
+        let record = |unique: &UniqueSlug| CreateArticle {
+            author_id: me.id,
+            slug: unique.slug.to_owned(),
+            slug_base: unique.base.to_owned(),
+            title: "Raced".to_string(),
+            description: "description".to_string(),
+            body: "body".to_string(),
+            status: "published".to_string(),
+            published_at: None,
+        };
+        let attempts = Cell::new(0);
+        let article = Article::write_with_unique_slug(&conn, "Raced", None, |unique| {
+            attempts.set(attempts.get() + 1);
+            if attempts.get() == 1 {
+                // Another writer takes the slug between the check and the insert.
+                Article::create(&conn, &record(&unique))?;
+            }
+            Article::create(&conn, &record(&unique))
+        })
+        .unwrap();
+        assert_eq!(2, attempts.get());
+        assert_eq!("raced", article.slug_base);
+        // The conflict only rolled back its savepoint; the transaction is still usable.
+        assert_eq!(
+            article.id,
+            Article::fetch_by_slug(&conn, &article.slug).unwrap().id
+        );
+    }
+}
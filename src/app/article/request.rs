@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize)]
@@ -12,6 +13,8 @@ pub struct CreateArticleInner {
     pub description: String,
     pub body: String,
     pub tag_list: Option<Vec<String>>,
+    pub status: Option<String>,
+    pub publish_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -20,8 +23,14 @@ pub struct UpdateArticleRequest {
 }
 
 #[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct UpdateArticleInner {
     pub title: Option<String>,
     pub description: Option<String>,
     pub body: Option<String>,
+    pub status: Option<String>,
+    pub publish_at: Option<DateTime<Utc>>,
+    pub tag_list: Option<Vec<String>>,
+    pub add_tags: Option<Vec<String>>,
+    pub remove_tags: Option<Vec<String>>,
 }
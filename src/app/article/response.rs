@@ -1,8 +1,10 @@
-use crate::app::article::model::Article;
+use crate::app::article::model::{Article, SearchHighlight};
 use crate::app::favorite::model::FavoriteInfo;
 use crate::app::profile::model::Profile;
 use crate::app::tag::model::Tag;
 use crate::utils::date::Iso8601;
+use crate::utils::markdown::{Rendered, TocEntry};
+use crate::utils::mention::MentionRange;
 use serde::{Deserialize, Serialize};
 use std::convert::From;
 
@@ -29,14 +31,22 @@ impl From<(Article, Profile, FavoriteInfo, Vec<Tag>)> for SingleArticleResponse
                     .collect(),
                 created_at: Iso8601(article.created_at),
                 updated_at: Iso8601(article.updated_at),
+                status: article.status,
+                published_at: article.published_at.map(Iso8601),
                 favorited: favorite_info.is_favorited.to_owned(),
                 favorites_count: favorite_info.favorites_count.to_owned(),
+                comments_locked: article.comments_locked,
+                comments_followers_only: article.comments_followers_only,
                 author: AuthorContent {
                     username: profile.username,
                     bio: profile.bio,
                     image: profile.image,
                     following: profile.following,
                 },
+                body_html: None,
+                toc: None,
+                highlight: None,
+                mentions: None,
             },
         }
     }
@@ -47,6 +57,8 @@ impl From<(Article, Profile, FavoriteInfo, Vec<Tag>)> for SingleArticleResponse
 pub struct MultipleArticlesResponse {
     pub articles: Vec<ArticleContent>,
     pub articles_count: ArticleCount,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
 }
 
 type ArticlesCount = i64;
@@ -69,6 +81,8 @@ impl From<Item> for MultipleArticlesResponse {
         Self {
             articles_count,
             articles,
+            next_cursor: None,
+            prev_cursor: None,
         }
     }
 }
@@ -83,9 +97,21 @@ pub struct ArticleContent {
     pub tag_list: Vec<String>,
     pub created_at: Iso8601,
     pub updated_at: Iso8601,
+    pub status: String,
+    pub published_at: Option<Iso8601>,
     pub favorited: bool,
     pub favorites_count: i64,
+    pub comments_locked: bool,
+    pub comments_followers_only: bool,
     pub author: AuthorContent,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_html: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toc: Option<Vec<TocEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<HighlightContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mentions: Option<Vec<MentionRange>>,
 }
 
 impl From<(Article, Profile, FavoriteInfo, Vec<Tag>)> for ArticleContent {
@@ -100,18 +126,50 @@ impl From<(Article, Profile, FavoriteInfo, Vec<Tag>)> for ArticleContent {
             tag_list: tag_list.iter().map(move |tag| tag.name.clone()).collect(),
             created_at: Iso8601(article.created_at),
             updated_at: Iso8601(article.updated_at),
+            status: article.status,
+            published_at: article.published_at.map(Iso8601),
             favorited: favorite_info.is_favorited.to_owned(),
             favorites_count: favorite_info.favorites_count.to_owned(),
+            comments_locked: article.comments_locked,
+            comments_followers_only: article.comments_followers_only,
             author: AuthorContent {
                 username: profile.username,
                 bio: profile.bio,
                 image: profile.image,
                 following: profile.following,
             },
+            body_html: None,
+            toc: None,
+            highlight: None,
+            mentions: None,
         }
     }
 }
 
+impl ArticleContent {
+    pub fn attach_rendered(&mut self, rendered: Rendered) {
+        self.body_html = Some(rendered.html);
+        self.toc = Some(rendered.toc);
+    }
+
+    pub fn attach_mentions(&mut self, mentions: Vec<MentionRange>) {
+        self.mentions = Some(mentions);
+    }
+
+    pub fn attach_highlight(&mut self, highlight: SearchHighlight) {
+        self.highlight = Some(HighlightContent {
+            title: highlight.title,
+            snippet: highlight.snippet,
+        });
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct HighlightContent {
+    pub title: String,
+    pub snippet: String,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct AuthorContent {
     pub username: String,
@@ -0,0 +1,105 @@
+use super::model::Article;
+use super::service;
+use crate::app::mention;
+use crate::error::AppError;
+use crate::utils::db::{self, DbPool};
+use actix_web::rt;
+use chrono::Utc;
+use std::time::Duration;
+
+// Publishes scheduled articles when their `published_at` comes due.
+// It sleeps until the next known schedule, but never longer than the polling interval so
+// that articles scheduled in the meantime are picked up too.
+pub fn spawn(pool: DbPool, interval: Duration) {
+    rt::spawn(async move {
+        loop {
+            let pool = pool.clone();
+            let next_run = actix_web::web::block(move || publish_due(&pool, interval)).await;
+            let wait = match next_run {
+                Ok(Ok(wait)) => wait,
+                Ok(Err(err)) => {
+                    error!("failed to publish scheduled articles: {}", err);
+                    interval
+                }
+                Err(_err) => interval,
+            };
+            rt::time::sleep(wait).await;
+        }
+    });
+}
+
+fn publish_due(pool: &DbPool, interval: Duration) -> Result<Duration, AppError> {
+    let conn = pool.get()?;
+    let now = Utc::now().naive_utc();
+    let published = db::unit_of_work(&conn, || {
+        let published = Article::publish_due(&conn, now)?;
+        for article in &published {
+            mention::service::sync_article_mentions(&conn, article)?;
+            service::publish_feed_item(&conn, article)?;
+        }
+        Ok(published)
+    })?;
+    if !published.is_empty() {
+        info!("published {} scheduled article(s)", published.len());
+    }
+
+    let wait = Article::fetch_next_scheduled_at(&conn)?
+        .and_then(|next| (next - now).to_std().ok())
+        .map(|until_next| until_next.max(Duration::from_secs(1)))
+        .unwrap_or(interval);
+    Ok(wait.min(interval))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::article::model::CreateArticle;
+    use crate::schema::notifications;
+    use crate::utils::db::testing;
+    use diesel::prelude::*;
+
+    #[test]
+    fn mentions_are_notified_when_a_scheduled_article_is_published() {
+        let pool = match testing::pool() {
+            Some(pool) => pool,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+        let author = testing::insert_user(&conn, "sched_author");
+        let reader = testing::insert_user(&conn, "sched_reader");
+        let publish_at = Utc::now().naive_utc() + chrono::Duration::hours(1);
+        let article = Article::create(
+            &conn,
+            &CreateArticle {
+                author_id: author.id,
+                slug: "scheduled-mention".to_string(),
+                slug_base: "scheduled-mention".to_string(),
+                title: "Scheduled".to_string(),
+                description: "description".to_string(),
+                body: "Soon, @sched_reader".to_string(),
+                status: "scheduled".to_string(),
+                published_at: Some(publish_at),
+            },
+        )
+        .unwrap();
+        mention::service::sync_article_mentions(&conn, &article).unwrap();
+        let notified = |conn: &diesel::PgConnection| {
+            notifications::table
+                .filter(notifications::user_id.eq(reader.id))
+                .filter(notifications::kind.eq("mention"))
+                .count()
+                .get_result::<i64>(conn)
+                .unwrap()
+        };
+        assert_eq!(0, notified(&conn));
+
+        diesel::sql_query(
+            "UPDATE articles SET published_at = published_at - INTERVAL '2 hours' WHERE slug = 'scheduled-mention'",
+        )
+        .execute(&conn)
+        .unwrap();
+        drop(conn);
+        publish_due(&pool, Duration::from_secs(5)).unwrap();
+        assert_eq!(1, notified(&pool.get().unwrap()));
+    }
+}
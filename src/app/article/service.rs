@@ -1,67 +1,109 @@
-use crate::app::article::model::{Article, CreateArticle, UpdateArticle};
+use crate::app::article::model::{
+    Article, ArticleStatus, CreateArticle, FetchBySlugAndAuthorId, Publication, SearchHighlight,
+    SlugHistory, UniqueSlug, UpdateArticle,
+};
+use crate::app::article::response::SingleArticleResponse;
 use crate::app::favorite;
 use crate::app::favorite::model::FavoriteInfo;
 use crate::app::follow::model::Follow;
+use crate::app::mention;
 use crate::app::profile;
 use crate::app::profile::model::Profile;
 use crate::app::profile::service::FetchProfileById;
+use crate::app::revision::model::Revision;
+use crate::app::stream::{self, hub::StreamEvent};
 use crate::app::tag::model::{NewTag, Tag};
 use crate::app::user::model::User;
+use crate::app::webhook::{self, model::WebhookEvent};
 use crate::error::AppError;
 use crate::schema::articles::dsl::*;
-use crate::schema::{articles, favorites, tags, users};
+use crate::schema::{articles, tag_follows, tags, users};
+use crate::utils::cursor::Cursor;
+use crate::utils::db;
+use crate::utils::markdown;
+use chrono::{NaiveDateTime, Utc};
+use diesel::dsl::sql;
+use diesel::expression::bound::Bound;
+use diesel::expression::operators::{Asc, Desc};
+use diesel::expression::{SqlLiteral, UncheckedBind};
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bool, Double, Float, Text, Timestamp, Uuid as SqlUuid};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
 pub struct CreateArticleService {
-    pub slug: String,
     pub title: String,
     pub description: String,
     pub body: String,
     pub tag_list: Option<Vec<String>>,
+    pub status: Option<String>,
+    pub publish_at: Option<NaiveDateTime>,
     pub me: User,
 }
 pub fn create(
     conn: &PgConnection,
     params: &CreateArticleService,
 ) -> Result<(Article, Profile, FavoriteInfo, Vec<Tag>), AppError> {
-    let article = Article::create(
-        conn,
-        &CreateArticle {
-            author_id: params.me.id,
-            slug: params.slug.clone(),
-            title: params.title.clone(),
-            description: params.description.clone(),
-            body: params.body.clone(),
-        },
-    )?;
-    let tag_list = create_tag_list(conn, &params.tag_list, &article)?;
-    let profile = profile::service::fetch_profile_by_id(
-        conn,
-        &FetchProfileById {
-            user: params.me.to_owned(),
-            id: article.author_id,
-        },
-    )?;
-
-    let favorite_info = {
-        let is_favorited = {
-            let favorited_article_ids =
-                favorite::service::fetch_favorited_article_ids_by_user_id(conn, params.me.id)?;
-            favorited_article_ids
-                .into_iter()
-                .any(|_id| _id == article.id)
-        };
-        let favorites_count =
-            favorite::service::fetch_favorites_count_by_article_id(conn, article.id)?;
-        FavoriteInfo {
-            is_favorited,
-            favorites_count,
+    db::unit_of_work(conn, || {
+        let publication = Publication::resolve(
+            params.status.as_deref(),
+            params.publish_at,
+            None,
+            Utc::now().naive_utc(),
+        )?;
+        let article = Article::write_with_unique_slug(conn, &params.title, None, |unique| {
+            Article::create(
+                conn,
+                &CreateArticle {
+                    author_id: params.me.id,
+                    slug: unique.slug,
+                    slug_base: unique.base,
+                    title: params.title.clone(),
+                    description: params.description.clone(),
+                    body: params.body.clone(),
+                    status: publication.status.as_str().to_string(),
+                    published_at: publication.published_at,
+                },
+            )
+        })?;
+        mention::service::sync_article_mentions(conn, &article)?;
+        if article.is_published() {
+            publish_feed_item(conn, &article)?;
         }
-    };
+        #[cfg(test)]
+        db::fail_point("article::create::tags")?;
+        let tag_list = create_tag_list(conn, &params.tag_list, &article)?;
+        Revision::record(conn, &article, params.me.id)?;
+        let profile = profile::service::fetch_profile_by_id(
+            conn,
+            &FetchProfileById {
+                me: Some(params.me.to_owned()),
+                id: article.author_id,
+            },
+        )?;
+
+        let favorite_info =
+            favorite::service::fetch_favorite_info(conn, Some(params.me.id), article.id)?;
+
+        let item = (article, profile, favorite_info, tag_list);
+        webhook::service::enqueue(conn, params.me.id, WebhookEvent::ArticleCreated, || {
+            Ok(SingleArticleResponse::from(item.clone()))
+        })?;
+        Ok(item)
+    })
+}
 
-    Ok((article, profile, favorite_info, tag_list))
+// Lets the author's followers know a new article reached their feed.
+pub fn publish_feed_item(conn: &PgConnection, article: &Article) -> Result<(), AppError> {
+    stream::hub::publish(
+        conn,
+        StreamEvent::Feed {
+            article_slug: article.slug.to_owned(),
+            author_id: article.author_id,
+        },
+    )
 }
 
 fn create_tag_list(
@@ -72,7 +114,8 @@ fn create_tag_list(
     let list = tag_list
         .as_ref()
         .map(|tag_list| {
-            let records = tag_list
+            let records = Tag::normalize_names(tag_list);
+            let records = records
                 .iter()
                 .map(|tag| NewTag {
                     name: tag,
@@ -86,11 +129,143 @@ fn create_tag_list(
 }
 
 pub struct FetchArticlesList {
+    pub me: Option<User>,
+    pub q: Option<String>,
     pub tag: Option<String>,
     pub author: Option<String>,
     pub favorited: Option<String>,
+    pub sort: Option<ArticleSort>,
     pub offset: i64,
     pub limit: i64,
+    pub cursor: Option<PageCursor>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArticleSort {
+    Recent,
+    MostFavorited,
+    MostCommented,
+    Trending,
+}
+
+impl ArticleSort {
+    pub fn parse(text: &str) -> Result<Self, AppError> {
+        match text {
+            "recent" => Ok(ArticleSort::Recent),
+            "most_favorited" => Ok(ArticleSort::MostFavorited),
+            "most_commented" => Ok(ArticleSort::MostCommented),
+            "trending" => Ok(ArticleSort::Trending),
+            _ => Err(AppError::UnprocessableEntity(json!({
+                "error": "sort must be one of recent, most_favorited, most_commented or trending"
+            }))),
+        }
+    }
+}
+
+// When an article went public. Drafts published long after they were started sort by the
+// day they were published, not by the day they were created.
+const PUBLISHED_AT_SQL: &str = "COALESCE(articles.published_at, articles.created_at)";
+
+const FAVORITES_COUNT_SQL: &str =
+    "(SELECT COUNT(*) FROM favorites WHERE favorites.article_id = articles.id)";
+const COMMENTS_COUNT_SQL: &str =
+    "(SELECT COUNT(*) FROM comments WHERE comments.article_id = articles.id AND comments.deleted_at IS NULL AND comments.hidden_at IS NULL)";
+
+// Engagement (a comment weighs twice a favorite) divided by the article's age in hours,
+// raised to a gravity of 1.5, so older articles need ever more activity to stay on top.
+fn trending_score_sql() -> String {
+    format!(
+        "({} + 2 * {} + 1) / POWER(EXTRACT(EPOCH FROM (NOW() AT TIME ZONE 'UTC') - {}) / 3600 + 2, 1.5)",
+        FAVORITES_COUNT_SQL, COMMENTS_COUNT_SQL, PUBLISHED_AT_SQL
+    )
+}
+
+fn newest_first() -> (SqlLiteral<Timestamp>, Desc<articles::id>) {
+    (
+        sql::<Timestamp>(&format!("{} DESC", PUBLISHED_AT_SQL)),
+        articles::id.desc(),
+    )
+}
+
+fn oldest_first() -> (SqlLiteral<Timestamp>, Asc<articles::id>) {
+    (
+        sql::<Timestamp>(&format!("{} ASC", PUBLISHED_AT_SQL)),
+        articles::id.asc(),
+    )
+}
+
+pub enum PageCursor {
+    After(Cursor),
+    Before(Cursor),
+}
+
+type KeysetPredicate = SqlLiteral<
+    Bool,
+    UncheckedBind<
+        SqlLiteral<Bool, UncheckedBind<SqlLiteral<Bool>, Bound<Timestamp, NaiveDateTime>>>,
+        Bound<SqlUuid, Uuid>,
+    >,
+>;
+
+impl PageCursor {
+    // Row comparison on (publication time, id), which matches `newest_first`. `after` walks
+    // towards older articles, `before` towards newer.
+    fn predicate(&self) -> KeysetPredicate {
+        let (op, cursor) = match self {
+            PageCursor::After(cursor) => ("<", cursor),
+            PageCursor::Before(cursor) => (">", cursor),
+        };
+        sql::<Bool>(&format!("({}, articles.id) {} (", PUBLISHED_AT_SQL, op))
+            .bind::<Timestamp, _>(cursor.published_at)
+            .sql(", ")
+            .bind::<SqlUuid, _>(cursor.id)
+            .sql(")")
+    }
+
+    fn is_before(&self) -> bool {
+        matches!(self, PageCursor::Before(_))
+    }
+}
+
+// Cursors for the pages around the one returned: `next` continues towards older articles
+// (pass it as `after`), `prev` goes back towards newer ones (pass it as `before`).
+#[derive(Debug, Default, PartialEq)]
+pub struct PageCursors {
+    pub next: Option<Cursor>,
+    pub prev: Option<Cursor>,
+}
+
+// Rows are loaded with `limit + 1` so we know whether another page exists without
+// counting. `before` pages are loaded oldest first and flipped back here.
+fn into_page(
+    mut rows: Vec<(Article, User)>,
+    limit: i64,
+    offset: i64,
+    cursor: &Option<PageCursor>,
+) -> (Vec<(Article, User)>, PageCursors) {
+    let has_more = rows.len() as i64 > limit;
+    rows.truncate(limit.max(0) as usize);
+    let is_before = cursor.as_ref().is_some_and(PageCursor::is_before);
+    if is_before {
+        rows.reverse();
+    }
+    // NOTE: the row a cursor points at lies on the far side of it, so a page reached
+    // through `after` always has newer rows before it, and one reached through `before`
+    // always has older rows after it.
+    let (has_older, has_newer) = match cursor {
+        Some(PageCursor::After(_)) => (has_more, true),
+        Some(PageCursor::Before(_)) => (true, has_more),
+        None => (has_more, offset > 0),
+    };
+    let to_cursor = |(article, _): &(Article, User)| Cursor {
+        published_at: article.published_at.unwrap_or(article.created_at),
+        id: article.id,
+    };
+    let cursors = PageCursors {
+        next: rows.last().map(to_cursor).filter(|_| has_older),
+        prev: rows.first().map(to_cursor).filter(|_| has_newer),
+    };
+    (rows, cursors)
 }
 
 type ArticlesCount = i64;
@@ -99,38 +274,53 @@ type ArticlesList = Vec<(ArticlesListInner, Vec<Tag>)>;
 pub fn fetch_articles_list(
     conn: &PgConnection,
     params: FetchArticlesList,
-) -> Result<(ArticlesList, ArticlesCount), AppError> {
+) -> Result<(ArticlesList, ArticlesCount, PageCursors), AppError> {
     use diesel::prelude::*;
+    // NOTE: a search without an explicit sort is ranked by relevance.
+    let ranked_by_relevance = params.q.is_some() && params.sort.is_none();
+    let sort = params.sort.unwrap_or(ArticleSort::Recent);
+    if params.cursor.is_some() && (ranked_by_relevance || sort != ArticleSort::Recent) {
+        return Err(AppError::UnprocessableEntity(json!({
+            "error": "cursors are only supported when sorting by recent"
+        })));
+    }
     let query = || {
-        let mut query = articles::table.inner_join(users::table).into_boxed();
+        let mut query = articles::table
+            .inner_join(users::table)
+            .filter(articles::status.eq(ArticleStatus::Published.as_str()))
+            .into_boxed();
 
         if let Some(tag_name) = &params.tag {
             let tagged_article_ids = tags::table
-                .filter(tags::name.eq(tag_name))
-                .select(tags::article_id)
-                .load::<Uuid>(conn)
-                .expect("could not fetch tagged article ids."); // TODO: use ? or error handling
+                .filter(tags::name.eq(tag_name.to_owned()))
+                .select(tags::article_id);
             query = query.filter(articles::id.eq_any(tagged_article_ids));
         }
 
         if let Some(author_name) = &params.author {
-            let article_ids_by_author = users::table
-                .inner_join(articles::table)
-                .filter(users::username.eq(author_name))
-                .select(articles::id)
-                .load::<Uuid>(conn)
-                .expect("could not fetch authors id."); // TODO: use ? or error handling
-            query = query.filter(articles::id.eq_any(article_ids_by_author));
+            query = query.filter(users::username.eq(author_name.to_owned()));
         }
 
         if let Some(favorited_username) = &params.favorited {
-            let favorited_article_ids = favorites::table
-                .inner_join(users::table)
-                .filter(users::username.eq(favorited_username))
-                .select(favorites::article_id)
-                .load::<Uuid>(conn)
-                .expect("could not fetch favorited articles id."); // TODO: use ? or error handling
-            query = query.filter(articles::id.eq_any(favorited_article_ids));
+            // NOTE: raw SQL because diesel 1.x cannot alias `users`, which the outer query
+            // already joins for the author.
+            query = query.filter(
+                sql::<Bool>(
+                    "articles.id IN (SELECT favorites.article_id FROM favorites \
+                     INNER JOIN users AS favoriters ON favoriters.id = favorites.user_id \
+                     WHERE favoriters.username = ",
+                )
+                .bind::<Text, _>(favorited_username.to_owned())
+                .sql(")"),
+            );
+        }
+
+        if let Some(q) = &params.q {
+            query = query.filter(
+                sql::<Bool>("articles.search_vector @@ websearch_to_tsquery('english', ")
+                    .bind::<Text, _>(q.to_owned())
+                    .sql(")"),
+            );
         }
 
         query
@@ -140,72 +330,114 @@ pub fn fetch_articles_list(
         .select(diesel::dsl::count(articles::id))
         .first::<i64>(conn)?;
 
-    let articles_list = {
-        let article_and_user_list =
-            query()
-                .offset(params.offset)
-                .limit(params.limit)
-                .load::<(Article, User)>(conn)?;
-
-        let tags_list = {
-            let articles_list = article_and_user_list
-                .clone()
-                .into_iter()
-                .map(|(article, _)| article)
-                .collect::<Vec<_>>();
-            let tags_list = Tag::belonging_to(&articles_list)
-                .order(tags::name.asc())
-                .load::<Tag>(conn)?;
-            let tags_list: Vec<Vec<Tag>> = tags_list.grouped_by(&articles_list);
-            tags_list
+    let (articles_list, cursors) = {
+        let mut query = query();
+        // NOTE: every ordering ends with (publication time, id) so pages never overlap on ties.
+        match &params.cursor {
+            Some(cursor) => {
+                query = query.filter(cursor.predicate());
+                query = if cursor.is_before() {
+                    query.order(oldest_first())
+                } else {
+                    query.order(newest_first())
+                };
+            }
+            None => {
+                query = match (&params.q, sort) {
+                    (Some(q), _) if ranked_by_relevance => query.order((
+                        sql::<Float>(
+                            "ts_rank(articles.search_vector, websearch_to_tsquery('english', ",
+                        )
+                        .bind::<Text, _>(q.to_owned())
+                        .sql(")) DESC"),
+                        newest_first(),
+                    )),
+                    (_, ArticleSort::Recent) => query.order(newest_first()),
+                    (_, ArticleSort::MostFavorited) => query.order((
+                        sql::<BigInt>(&format!("{} DESC", FAVORITES_COUNT_SQL)),
+                        newest_first(),
+                    )),
+                    (_, ArticleSort::MostCommented) => query.order((
+                        sql::<BigInt>(&format!("{} DESC", COMMENTS_COUNT_SQL)),
+                        newest_first(),
+                    )),
+                    (_, ArticleSort::Trending) => query.order((
+                        sql::<Double>(&format!("{} DESC", trending_score_sql())),
+                        newest_first(),
+                    )),
+                };
+                query = query.offset(params.offset);
+            }
+        }
+        let (article_and_user_list, cursors) = into_page(
+            query
+                .limit(params.limit.saturating_add(1))
+                .load::<(Article, User)>(conn)?,
+            params.limit,
+            params.offset,
+            &params.cursor,
+        );
+        let cursors = if !ranked_by_relevance && sort == ArticleSort::Recent {
+            cursors
+        } else {
+            PageCursors::default()
         };
 
-        let article_ids_list = article_and_user_list
-            .clone()
-            .into_iter()
-            .map(|(article, _)| article.id);
+        let articles_list = fetch_list_items(conn, params.me.as_ref(), article_and_user_list)?;
+        (articles_list, cursors)
+    };
 
-        let favorites_count_list: Result<Vec<_>, _> = article_ids_list
-            .map(|article_id| {
-                favorite::service::fetch_favorites_count_by_article_id(conn, article_id)
-            })
-            .collect();
-        let favorites_count_list = favorites_count_list?;
+    Ok((articles_list, articles_count, cursors))
+}
 
-        let article_and_profile_list = {
-            article_and_user_list
-                .into_iter()
-                .map(|(article, user)| {
-                    let profile = Profile {
-                        username: user.username,
-                        bio: user.bio,
-                        image: user.image,
-                        following: false, // NOTE: because not authz
-                    };
-                    let is_favorited = false; // NOTE: because not authz
-                    (article, profile, is_favorited)
-                })
-                .zip(favorites_count_list)
-                .map(|((article, profile, is_favorited), favorites_count)| {
-                    (
-                        article,
-                        profile,
-                        FavoriteInfo {
-                            is_favorited,
-                            favorites_count,
-                        },
-                    )
-                })
-                .collect::<Vec<_>>()
-        };
+// Builds list items for a page of articles with a fixed number of queries, however long
+// the page is: one for tags, one for favorite counts and, when there is a viewer, one for
+// their favorites and one for their follows.
+fn fetch_list_items(
+    conn: &PgConnection,
+    me: Option<&User>,
+    article_and_user_list: Vec<(Article, User)>,
+) -> Result<ArticlesList, AppError> {
+    let (articles_list, authors): (Vec<_>, Vec<_>) = article_and_user_list.into_iter().unzip();
 
-        article_and_profile_list
-            .into_iter()
-            .zip(tags_list)
-            .collect::<Vec<_>>()
+    let tags_list = Tag::belonging_to(&articles_list)
+        .order(tags::name.asc())
+        .load::<Tag>(conn)?
+        .grouped_by(&articles_list);
+
+    let article_ids = articles_list
+        .iter()
+        .map(|article| article.id)
+        .collect::<Vec<_>>();
+    let mut favorite_infos =
+        favorite::service::fetch_favorite_infos(conn, me.map(|me| me.id), &article_ids)?;
+
+    let followee_ids = match me {
+        Some(me) => {
+            let author_ids = authors.iter().map(|author| author.id).collect::<Vec<_>>();
+            Follow::fetch_followee_ids(conn, me.id, &author_ids)?
+        }
+        None => HashSet::new(),
     };
 
-    Ok((articles_list, articles_count))
+    let list = articles_list
+        .into_iter()
+        .zip(authors)
+        .zip(tags_list)
+        .map(|((article, author), tags_list)| {
+            let profile = Profile {
+                following: followee_ids.contains(&author.id),
+                username: author.username,
+                bio: author.bio,
+                image: author.image,
+            };
+            let favorite_info = favorite_infos
+                .remove(&article.id)
+                .ok_or(AppError::InternalServerError)?;
+            Ok(((article, profile, favorite_info), tags_list))
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+    Ok(list)
 }
 
 pub struct FetchArticle {
@@ -226,26 +458,13 @@ pub fn fetch_article(
     let profile = profile::service::fetch_profile_by_id(
         conn,
         &FetchProfileById {
-            user: me.to_owned(),
+            me: Some(me.to_owned()),
             id: author.id,
         },
     )?;
 
-    let favorite_info = {
-        let is_favorited = {
-            let favorited_article_ids =
-                favorite::service::fetch_favorited_article_ids_by_user_id(conn, params.me.id)?;
-            favorited_article_ids
-                .into_iter()
-                .any(|favarited_article_id| favarited_article_id == article.id)
-        };
-        let favorites_count =
-            favorite::service::fetch_favorites_count_by_article_id(conn, article.id)?;
-        FavoriteInfo {
-            is_favorited,
-            favorites_count,
-        }
-    };
+    let favorite_info =
+        favorite::service::fetch_favorite_info(conn, Some(params.me.id), article.id)?;
 
     let tags_list = Tag::belonging_to(&article).load::<Tag>(conn)?;
 
@@ -254,39 +473,40 @@ pub fn fetch_article(
 
 pub struct FetchArticleBySlug {
     pub article_title_slug: String,
+    pub me: Option<User>,
 }
 pub fn fetch_article_by_slug(
     conn: &PgConnection,
     params: &FetchArticleBySlug,
 ) -> Result<(Article, Profile, FavoriteInfo, Vec<Tag>), AppError> {
     use diesel::prelude::*;
-    let FetchArticleBySlug { article_title_slug } = params;
+    let FetchArticleBySlug {
+        article_title_slug,
+        me,
+    } = params;
     let (article, author) = articles
         .inner_join(users::table)
         .filter(articles::slug.eq(article_title_slug))
         .get_result::<(Article, User)>(conn)?;
 
+    // NOTE: drafts and scheduled articles are only visible to their author.
+    let is_author = me.as_ref().map(|me| me.id == author.id).unwrap_or(false);
+    if !article.is_published() && !is_author {
+        return Err(AppError::NotFound(
+            json!({ "error": "requested record was not found" }),
+        ));
+    }
+
     let profile = profile::service::fetch_profile_by_id(
         conn,
         &FetchProfileById {
-            user: author.to_owned(),
+            me: me.to_owned(),
             id: author.id,
         },
     )?;
 
-    let favorite_info = {
-        let favorited_article_ids =
-            favorite::service::fetch_favorited_article_ids_by_user_id(conn, author.id)?;
-        let is_favorited = favorited_article_ids
-            .into_iter()
-            .any(|_id| _id == article.id);
-        let favorites_count =
-            favorite::service::fetch_favorites_count_by_article_id(conn, article.id)?;
-        FavoriteInfo {
-            is_favorited,
-            favorites_count,
-        }
-    };
+    let favorite_info =
+        favorite::service::fetch_favorite_info(conn, me.as_ref().map(|me| me.id), article.id)?;
 
     let tags_list = Tag::belonging_to(&article).load::<Tag>(conn)?;
 
@@ -299,165 +519,231 @@ pub struct FetchFollowedArticlesSerivce {
     pub me: User,
     pub offset: i64,
     pub limit: i64,
+    pub cursor: Option<PageCursor>,
 }
 pub fn fetch_following_articles(
     conn: &PgConnection,
     params: &FetchFollowedArticlesSerivce,
-) -> Result<(ArticlesList, ArticlesCount), AppError> {
+) -> Result<(ArticlesList, ArticlesCount, PageCursors), AppError> {
+    // NOTE: the feed is the union of articles by followed authors and articles carrying
+    // a followed tag. Both are expressed as subselects in a single WHERE, so an article
+    // matching both only shows up once.
     let query = {
         let following_user_ids = follows
             .filter(follows::follower_id.eq(params.me.id))
-            .select(follows::followee_id)
-            .get_results::<Uuid>(conn)?;
-
-        articles.filter(articles::author_id.eq_any(following_user_ids))
-    };
-
-    let articles_list = {
-        let article_and_user_list = query
-            .to_owned()
-            .inner_join(users::table)
-            .limit(params.limit)
-            .offset(params.offset)
-            .order(articles::created_at.desc())
-            .get_results::<(Article, User)>(conn)?;
-
-        let tags_list = {
-            let articles_list = article_and_user_list
-                .clone() // TODO: avoid clone
-                .into_iter()
-                .map(|(article, _)| article)
-                .collect::<Vec<_>>();
-
-            let tags_list = Tag::belonging_to(&articles_list).load::<Tag>(conn)?;
-            let tags_list: Vec<Vec<Tag>> = tags_list.grouped_by(&articles_list);
-            tags_list
-        };
-
-        let article_and_profile_list = {
-            let user_ids_list = article_and_user_list
-                .clone() // TODO: avoid clone
-                .into_iter()
-                .map(|(_, user)| user.id)
-                .collect::<Vec<_>>();
+            .select(follows::followee_id);
 
-            let follows_list = follows::table
-                .filter(follows::follower_id.eq(params.me.id))
-                .filter(follows::followee_id.eq_any(user_ids_list))
-                .get_results::<Follow>(conn)?;
+        let followed_tag_names = tag_follows::table
+            .filter(tag_follows::user_id.eq(params.me.id))
+            .select(tag_follows::tag_name);
+        let followed_tag_article_ids = tags::table
+            .filter(tags::name.eq_any(followed_tag_names))
+            .select(tags::article_id);
 
-            let article_ids_list = article_and_user_list
-                .clone()
-                .into_iter()
-                .map(|(article, _)| article.id);
+        articles
+            .filter(articles::status.eq(ArticleStatus::Published.as_str()))
+            .filter(
+                articles::author_id
+                    .eq_any(following_user_ids)
+                    .or(articles::id.eq_any(followed_tag_article_ids)),
+            )
+    };
 
-            let favorites_count_list: Result<Vec<_>, _> = article_ids_list
-                .map(|article_id| {
-                    favorite::service::fetch_favorites_count_by_article_id(conn, article_id)
-                })
-                .collect();
-            let favorites_count_list = favorites_count_list?;
+    let (articles_list, cursors) = {
+        let mut page_query = query.to_owned().inner_join(users::table).into_boxed();
+        match &params.cursor {
+            Some(cursor) => {
+                page_query = page_query.filter(cursor.predicate());
+                page_query = if cursor.is_before() {
+                    page_query.order(oldest_first())
+                } else {
+                    page_query.order(newest_first())
+                };
+            }
+            None => page_query = page_query.order(newest_first()).offset(params.offset),
+        }
+        let (article_and_user_list, cursors) = into_page(
+            page_query
+                .limit(params.limit.saturating_add(1))
+                .get_results::<(Article, User)>(conn)?,
+            params.limit,
+            params.offset,
+            &params.cursor,
+        );
 
-            let favorited_article_ids =
-                favorite::service::fetch_favorited_article_ids_by_user_id(conn, params.me.id)?;
+        let articles_list = fetch_list_items(conn, Some(&params.me), article_and_user_list)?;
+        (articles_list, cursors)
+    };
 
-            let is_favorited_by_me = |article: &Article| {
-                favorited_article_ids
-                    .iter()
-                    .copied()
-                    .any(|_id| _id == article.id)
-            };
+    let articles_count = query
+        .select(diesel::dsl::count(articles::id))
+        .first::<i64>(conn)?;
 
-            let follows_list = follows_list.into_iter();
-            article_and_user_list
-                .into_iter()
-                .map(|(article, user)| {
-                    let following = follows_list.clone().any(|item| item.followee_id == user.id);
-                    let profile = Profile {
-                        username: user.username,
-                        bio: user.bio,
-                        image: user.image,
-                        following: following.to_owned(),
-                    };
-                    let is_favorited = is_favorited_by_me(&article);
-                    (article, profile, is_favorited)
-                })
-                .zip(favorites_count_list)
-                .map(|((article, profile, is_favorited), favorites_count)| {
-                    (
-                        article,
-                        profile,
-                        FavoriteInfo {
-                            is_favorited,
-                            favorites_count,
-                        },
-                    )
-                })
-                .collect::<Vec<_>>()
-        };
+    Ok((articles_list, articles_count, cursors))
+}
 
-        article_and_profile_list
-            .into_iter()
-            .zip(tags_list)
-            .collect::<Vec<_>>()
+pub struct FetchDigestArticles {
+    pub me: User,
+    pub published_since: NaiveDateTime,
+    pub limit: i64,
+}
+// Articles by the authors `me` follows, published at or after `published_since`, newest
+// first. Unlike the feed it leaves out followed tags and `me`'s own articles, since a digest
+// is about the people they follow.
+pub fn fetch_digest_articles(
+    conn: &PgConnection,
+    params: &FetchDigestArticles,
+) -> Result<(ArticlesList, ArticlesCount), AppError> {
+    let query = {
+        let following_user_ids = follows
+            .filter(follows::follower_id.eq(params.me.id))
+            .select(follows::followee_id);
+        articles
+            .filter(articles::status.eq(ArticleStatus::Published.as_str()))
+            .filter(articles::author_id.eq_any(following_user_ids))
+            .filter(articles::author_id.ne(params.me.id))
+            .filter(articles::published_at.ge(params.published_since))
     };
-
+    let article_and_user_list = query
+        .to_owned()
+        .inner_join(users::table)
+        .order(newest_first())
+        .limit(params.limit)
+        .get_results::<(Article, User)>(conn)?;
+    let articles_list = fetch_list_items(conn, Some(&params.me), article_and_user_list)?;
     let articles_count = query
         .select(diesel::dsl::count(articles::id))
         .first::<i64>(conn)?;
-
     Ok((articles_list, articles_count))
 }
 
 pub struct UpdateArticleService {
     pub me: User,
     pub article_title_slug: String,
-    pub slug: Option<String>,
     pub title: Option<String>,
     pub description: Option<String>,
     pub body: Option<String>,
+    pub status: Option<String>,
+    pub publish_at: Option<NaiveDateTime>,
+    pub tag_list: Option<Vec<String>>,
+    pub add_tags: Option<Vec<String>>,
+    pub remove_tags: Option<Vec<String>>,
 }
 pub fn update_article(
     conn: &PgConnection,
     params: &UpdateArticleService,
 ) -> Result<(Article, Profile, FavoriteInfo, Vec<Tag>), AppError> {
-    let article = Article::update(
+    if params.tag_list.is_some() && (params.add_tags.is_some() || params.remove_tags.is_some()) {
+        return Err(AppError::UnprocessableEntity(json!({
+            "error": "tagList cannot be combined with addTags or removeTags"
+        })));
+    }
+    if let (Some(add_tags), Some(remove_tags)) = (&params.add_tags, &params.remove_tags) {
+        let remove_tags = Tag::normalize_names(remove_tags);
+        let overlap = Tag::normalize_names(add_tags)
+            .into_iter()
+            .filter(|tag| remove_tags.contains(tag))
+            .collect::<Vec<_>>();
+        if !overlap.is_empty() {
+            return Err(AppError::UnprocessableEntity(json!({
+                "error": format!("tags cannot be both added and removed: {}", overlap.join(", "))
+            })));
+        }
+    }
+    db::unit_of_work(conn, || update_article_with_tags(conn, params))
+}
+
+fn update_article_with_tags(
+    conn: &PgConnection,
+    params: &UpdateArticleService,
+) -> Result<(Article, Profile, FavoriteInfo, Vec<Tag>), AppError> {
+    let current = Article::fetch_by_slug_and_author_id(
         conn,
-        &params.article_title_slug,
-        &params.me.id,
-        &UpdateArticle {
-            slug: params.slug.to_owned(),
-            title: params.title.to_owned(),
-            description: params.description.to_owned(),
-            body: params.body.to_owned(),
+        &FetchBySlugAndAuthorId {
+            slug: params.article_title_slug.to_owned(),
+            author_id: params.me.id,
         },
     )?;
+    let publication = Publication::resolve(
+        params.status.as_deref(),
+        params.publish_at,
+        Some(&current),
+        Utc::now().naive_utc(),
+    )?;
 
+    let update = |unique: Option<UniqueSlug>| {
+        let (new_slug, new_slug_base) = match unique {
+            Some(unique) => (Some(unique.slug), Some(unique.base)),
+            None => (None, None),
+        };
+        Article::update(
+            conn,
+            &params.article_title_slug,
+            &params.me.id,
+            &UpdateArticle {
+                slug: new_slug,
+                slug_base: new_slug_base,
+                title: params.title.to_owned(),
+                description: params.description.to_owned(),
+                body: params.body.to_owned(),
+                status: Some(publication.status.as_str().to_string()),
+                published_at: Some(publication.published_at),
+            },
+        )
+    };
+    let article = match &params.title {
+        Some(_title) => Article::write_with_unique_slug(conn, _title, Some(&current), |unique| {
+            update(Some(unique))
+        })?,
+        None => update(None)?,
+    };
+
+    if article.slug != current.slug {
+        SlugHistory::record(conn, article.id, &current.slug, &article.slug)?;
+    }
+    let is_content_changed = article.title != current.title
+        || article.description != current.description
+        || article.body != current.body;
+    if is_content_changed {
+        Revision::record(conn, &article, params.me.id)?;
+    }
+    let is_newly_published = article.is_published() && !current.is_published();
+    if article.body != current.body || is_newly_published {
+        mention::service::sync_article_mentions(conn, &article)?;
+    }
+    if is_newly_published {
+        publish_feed_item(conn, &article)?;
+    }
+
+    #[cfg(test)]
+    db::fail_point("article::update::tags")?;
+    if let Some(tag_list) = &params.tag_list {
+        Tag::replace_list(conn, article.id, &Tag::normalize_names(tag_list))?;
+    }
+    if let Some(remove_tags) = &params.remove_tags {
+        Tag::delete_list(conn, article.id, &Tag::normalize_names(remove_tags))?;
+    }
+    if let Some(add_tags) = &params.add_tags {
+        Tag::add_list(conn, article.id, &Tag::normalize_names(add_tags))?;
+    }
     let tag_list = Tag::fetch_list_by_article_id(conn, article.id)?;
 
     let profile = profile::service::fetch_profile_by_id(
         conn,
         &FetchProfileById {
-            user: params.me.to_owned(),
+            me: Some(params.me.to_owned()),
             id: article.author_id,
         },
     )?;
 
-    let favorite_info = {
-        let favorited_article_ids =
-            favorite::service::fetch_favorited_article_ids_by_user_id(conn, params.me.id)?;
-        let is_favorited = favorited_article_ids
-            .into_iter()
-            .any(|_id| _id == article.id);
-        let favorites_count =
-            favorite::service::fetch_favorites_count_by_article_id(conn, article.id)?;
-        FavoriteInfo {
-            is_favorited,
-            favorites_count,
-        }
-    };
+    let favorite_info =
+        favorite::service::fetch_favorite_info(conn, Some(params.me.id), article.id)?;
 
-    Ok((article, profile, favorite_info, tag_list))
+    let item = (article, profile, favorite_info, tag_list);
+    webhook::service::enqueue(conn, params.me.id, WebhookEvent::ArticleUpdated, || {
+        Ok(SingleArticleResponse::from(item.clone()))
+    })?;
+    Ok(item)
 }
 
 pub struct DeleteArticle {
@@ -465,16 +751,470 @@ pub struct DeleteArticle {
     pub author_id: Uuid,
 }
 pub fn delete_article(conn: &PgConnection, params: &DeleteArticle) -> Result<(), AppError> {
-    use crate::schema::articles::dsl::*;
-    use diesel::prelude::*;
+    db::unit_of_work(conn, || {
+        use crate::schema::articles::dsl::*;
+        use diesel::prelude::*;
 
-    let _ = diesel::delete(
-        articles
+        let current = articles
             .filter(slug.eq(&params.slug))
-            .filter(author_id.eq(params.author_id)),
-    )
-    .execute(conn)?;
-    // NOTE: references tag rows are deleted automatically by DELETE CASCADE
+            .filter(author_id.eq(params.author_id))
+            .first::<Article>(conn)
+            .optional()?;
+        if let Some(current) = current {
+            // The payload is the article as it was, so it has to be built before it's gone.
+            webhook::service::enqueue(
+                conn,
+                params.author_id,
+                WebhookEvent::ArticleDeleted,
+                || {
+                    let me = User::find_by_id(conn, params.author_id)?;
+                    let item = fetch_article(
+                        conn,
+                        &FetchArticle {
+                            article_id: current.id,
+                            me,
+                        },
+                    )?;
+                    Ok(SingleArticleResponse::from(item))
+                },
+            )?;
+        }
+        let _ = diesel::delete(
+            articles
+                .filter(slug.eq(&params.slug))
+                .filter(author_id.eq(params.author_id)),
+        )
+        .execute(conn)?;
+        // NOTE: references tag rows are deleted automatically by DELETE CASCADE
 
-    Ok(())
+        Ok(())
+    })
+}
+
+pub struct FetchDrafts {
+    pub me: User,
+    pub offset: i64,
+    pub limit: i64,
+}
+pub fn fetch_drafts(
+    conn: &PgConnection,
+    params: &FetchDrafts,
+) -> Result<(ArticlesList, ArticlesCount), AppError> {
+    let query = || {
+        articles
+            .filter(articles::author_id.eq(params.me.id))
+            .filter(articles::status.ne(ArticleStatus::Published.as_str()))
+    };
+
+    let articles_count = query()
+        .select(diesel::dsl::count(articles::id))
+        .first::<i64>(conn)?;
+
+    let articles_list = query()
+        .order(articles::updated_at.desc())
+        .offset(params.offset)
+        .limit(params.limit)
+        .load::<Article>(conn)?;
+
+    let tags_list = Tag::belonging_to(&articles_list)
+        .order(tags::name.asc())
+        .load::<Tag>(conn)?
+        .grouped_by(&articles_list);
+
+    let profile = Profile {
+        username: params.me.username.to_owned(),
+        bio: params.me.bio.to_owned(),
+        image: params.me.image.to_owned(),
+        following: false,
+    };
+
+    let articles_list = articles_list
+        .into_iter()
+        .map(|article| {
+            // NOTE: unpublished articles cannot be favorited by anyone.
+            let favorite_info = FavoriteInfo {
+                is_favorited: false,
+                favorites_count: 0,
+            };
+            (article, profile.to_owned(), favorite_info)
+        })
+        .zip(tags_list)
+        .collect::<Vec<_>>();
+
+    Ok((articles_list, articles_count))
+}
+
+// Rendered Markdown for each article, served from the per-revision cache without writing.
+pub fn fetch_rendered_bodies(
+    conn: &PgConnection,
+    articles_list: &[&Article],
+) -> Result<HashMap<Uuid, markdown::Rendered>, AppError> {
+    let article_ids = articles_list
+        .iter()
+        .map(|article| article.id)
+        .collect::<Vec<_>>();
+    let mut rendered_bodies = HashMap::new();
+    for revision in Revision::fetch_latest_by_article_ids(conn, &article_ids)? {
+        rendered_bodies.insert(revision.article_id, revision.rendered());
+    }
+    // NOTE: articles without any revision yet are rendered on the fly.
+    for article in articles_list {
+        rendered_bodies
+            .entry(article.id)
+            .or_insert_with(|| markdown::render(&article.body));
+    }
+    Ok(rendered_bodies)
+}
+
+pub fn fetch_search_highlights(
+    conn: &PgConnection,
+    q: &str,
+    articles_list: &[&Article],
+) -> Result<HashMap<Uuid, SearchHighlight>, AppError> {
+    let article_ids = articles_list
+        .iter()
+        .map(|article| article.id)
+        .collect::<Vec<_>>();
+    let highlights = SearchHighlight::fetch_list(conn, q, &article_ids)?
+        .into_iter()
+        .map(|highlight| (highlight.id, highlight))
+        .collect();
+    Ok(highlights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::db::testing;
+
+    fn create_params(me: &User, _title: &str) -> CreateArticleService {
+        CreateArticleService {
+            title: _title.to_string(),
+            description: "description".to_string(),
+            body: "body".to_string(),
+            tag_list: Some(vec!["rust".to_string()]),
+            status: None,
+            publish_at: None,
+            me: me.to_owned(),
+        }
+    }
+
+    #[test]
+    fn create_rolls_back_article_when_tags_fail() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let me = testing::insert_user(&conn, "uow_article_author");
+        {
+            let _armed = testing::arm_fail_point("article::create::tags");
+            assert!(create(&conn, &create_params(&me, "Rolled back")).is_err());
+        }
+        let count = articles
+            .filter(articles::author_id.eq(me.id))
+            .count()
+            .get_result::<i64>(&conn)
+            .unwrap();
+        assert_eq!(0, count);
+    }
+
+    #[test]
+    fn update_rolls_back_article_when_tags_fail() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let me = testing::insert_user(&conn, "uow_article_editor");
+        let (article, _, _, _) = create(&conn, &create_params(&me, "Original title")).unwrap();
+        {
+            let _armed = testing::arm_fail_point("article::update::tags");
+            let result = update_article(
+                &conn,
+                &UpdateArticleService {
+                    me: me.to_owned(),
+                    article_title_slug: article.slug.to_owned(),
+                    title: Some("Changed title".to_string()),
+                    description: None,
+                    body: None,
+                    status: None,
+                    publish_at: None,
+                    tag_list: Some(vec!["web".to_string()]),
+                    add_tags: None,
+                    remove_tags: None,
+                },
+            );
+            assert!(result.is_err());
+        }
+        let current = Article::fetch_by_slug(&conn, &article.slug).unwrap();
+        assert_eq!("Original title", current.title);
+        let tag_names = Tag::fetch_list_by_article_id(&conn, article.id)
+            .unwrap()
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["rust".to_string()], tag_names);
+    }
+
+    #[test]
+    fn search_combines_with_filters_and_skips_drafts() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let alice = testing::insert_user(&conn, "search_alice");
+        let bob = testing::insert_user(&conn, "search_bob");
+        let write = |author: &User, _title: &str, tag: &str, _status: &str| {
+            create(
+                &conn,
+                &CreateArticleService {
+                    tag_list: Some(vec![tag.to_string()]),
+                    status: Some(_status.to_string()),
+                    ..create_params(author, _title)
+                },
+            )
+            .unwrap();
+        };
+        write(&alice, "Zanzibar ownership", "rust", "published");
+        write(&alice, "Zanzibar lifetimes", "web", "published");
+        write(&bob, "Zanzibar macros", "rust", "published");
+        write(&alice, "Zanzibar secrets", "rust", "draft");
+        write(&alice, "Gardening", "rust", "published");
+
+        let search = |tag: Option<&str>, author: Option<&str>| {
+            let (list, count, _) = fetch_articles_list(
+                &conn,
+                FetchArticlesList {
+                    me: None,
+                    q: Some("zanzibar".to_string()),
+                    tag: tag.map(str::to_string),
+                    author: author.map(str::to_string),
+                    favorited: None,
+                    sort: None,
+                    offset: 0,
+                    limit: 20,
+                    cursor: None,
+                },
+            )
+            .unwrap();
+            let mut titles = list
+                .into_iter()
+                .map(|((article, _, _), _)| article.title)
+                .collect::<Vec<_>>();
+            titles.sort();
+            assert_eq!(titles.len() as i64, count);
+            titles
+        };
+        assert_eq!(
+            vec![
+                "Zanzibar lifetimes",
+                "Zanzibar macros",
+                "Zanzibar ownership"
+            ],
+            search(None, None)
+        );
+        assert_eq!(
+            vec!["Zanzibar macros", "Zanzibar ownership"],
+            search(Some("rust"), None)
+        );
+        assert_eq!(
+            vec!["Zanzibar lifetimes", "Zanzibar ownership"],
+            search(None, Some("search_alice"))
+        );
+        assert_eq!(
+            vec!["Zanzibar ownership"],
+            search(Some("rust"), Some("search_alice"))
+        );
+    }
+
+    #[test]
+    fn sorts_order_by_publication_and_engagement() {
+        use crate::app::favorite::model::{Favorite, FavorteAction};
+        use crate::schema::comments;
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let author = testing::insert_user(&conn, "sort_author");
+        let readers = [
+            testing::insert_user(&conn, "sort_reader_1"),
+            testing::insert_user(&conn, "sort_reader_2"),
+        ];
+        let now = Utc::now().naive_utc();
+        // (title, created, published, favorites, comments, hidden comments): "Late draft" was
+        // started first but only published an hour ago.
+        let fixtures = [
+            ("Late draft", 240, 1, 0, 1, 0),
+            ("Two days", 48, 48, 1, 2, 0),
+            ("Three days", 72, 72, 2, 0, 3),
+        ];
+        for (_title, created_hours, published_hours, favorite_count, comment_count, hidden_count) in
+            fixtures
+        {
+            let (article, _, _, _) = create(&conn, &create_params(&author, _title)).unwrap();
+            diesel::update(articles.find(article.id))
+                .set((
+                    articles::created_at.eq(now - chrono::Duration::hours(created_hours)),
+                    articles::published_at.eq(now - chrono::Duration::hours(published_hours)),
+                ))
+                .execute(&conn)
+                .unwrap();
+            for reader in &readers[..favorite_count] {
+                let record = FavorteAction {
+                    user_id: reader.id,
+                    article_id: article.id,
+                };
+                Favorite::favorite(&conn, &record).unwrap();
+            }
+            for index in 0..comment_count + hidden_count {
+                let hidden = (index >= comment_count).then_some(now);
+                diesel::insert_into(comments::table)
+                    .values((
+                        comments::article_id.eq(article.id),
+                        comments::author_id.eq(readers[0].id),
+                        comments::body.eq("comment"),
+                        comments::hidden_at.eq(hidden),
+                    ))
+                    .execute(&conn)
+                    .unwrap();
+            }
+        }
+
+        let list = |sort: ArticleSort, limit: i64, cursor: Option<PageCursor>| {
+            let (list, _, cursors) = fetch_articles_list(
+                &conn,
+                FetchArticlesList {
+                    me: None,
+                    q: None,
+                    tag: None,
+                    author: Some("sort_author".to_string()),
+                    favorited: None,
+                    sort: Some(sort),
+                    offset: 0,
+                    limit,
+                    cursor,
+                },
+            )
+            .unwrap();
+            let titles = list
+                .into_iter()
+                .map(|((article, _, _), _)| article.title)
+                .collect::<Vec<_>>();
+            (titles, cursors.next)
+        };
+        let titles = |sort: ArticleSort| list(sort, 10, None).0;
+        assert_eq!(
+            vec!["Late draft", "Two days", "Three days"],
+            titles(ArticleSort::Recent)
+        );
+        assert_eq!(
+            vec!["Three days", "Two days", "Late draft"],
+            titles(ArticleSort::MostFavorited)
+        );
+        assert_eq!(
+            vec!["Two days", "Late draft", "Three days"],
+            titles(ArticleSort::MostCommented)
+        );
+        assert_eq!(
+            vec!["Late draft", "Two days", "Three days"],
+            titles(ArticleSort::Trending)
+        );
+
+        // Cursors follow the same publication order one article at a time.
+        let mut walked = vec![];
+        let mut cursor = None;
+        loop {
+            let (page, next) = list(ArticleSort::Recent, 1, cursor.map(PageCursor::After));
+            walked.extend(page);
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(titles(ArticleSort::Recent), walked);
+    }
+
+    #[test]
+    fn feed_runs_a_fixed_number_of_queries_per_page() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let me = testing::insert_user(&conn, "n1_reader");
+        let author = testing::insert_user(&conn, "n1_author");
+        Follow::create_follow(
+            &conn,
+            &crate::app::follow::model::NewFollow {
+                follower_id: me.id,
+                followee_id: author.id,
+            },
+        )
+        .unwrap();
+        for index in 0..5 {
+            create(&conn, &create_params(&author, &format!("Page {}", index))).unwrap();
+        }
+
+        testing::predictable_plans(&conn);
+        let scans_for_page = |limit: i64| {
+            let tables = ["favorites", "follows", "tags"];
+            let before: i64 = tables.iter().map(|t| testing::table_scans(&conn, t)).sum();
+            let (list, _, _) = fetch_following_articles(
+                &conn,
+                &FetchFollowedArticlesSerivce {
+                    me: me.to_owned(),
+                    offset: 0,
+                    limit,
+                    cursor: None,
+                },
+            )
+            .unwrap();
+            assert_eq!(limit as usize, list.len());
+            let after: i64 = tables.iter().map(|t| testing::table_scans(&conn, t)).sum();
+            after - before
+        };
+        assert_eq!(scans_for_page(1), scans_for_page(5));
+    }
+
+    #[test]
+    fn article_flags_follow_the_viewer() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let author = testing::insert_user(&conn, "viewer_author");
+        let reader = testing::insert_user(&conn, "viewer_reader");
+        let (article, _, _, _) = create(&conn, &create_params(&author, "Viewed")).unwrap();
+        Follow::create_follow(
+            &conn,
+            &crate::app::follow::model::NewFollow {
+                follower_id: reader.id,
+                followee_id: author.id,
+            },
+        )
+        .unwrap();
+        crate::app::favorite::model::Favorite::favorite(
+            &conn,
+            &crate::app::favorite::model::FavorteAction {
+                user_id: reader.id,
+                article_id: article.id,
+            },
+        )
+        .unwrap();
+
+        let show = |me: Option<User>| {
+            let (_, profile, favorite_info, _) = fetch_article_by_slug(
+                &conn,
+                &FetchArticleBySlug {
+                    article_title_slug: article.slug.to_owned(),
+                    me,
+                },
+            )
+            .unwrap();
+            assert_eq!("viewer_author", profile.username);
+            assert_eq!(1, favorite_info.favorites_count);
+            (profile.following, favorite_info.is_favorited)
+        };
+        assert_eq!((true, true), show(Some(reader.to_owned())));
+        assert_eq!((false, false), show(Some(author.to_owned())));
+        assert_eq!((false, false), show(None));
+    }
 }
@@ -3,20 +3,84 @@ use super::{
     response::{MultipleCommentsResponse, SingleCommentResponse}, 
     service
 };
+use super::model::Comment;
+use crate::app::article::model::CommentSettings;
+use crate::app::article::response::SingleArticleResponse;
+use crate::app::mention;
+use crate::app::profile::model::Profile;
 use crate::error::AppError;
 use crate::middleware::auth;
 use crate::middleware::state::AppState;
 use crate::utils::uuid;
 use actix_web::{web, HttpRequest, HttpResponse};
+use diesel::pg::PgConnection;
+use serde::Deserialize;
 
 type ArticleIdSlug = String;
 type CommentIdSlug = String;
 
-pub async fn index(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+fn single_comment_response(
+    conn: &PgConnection,
+    comment: Comment,
+    profile: Profile,
+) -> Result<SingleCommentResponse, AppError> {
+    let mut mentions = mention::service::fetch_comment_mention_ranges(conn, &[&comment])?;
+    let mut res = SingleCommentResponse::from((comment, profile));
+    res.comment.mentions = mentions.remove(&res.comment.id).unwrap_or_default();
+    Ok(res)
+}
+
+#[derive(Deserialize)]
+pub struct CommentsListQueryParameter {
+    order: Option<String>,
+    view: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+pub async fn index(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<ArticleIdSlug>,
+    params: web::Query<CommentsListQueryParameter>,
+) -> Result<HttpResponse, AppError> {
     let auth_user = auth::access_auth_user(&req).ok();
     let conn = state.get_conn()?;
-    let list = service::fetch_comments_list(&conn, &auth_user)?;
-    let res = MultipleCommentsResponse::from(list);
+    let article_title_slug = path.into_inner();
+    let order = params
+        .order
+        .as_deref()
+        .map(service::CommentOrder::parse)
+        .transpose()?
+        .unwrap_or(service::CommentOrder::Oldest);
+    let view = params
+        .view
+        .as_deref()
+        .map(service::CommentView::parse)
+        .transpose()?
+        .unwrap_or(service::CommentView::Flat);
+    let list = service::fetch_comments_list(
+        &conn,
+        &service::FetchCommentsList {
+            me: auth_user,
+            article_title_slug,
+            order,
+            view,
+            offset: params.offset.unwrap_or(0).max(0),
+            // NOTE: unlike other lists, comments are only paged when a limit is asked for.
+            limit: params
+                .limit
+                .map(|limit| state.config.pagination.limit(Some(limit))),
+        },
+    )?;
+    let mentions = mention::service::fetch_comment_mention_ranges(
+        &conn,
+        &list.0.iter().map(|(comment, _)| comment).collect::<Vec<_>>(),
+    )?;
+    let res = match view {
+        service::CommentView::Flat => MultipleCommentsResponse::flat(list, mentions),
+        service::CommentView::Tree => MultipleCommentsResponse::tree(list, mentions),
+    };
     Ok(HttpResponse::Ok().json(res))
 }
 
@@ -35,9 +99,35 @@ pub async fn create(
             body: form.comment.body.to_owned(),
             article_title_slug,
             author: auth_user,
+            parent_id: form.comment.parent_id,
+            max_depth: state.config.comments.max_depth,
         },
     )?;
-    let res = SingleCommentResponse::from((comment, profile));
+    let res = single_comment_response(&conn, comment, profile)?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn update(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(ArticleIdSlug, CommentIdSlug)>,
+    form: web::Json<request::UpdateCommentRequest>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let (article_title_slug, comment_id) = path.into_inner();
+    let comment_id = uuid::parse(&comment_id)?;
+    let (comment, profile) = service::update_comment(
+        &conn,
+        &service::UpdateCommentService {
+            me: auth_user,
+            article_title_slug,
+            comment_id,
+            body: form.comment.body.to_owned(),
+            edit_window: state.config.comments.edit_window(),
+        },
+    )?;
+    let res = single_comment_response(&conn, comment, profile)?;
     Ok(HttpResponse::Ok().json(res))
 }
 
@@ -50,7 +140,7 @@ pub async fn delete(
     let conn = state.get_conn()?;
     let (article_title_slug, comment_id) = path.into_inner();
     let comment_id = uuid::parse(&comment_id)?;
-    let _ = service::delete_comment(
+    service::delete_comment(
         &conn,
         &service::DeleteCommentService {
             article_title_slug,
@@ -60,3 +150,66 @@ pub async fn delete(
     )?;
     Ok(HttpResponse::Ok().json("Ok"))
 }
+
+async fn set_hidden(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(ArticleIdSlug, CommentIdSlug)>,
+    hidden: bool,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let (article_title_slug, comment_id) = path.into_inner();
+    let comment_id = uuid::parse(&comment_id)?;
+    let (comment, profile) = service::set_comment_hidden(
+        &conn,
+        &service::HideCommentService {
+            me: auth_user,
+            article_title_slug,
+            comment_id,
+            hidden,
+        },
+    )?;
+    let res = single_comment_response(&conn, comment, profile)?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn hide(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(ArticleIdSlug, CommentIdSlug)>,
+) -> Result<HttpResponse, AppError> {
+    set_hidden(state, req, path, true).await
+}
+
+pub async fn unhide(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(ArticleIdSlug, CommentIdSlug)>,
+) -> Result<HttpResponse, AppError> {
+    set_hidden(state, req, path, false).await
+}
+
+pub async fn update_settings(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<ArticleIdSlug>,
+    form: web::Json<request::UpdateCommentSettingsRequest>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let article_title_slug = path.into_inner();
+    let article = service::update_comment_settings(
+        &conn,
+        &service::UpdateCommentSettingsService {
+            me: auth_user,
+            article_title_slug,
+            settings: CommentSettings {
+                comments_locked: form.settings.locked,
+                comments_followers_only: form.settings.followers_only,
+            },
+        },
+    )?;
+    let res = SingleArticleResponse::from(article);
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -1,9 +1,10 @@
 use crate::app::article::model::Article;
 use crate::app::user::model::User;
 use crate::error::AppError;
+use crate::schema::comment_revisions;
 use crate::schema::comments;
 use crate::schema::comments::dsl::*;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use diesel::pg::PgConnection;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -19,6 +20,24 @@ pub struct Comment {
     pub body: String,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub parent_id: Option<Uuid>,
+    // Top-level comment of the thread, so a whole thread loads in one query.
+    pub root_id: Option<Uuid>,
+    pub depth: i32,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub edited_at: Option<NaiveDateTime>,
+    // Hidden by the article's author. Only they still see the comment's content.
+    pub hidden_at: Option<NaiveDateTime>,
+}
+
+impl Comment {
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden_at.is_some()
+    }
 }
 
 #[derive(Insertable, Clone)]
@@ -27,6 +46,9 @@ pub struct CreateComment {
     pub body: String,
     pub author_id: Uuid,
     pub article_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub root_id: Option<Uuid>,
+    pub depth: i32,
 }
 impl Comment {
     pub fn create(conn: &PgConnection, record: &CreateComment) -> Result<Self, AppError> {
@@ -37,6 +59,76 @@ impl Comment {
         Ok(new_comment)
     }
 
+    pub fn fetch_by_id_and_article_id(
+        conn: &PgConnection,
+        _id: Uuid,
+        _article_id: Uuid,
+    ) -> Result<Self, AppError> {
+        use diesel::prelude::*;
+        let item = comments
+            .filter(comments::id.eq(_id))
+            .filter(comments::article_id.eq(_article_id))
+            .first::<Self>(conn)?;
+        Ok(item)
+    }
+
+    // Keeps the current body as a revision, then replaces it.
+    pub fn update_body(
+        conn: &PgConnection,
+        comment: &Comment,
+        _body: &str,
+    ) -> Result<Self, AppError> {
+        use diesel::prelude::*;
+        let _ = diesel::insert_into(comment_revisions::table)
+            .values(&NewCommentRevision {
+                comment_id: comment.id,
+                body: &comment.body,
+            })
+            .execute(conn)?;
+        let item = diesel::update(comments.find(comment.id))
+            .set((
+                body.eq(_body),
+                edited_at.eq(diesel::dsl::now.nullable()),
+                updated_at.eq(diesel::dsl::now),
+            ))
+            .get_result::<Self>(conn)?;
+        Ok(item)
+    }
+
+    pub fn set_hidden(conn: &PgConnection, _id: Uuid, hidden: bool) -> Result<Self, AppError> {
+        use diesel::prelude::*;
+        let _hidden_at = if hidden {
+            Some(Utc::now().naive_utc())
+        } else {
+            None
+        };
+        let item = diesel::update(comments.find(_id))
+            .set(hidden_at.eq(_hidden_at))
+            .get_result::<Self>(conn)?;
+        Ok(item)
+    }
+
+    pub fn has_replies(conn: &PgConnection, _id: Uuid) -> Result<bool, AppError> {
+        use diesel::dsl::exists;
+        use diesel::prelude::*;
+        let _has_replies =
+            diesel::select(exists(comments.filter(parent_id.eq(_id)))).get_result(conn)?;
+        Ok(_has_replies)
+    }
+
+    // Blanks the comment but keeps the row, so replies still hang off their parent. Earlier
+    // bodies go with it, so nothing of the deleted text is left behind.
+    pub fn soft_delete(conn: &PgConnection, _id: Uuid) -> Result<Self, AppError> {
+        use diesel::prelude::*;
+        let _ = diesel::delete(comment_revisions::table)
+            .filter(comment_revisions::comment_id.eq(_id))
+            .execute(conn)?;
+        let item = diesel::update(comments.find(_id))
+            .set((body.eq(""), deleted_at.eq(diesel::dsl::now.nullable())))
+            .get_result::<Self>(conn)?;
+        Ok(item)
+    }
+
     pub fn delete(conn: &PgConnection, params: &DeleteCommentAction) -> Result<(), AppError> {
         use diesel::prelude::*;
         let _ = diesel::delete(comments)
@@ -48,6 +140,23 @@ impl Comment {
     }
 }
 
+#[derive(Identifiable, Queryable, Associations, Debug, Serialize, Deserialize, Clone)]
+#[belongs_to(Comment, foreign_key = "comment_id")]
+#[table_name = "comment_revisions"]
+pub struct CommentRevision {
+    pub id: Uuid,
+    pub comment_id: Uuid,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "comment_revisions"]
+struct NewCommentRevision<'a> {
+    comment_id: Uuid,
+    body: &'a str,
+}
+
 pub struct DeleteCommentAction {
     pub comment_id: Uuid,
     pub article_id: Uuid,
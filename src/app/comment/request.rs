@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Serialize, Deserialize)]
 pub struct CreateCommentRequest {
@@ -6,6 +7,30 @@ pub struct CreateCommentRequest {
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InnerComment {
     pub body: String,
+    pub parent_id: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UpdateCommentRequest {
+    pub comment: UpdateCommentInner,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UpdateCommentInner {
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UpdateCommentSettingsRequest {
+    pub settings: UpdateCommentSettingsInner,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCommentSettingsInner {
+    pub locked: Option<bool>,
+    pub followers_only: Option<bool>,
 }
@@ -1,7 +1,10 @@
 use crate::app::comment::model::Comment;
+use crate::app::comment::service::CommentsList;
 use crate::app::profile::model::Profile;
 use crate::utils::date::Iso8601;
+use crate::utils::mention::MentionRange;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::convert::From;
 use uuid::Uuid;
 
@@ -13,48 +16,67 @@ pub struct SingleCommentResponse {
 impl From<(Comment, Profile)> for SingleCommentResponse {
     fn from((comment, profile): (Comment, Profile)) -> Self {
         Self {
-            comment: InnerComment {
-                id: comment.id,
-                body: comment.body,
-                author: InnerAuthor {
-                    username: profile.username,
-                    bio: profile.bio,
-                    image: profile.image,
-                    following: profile.following,
-                },
-                created_at: Iso8601(comment.created_at),
-                updated_at: Iso8601(comment.updated_at),
-            },
+            comment: InnerComment::from((comment, Some(profile))),
         }
     }
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MultipleCommentsResponse {
     pub comments: Vec<InnerComment>,
+    pub comments_count: i64,
 }
 
-impl From<Vec<(Comment, Profile)>> for MultipleCommentsResponse {
-    fn from(list: Vec<(Comment, Profile)>) -> Self {
+impl MultipleCommentsResponse {
+    pub fn flat(
+        (list, comments_count): (CommentsList, i64),
+        mut mentions: HashMap<Uuid, Vec<MentionRange>>,
+    ) -> Self {
+        let comments = list
+            .into_iter()
+            .map(|item| {
+                let mut inner = InnerComment::from(item);
+                inner.mentions = mentions.remove(&inner.id).unwrap_or_default();
+                inner
+            })
+            .collect();
         Self {
-            comments: list
-                .into_iter()
-                .map(|item| {
-                    let (comment, profile) = item;
-                    InnerComment {
-                        id: comment.id,
-                        created_at: Iso8601(comment.created_at),
-                        updated_at: Iso8601(comment.updated_at),
-                        body: comment.body,
-                        author: InnerAuthor {
-                            username: profile.username,
-                            bio: profile.bio,
-                            image: profile.image,
-                            following: profile.following,
-                        },
-                    }
-                })
-                .collect(),
+            comments,
+            comments_count,
+        }
+    }
+
+    // Nests each reply under its parent. Expects parents to come before their replies, and
+    // replies in the order they should be shown.
+    pub fn tree(
+        (list, comments_count): (CommentsList, i64),
+        mut mentions: HashMap<Uuid, Vec<MentionRange>>,
+    ) -> Self {
+        let mut roots = vec![];
+        let mut replies = HashMap::<Uuid, Vec<InnerComment>>::new();
+        for (comment, profile) in list {
+            let parent_id = comment.parent_id;
+            let mut inner = InnerComment::from((comment, profile));
+            inner.mentions = mentions.remove(&inner.id).unwrap_or_default();
+            match parent_id {
+                Some(parent_id) => replies.entry(parent_id).or_default().push(inner),
+                None => roots.push(inner),
+            }
+        }
+        fn attach(comment: &mut InnerComment, replies: &mut HashMap<Uuid, Vec<InnerComment>>) {
+            let mut children = replies.remove(&comment.id).unwrap_or_default();
+            for child in children.iter_mut() {
+                attach(child, replies);
+            }
+            comment.replies = Some(children);
+        }
+        for root in roots.iter_mut() {
+            attach(root, &mut replies);
+        }
+        Self {
+            comments: roots,
+            comments_count,
         }
     }
 }
@@ -66,7 +88,40 @@ pub struct InnerComment {
     pub created_at: Iso8601,
     pub updated_at: Iso8601,
     pub body: String,
-    pub author: InnerAuthor,
+    pub edited: bool,
+    pub edited_at: Option<Iso8601>,
+    // None when the comment is only a placeholder, because it was deleted or hidden.
+    pub author: Option<InnerAuthor>,
+    pub parent_id: Option<Uuid>,
+    pub deleted: bool,
+    pub hidden: bool,
+    pub mentions: Vec<MentionRange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replies: Option<Vec<InnerComment>>,
+}
+
+impl From<(Comment, Option<Profile>)> for InnerComment {
+    fn from((comment, profile): (Comment, Option<Profile>)) -> Self {
+        Self {
+            id: comment.id,
+            created_at: Iso8601(comment.created_at),
+            updated_at: Iso8601(comment.updated_at),
+            edited: comment.edited_at.is_some(),
+            edited_at: comment.edited_at.map(Iso8601),
+            deleted: comment.is_deleted(),
+            hidden: comment.is_hidden(),
+            body: comment.body,
+            author: profile.map(|profile| InnerAuthor {
+                username: profile.username,
+                bio: profile.bio,
+                image: profile.image,
+                following: profile.following,
+            }),
+            parent_id: comment.parent_id,
+            mentions: vec![],
+            replies: None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
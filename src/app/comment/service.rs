@@ -1,77 +1,334 @@
 use super::model::{Comment, CreateComment, DeleteCommentAction};
-use crate::app::article::model::{Article, FetchBySlugAndAuthorId};
+use super::response::SingleCommentResponse;
+use crate::app::article::model::{Article, CommentSettings};
+use crate::app::article::service::{fetch_article, FetchArticle};
+use crate::app::favorite::model::FavoriteInfo;
+use crate::app::follow::model::Follow;
+use crate::app::mention;
+use crate::app::notification::{self, service::NotificationEvent};
 use crate::app::profile::model::Profile;
-use crate::app::profile::service::{
-    conver_user_to_profile, fetch_profile_by_id, ConverUserToProfile, FetchProfileById,
-};
+use crate::app::profile::service::{fetch_profile_by_id, FetchProfileById};
+use crate::app::stream::{self, hub::StreamEvent};
+use crate::app::tag::model::Tag;
 use crate::app::user::model::User;
+use crate::app::webhook::{self, model::WebhookEvent};
 use crate::error::AppError;
+use crate::utils::db;
 // use crate::schema::follows;
+use chrono::{NaiveDateTime, Utc};
 use diesel::pg::PgConnection;
+use serde_json::json;
+use std::collections::HashSet;
+use std::time::Duration;
 use uuid::Uuid;
 
 pub struct CreateCommentService {
     pub body: String,
     pub article_title_slug: String,
     pub author: User,
+    pub parent_id: Option<Uuid>,
+    // `comments.max_depth`.
+    pub max_depth: i32,
 }
 pub fn create(
     conn: &PgConnection,
     params: &CreateCommentService,
 ) -> Result<(Comment, Profile), AppError> {
-    let CreateCommentService {
-        body,
-        article_title_slug,
-        author,
-    } = params;
-    let article = Article::fetch_by_slug_and_author_id(
-        conn,
-        &FetchBySlugAndAuthorId {
-            slug: article_title_slug.to_owned(),
-            author_id: author.id,
-        },
-    )?;
-    let comment = Comment::create(
-        conn,
-        &CreateComment {
-            body: body.to_string(),
-            author_id: author.id,
-            article_id: article.id.to_owned(),
-        },
-    )?;
-    let profile = fetch_profile_by_id(
-        conn,
-        &FetchProfileById {
-            id: author.id,
-            user: author.to_owned(),
-        },
-    )?;
-    Ok((comment, profile))
+    db::unit_of_work(conn, || {
+        let CreateCommentService {
+            body,
+            article_title_slug,
+            author,
+            parent_id,
+            max_depth,
+        } = params;
+        let article = Article::fetch_published_by_slug(conn, article_title_slug)?;
+        check_can_comment(conn, &article, author)?;
+        let (root_id, depth) = match parent_id {
+            Some(parent_id) => {
+                let parent = Comment::fetch_by_id_and_article_id(conn, *parent_id, article.id)?;
+                if parent.is_deleted() {
+                    return Err(AppError::UnprocessableEntity(
+                        json!({ "error": "cannot reply to a deleted comment" }),
+                    ));
+                }
+                if parent.depth >= *max_depth {
+                    return Err(AppError::UnprocessableEntity(json!({
+                        "error": format!("replies cannot nest more than {} levels deep", max_depth)
+                    })));
+                }
+                (Some(parent.root_id.unwrap_or(parent.id)), parent.depth + 1)
+            }
+            None => (None, 0),
+        };
+        let comment = Comment::create(
+            conn,
+            &CreateComment {
+                body: body.to_string(),
+                author_id: author.id,
+                article_id: article.id.to_owned(),
+                parent_id: *parent_id,
+                root_id,
+                depth,
+            },
+        )?;
+        notification::service::notify(
+            conn,
+            NotificationEvent::Commented {
+                comment: &comment,
+                article: &article,
+            },
+        )?;
+        mention::service::sync_comment_mentions(conn, &comment)?;
+        stream::hub::publish(
+            conn,
+            StreamEvent::Comment {
+                article_slug: article.slug.to_owned(),
+                comment_id: comment.id,
+            },
+        )?;
+        let profile = fetch_profile_by_id(
+            conn,
+            &FetchProfileById {
+                me: Some(author.to_owned()),
+                id: author.id,
+            },
+        )?;
+        webhook::service::enqueue(
+            conn,
+            article.author_id,
+            WebhookEvent::CommentCreated,
+            || {
+                Ok(SingleCommentResponse::from((
+                    comment.clone(),
+                    profile.clone(),
+                )))
+            },
+        )?;
+        Ok((comment, profile))
+    })
 }
 
+fn check_can_comment(
+    conn: &PgConnection,
+    article: &Article,
+    author: &User,
+) -> Result<(), AppError> {
+    if article.author_id == author.id {
+        return Ok(());
+    }
+    if article.comments_locked {
+        return Err(AppError::Forbidden(
+            json!({ "error": "comments on this article are locked" }),
+        ));
+    }
+    if article.comments_followers_only
+        && !Follow::fetch_followee_ids(conn, author.id, &[article.author_id])?
+            .contains(&article.author_id)
+    {
+        return Err(AppError::Forbidden(
+            json!({ "error": "only followers of the author can comment on this article" }),
+        ));
+    }
+    Ok(())
+}
+
+pub struct FetchCommentsList {
+    pub me: Option<User>,
+    pub article_title_slug: String,
+    pub order: CommentOrder,
+    pub view: CommentView,
+    pub offset: i64,
+    // None lists every comment, which is what RealWorld clients that do not page expect.
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentView {
+    // Every comment, each pointing at its parent. Pages count every comment.
+    Flat,
+    // Top-level comments with all of their replies. Pages count top-level comments only.
+    Tree,
+}
+
+impl CommentView {
+    pub fn parse(text: &str) -> Result<Self, AppError> {
+        match text {
+            "flat" => Ok(CommentView::Flat),
+            "tree" => Ok(CommentView::Tree),
+            _ => Err(AppError::UnprocessableEntity(json!({
+                "error": "view must be one of flat or tree"
+            }))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentOrder {
+    Oldest,
+    Newest,
+}
+
+impl CommentOrder {
+    pub fn parse(text: &str) -> Result<Self, AppError> {
+        match text {
+            "oldest" => Ok(CommentOrder::Oldest),
+            "newest" => Ok(CommentOrder::Newest),
+            _ => Err(AppError::UnprocessableEntity(json!({
+                "error": "order must be one of oldest or newest"
+            }))),
+        }
+    }
+}
+
+// Each comment with its author, or None for placeholders.
+pub type CommentsList = Vec<(Comment, Option<Profile>)>;
+
+// One page of the article's comments, along with how many comments the article has.
+// Deleted comments that still have replies, and hidden comments for anyone but the
+// article's author, come back as placeholders without a body or an author.
 pub fn fetch_comments_list(
     conn: &PgConnection,
-    me: &Option<User>,
-) -> Result<Vec<(Comment, Profile)>, AppError> {
+    params: &FetchCommentsList,
+) -> Result<(CommentsList, i64), AppError> {
     use crate::schema::comments;
-    use crate::schema::comments::dsl::*;
     use crate::schema::users;
     use diesel::prelude::*;
-    let _comments = comments
+    let article = Article::fetch_published_by_slug(conn, &params.article_title_slug)?;
+
+    let query = comments::table
         .inner_join(users::table)
-        .filter(comments::article_id.eq(article_id))
+        .filter(comments::article_id.eq(article.id))
+        .select((comments::all_columns, users::all_columns))
+        .into_boxed();
+    let query = match params.view {
+        CommentView::Flat => query,
+        CommentView::Tree => query.filter(comments::parent_id.is_null()),
+    };
+    // NOTE: id breaks ties between comments posted in the same instant, so pages never
+    // overlap or skip a comment.
+    let query = match params.order {
+        CommentOrder::Oldest => query.order((comments::created_at.asc(), comments::id.asc())),
+        CommentOrder::Newest => query.order((comments::created_at.desc(), comments::id.desc())),
+    };
+    let query = match params.limit {
+        Some(limit) => query.limit(limit),
+        None => query,
+    };
+    let mut _comments = query
+        .offset(params.offset)
         .get_results::<(Comment, User)>(conn)?;
+    if params.view == CommentView::Tree {
+        let root_ids = _comments
+            .iter()
+            .map(|(_comment, _)| _comment.id)
+            .collect::<Vec<_>>();
+        // Replies always read as a conversation, oldest first.
+        let replies = comments::table
+            .inner_join(users::table)
+            .filter(comments::root_id.eq_any(root_ids))
+            .select((comments::all_columns, users::all_columns))
+            .order((comments::created_at.asc(), comments::id.asc()))
+            .get_results::<(Comment, User)>(conn)?;
+        _comments.extend(replies);
+    }
+    let comments_count = comments::table
+        .filter(comments::article_id.eq(article.id))
+        .filter(comments::deleted_at.is_null())
+        .filter(comments::hidden_at.is_null())
+        .count()
+        .get_result::<i64>(conn)?;
+
+    let followee_ids = match &params.me {
+        Some(me) => {
+            let author_ids = _comments
+                .iter()
+                .map(|(_, _user)| _user.id)
+                .collect::<Vec<_>>();
+            Follow::fetch_followee_ids(conn, me.id, &author_ids)?
+        }
+        None => HashSet::new(),
+    };
 
+    let is_moderator = params
+        .me
+        .as_ref()
+        .is_some_and(|me| me.id == article.author_id);
     let _comments = _comments
-        .iter()
-        .map(|(_comment, _user)| {
-            // TODO: avoid N+1. Write one query to fetch all data somehow.
-            let profile = conver_user_to_profile(conn, &ConverUserToProfile { user: _user, me });
-            (_comment.to_owned(), profile)
+        .into_iter()
+        .map(|(mut _comment, _user)| {
+            if _comment.is_deleted() || (_comment.is_hidden() && !is_moderator) {
+                _comment.body = String::new();
+                return (_comment, None);
+            }
+            let profile = Profile {
+                following: followee_ids.contains(&_user.id),
+                username: _user.username,
+                bio: _user.bio,
+                image: _user.image,
+            };
+            (_comment, Some(profile))
         })
-        .collect::<Vec<(Comment, Profile)>>();
+        .collect::<CommentsList>();
+
+    Ok((_comments, comments_count))
+}
+
+pub struct UpdateCommentService {
+    pub me: User,
+    pub article_title_slug: String,
+    pub comment_id: Uuid,
+    pub body: String,
+    // `comments.edit_window_secs`.
+    pub edit_window: Option<Duration>,
+}
+pub fn update_comment(
+    conn: &PgConnection,
+    params: &UpdateCommentService,
+) -> Result<(Comment, Profile), AppError> {
+    db::unit_of_work(conn, || {
+        let article = Article::fetch_published_by_slug(conn, &params.article_title_slug)?;
+        let comment = Comment::fetch_by_id_and_article_id(conn, params.comment_id, article.id)?;
+        if comment.is_deleted() {
+            return Err(AppError::NotFound(
+                json!({ "error": "requested record was not found" }),
+            ));
+        }
+        if comment.author_id != params.me.id {
+            return Err(AppError::Forbidden(
+                json!({ "error": "only the author can edit a comment" }),
+            ));
+        }
+        if !is_within_edit_window(&comment, Utc::now().naive_utc(), params.edit_window) {
+            return Err(AppError::Forbidden(
+                json!({ "error": "the comment can no longer be edited" }),
+            ));
+        }
+        // NOTE: saving the same body again is not an edit and leaves no revision behind.
+        let comment = if comment.body == params.body {
+            comment
+        } else {
+            let comment = Comment::update_body(conn, &comment, &params.body)?;
+            mention::service::sync_comment_mentions(conn, &comment)?;
+            comment
+        };
+        let profile = fetch_profile_by_id(
+            conn,
+            &FetchProfileById {
+                me: Some(params.me.to_owned()),
+                id: params.me.id,
+            },
+        )?;
+        Ok((comment, profile))
+    })
+}
 
-    Ok(_comments)
+fn is_within_edit_window(comment: &Comment, now: NaiveDateTime, window: Option<Duration>) -> bool {
+    match window.map(chrono::Duration::from_std) {
+        Some(Ok(window)) => now - comment.created_at <= window,
+        // Too large for chrono to represent, so effectively unlimited.
+        Some(Err(_)) | None => true,
+    }
 }
 
 pub struct DeleteCommentService {
@@ -80,20 +337,200 @@ pub struct DeleteCommentService {
     pub comment_id: Uuid,
 }
 pub fn delete_comment(conn: &PgConnection, params: &DeleteCommentService) -> Result<(), AppError> {
-    let article = Article::fetch_by_slug_and_author_id(
-        conn,
-        &FetchBySlugAndAuthorId {
-            slug: params.article_title_slug.to_owned(),
-            author_id: params.author_id,
-        },
-    )?;
-    let _ = Comment::delete(
-        conn,
-        &DeleteCommentAction {
-            comment_id: params.comment_id,
-            article_id: article.id,
-            author_id: params.author_id,
-        },
-    )?;
-    Ok(())
+    db::unit_of_work(conn, || {
+        let article = Article::fetch_published_by_slug(conn, &params.article_title_slug)?;
+        let comment = Comment::fetch_by_id_and_article_id(conn, params.comment_id, article.id)?;
+        if comment.is_deleted() {
+            return Err(AppError::NotFound(
+                json!({ "error": "requested record was not found" }),
+            ));
+        }
+        if comment.author_id != params.author_id && article.author_id != params.author_id {
+            return Err(AppError::Forbidden(json!({
+                "error": "only the comment's author or the article's author can delete a comment"
+            })));
+        }
+        if Comment::has_replies(conn, comment.id)? {
+            let comment = Comment::soft_delete(conn, comment.id)?;
+            return mention::service::sync_comment_mentions(conn, &comment);
+        }
+        Comment::delete(
+            conn,
+            &DeleteCommentAction {
+                comment_id: params.comment_id,
+                article_id: article.id,
+                author_id: comment.author_id,
+            },
+        )?;
+        Ok(())
+    })
+}
+
+pub struct HideCommentService {
+    pub me: User,
+    pub article_title_slug: String,
+    pub comment_id: Uuid,
+    pub hidden: bool,
+}
+// Only the article's author moderates its comments.
+pub fn set_comment_hidden(
+    conn: &PgConnection,
+    params: &HideCommentService,
+) -> Result<(Comment, Profile), AppError> {
+    db::unit_of_work(conn, || {
+        let article = Article::fetch_published_by_slug(conn, &params.article_title_slug)?;
+        if article.author_id != params.me.id {
+            return Err(AppError::Forbidden(
+                json!({ "error": "only the article's author can hide comments" }),
+            ));
+        }
+        let comment = Comment::fetch_by_id_and_article_id(conn, params.comment_id, article.id)?;
+        if comment.is_deleted() {
+            return Err(AppError::NotFound(
+                json!({ "error": "requested record was not found" }),
+            ));
+        }
+        let comment = Comment::set_hidden(conn, comment.id, params.hidden)?;
+        let profile = fetch_profile_by_id(
+            conn,
+            &FetchProfileById {
+                me: Some(params.me.to_owned()),
+                id: comment.author_id,
+            },
+        )?;
+        Ok((comment, profile))
+    })
+}
+
+pub struct UpdateCommentSettingsService {
+    pub me: User,
+    pub article_title_slug: String,
+    pub settings: CommentSettings,
+}
+pub fn update_comment_settings(
+    conn: &PgConnection,
+    params: &UpdateCommentSettingsService,
+) -> Result<(Article, Profile, FavoriteInfo, Vec<Tag>), AppError> {
+    db::unit_of_work(conn, || {
+        let article = Article::fetch_by_slug(conn, &params.article_title_slug)?;
+        if article.author_id != params.me.id {
+            return Err(AppError::Forbidden(
+                json!({ "error": "only the article's author can change comment settings" }),
+            ));
+        }
+        let article = Article::update_comment_settings(conn, article.id, &params.settings)?;
+        fetch_article(
+            conn,
+            &FetchArticle {
+                article_id: article.id,
+                me: params.me.to_owned(),
+            },
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::article::model::CreateArticle;
+    use crate::app::follow::model::NewFollow;
+    use crate::utils::db::testing;
+
+    #[test]
+    fn comments_list_runs_a_fixed_number_of_queries() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let me = testing::insert_user(&conn, "n1_comment_reader");
+        let article = Article::create(
+            &conn,
+            &CreateArticle {
+                author_id: me.id,
+                slug: "n1-comments".to_string(),
+                slug_base: "n1-comments".to_string(),
+                title: "Comments".to_string(),
+                description: "description".to_string(),
+                body: "body".to_string(),
+                status: "published".to_string(),
+                published_at: None,
+            },
+        )
+        .unwrap();
+        let add_comment = |username: &str| {
+            let commenter = testing::insert_user(&conn, username);
+            Follow::create_follow(
+                &conn,
+                &NewFollow {
+                    follower_id: me.id,
+                    followee_id: commenter.id,
+                },
+            )
+            .unwrap();
+            Comment::create(
+                &conn,
+                &CreateComment {
+                    body: "comment".to_string(),
+                    author_id: commenter.id,
+                    article_id: article.id,
+                    parent_id: None,
+                    root_id: None,
+                    depth: 0,
+                },
+            )
+            .unwrap();
+        };
+        let follows_scans = || {
+            let before = testing::table_scans(&conn, "follows");
+            let (list, _) = fetch_comments_list(
+                &conn,
+                &FetchCommentsList {
+                    me: Some(me.to_owned()),
+                    article_title_slug: article.slug.to_owned(),
+                    order: CommentOrder::Oldest,
+                    view: CommentView::Flat,
+                    offset: 0,
+                    limit: Some(20),
+                },
+            )
+            .unwrap();
+            assert!(list
+                .iter()
+                .all(|(_, profile)| profile.as_ref().is_some_and(|profile| profile.following)));
+            testing::table_scans(&conn, "follows") - before
+        };
+
+        testing::predictable_plans(&conn);
+        add_comment("n1_commenter_0");
+        let scans_for_one = follows_scans();
+        for index in 1..5 {
+            add_comment(&format!("n1_commenter_{}", index));
+        }
+        assert_eq!(scans_for_one, follows_scans());
+    }
+
+    #[test]
+    fn edit_window_is_measured_from_posting() {
+        let created_at =
+            NaiveDateTime::parse_from_str("2024-01-01 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let comment = Comment {
+            id: Uuid::nil(),
+            article_id: Uuid::nil(),
+            author_id: Uuid::nil(),
+            body: "body".to_string(),
+            created_at,
+            updated_at: created_at,
+            parent_id: None,
+            root_id: None,
+            depth: 0,
+            deleted_at: None,
+            edited_at: None,
+            hidden_at: None,
+        };
+        let window = Some(Duration::from_secs(60 * 60));
+        let later = |minutes| created_at + chrono::Duration::minutes(minutes);
+        assert!(is_within_edit_window(&comment, later(60), window));
+        assert!(!is_within_edit_window(&comment, later(61), window));
+        assert!(is_within_edit_window(&comment, later(60 * 24 * 365), None));
+    }
 }
@@ -0,0 +1,33 @@
+use super::{request, response::DigestResponse, service};
+use crate::error::AppError;
+use crate::middleware::auth;
+use crate::middleware::state::AppState;
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+
+pub async fn show(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let subscription = service::fetch_digest(&conn, &auth_user)?;
+    let res = DigestResponse::from(subscription);
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn update(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    form: web::Json<request::UpdateDigestRequest>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let subscription = service::update_digest(
+        &conn,
+        &service::UpdateDigestService {
+            me: auth_user,
+            frequency: form.digest.frequency.to_owned(),
+            now: Utc::now().naive_utc(),
+        },
+    )?;
+    let res = DigestResponse::from(subscription);
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -0,0 +1,6 @@
+pub mod api;
+pub mod model;
+pub mod request;
+pub mod response;
+pub mod scheduler;
+pub mod service;
@@ -0,0 +1,200 @@
+use crate::app::article::model::Article;
+use crate::app::user::model::User;
+use crate::error::AppError;
+use crate::schema::{digest_articles, digest_subscriptions};
+use chrono::{Duration, NaiveDateTime};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::{Timestamp, Uuid as SqlUuid};
+use serde_json::json;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestFrequency {
+    Daily,
+    Weekly,
+}
+
+impl DigestFrequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestFrequency::Daily => "daily",
+            DigestFrequency::Weekly => "weekly",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, AppError> {
+        match text {
+            "daily" => Ok(DigestFrequency::Daily),
+            "weekly" => Ok(DigestFrequency::Weekly),
+            _ => Err(AppError::UnprocessableEntity(json!({
+                "error": "frequency must be one of daily or weekly"
+            }))),
+        }
+    }
+
+    // How long after the previous digest the next one is due.
+    pub fn period(&self) -> Duration {
+        match self {
+            DigestFrequency::Daily => Duration::days(1),
+            DigestFrequency::Weekly => Duration::weeks(1),
+        }
+    }
+}
+
+// A subscription is due once its period has passed since the last digest, or since it was
+// created if none went out yet. Keep in sync with `DigestFrequency::period`. Disabled users
+// get no digests until they are enabled again.
+const IS_DUE: &str = "COALESCE(last_sent_at, created_at) \
+    <= $1 - CASE frequency WHEN 'weekly' THEN INTERVAL '7 days' ELSE INTERVAL '1 day' END \
+    AND user_id IN (SELECT id FROM users WHERE disabled_at IS NULL)";
+
+#[derive(Identifiable, Queryable, QueryableByName, Associations, Debug, Clone)]
+#[belongs_to(User, foreign_key = "user_id")]
+#[primary_key(user_id)]
+#[table_name = "digest_subscriptions"]
+pub struct DigestSubscription {
+    pub user_id: Uuid,
+    pub frequency: String,
+    pub last_sent_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(QueryableByName)]
+struct DueUserId {
+    #[sql_type = "SqlUuid"]
+    user_id: Uuid,
+}
+
+impl DigestSubscription {
+    // Subscribes the user, or changes the frequency of their subscription. Changing it
+    // keeps `last_sent_at`, so no articles are sent twice and none are skipped.
+    pub fn upsert(
+        conn: &PgConnection,
+        _user_id: Uuid,
+        _frequency: DigestFrequency,
+        now: NaiveDateTime,
+    ) -> Result<Self, AppError> {
+        let subscription = diesel::insert_into(digest_subscriptions::table)
+            .values((
+                digest_subscriptions::user_id.eq(_user_id),
+                digest_subscriptions::frequency.eq(_frequency.as_str()),
+            ))
+            .on_conflict(digest_subscriptions::user_id)
+            .do_update()
+            .set((
+                digest_subscriptions::frequency.eq(_frequency.as_str()),
+                digest_subscriptions::updated_at.eq(now),
+            ))
+            .get_result::<Self>(conn)?;
+        Ok(subscription)
+    }
+
+    pub fn find(conn: &PgConnection, _user_id: Uuid) -> Result<Option<Self>, AppError> {
+        let subscription = digest_subscriptions::table
+            .find(_user_id)
+            .first::<Self>(conn)
+            .optional()?;
+        Ok(subscription)
+    }
+
+    pub fn delete(conn: &PgConnection, _user_id: Uuid) -> Result<(), AppError> {
+        let _ = diesel::delete(digest_subscriptions::table.find(_user_id)).execute(conn)?;
+        Ok(())
+    }
+
+    pub fn fetch_due_user_ids(
+        conn: &PgConnection,
+        now: NaiveDateTime,
+    ) -> Result<Vec<Uuid>, AppError> {
+        let list = diesel::sql_query(format!(
+            "SELECT user_id FROM digest_subscriptions WHERE {} ORDER BY user_id",
+            IS_DUE
+        ))
+        .bind::<Timestamp, _>(now)
+        .load::<DueUserId>(conn)?;
+        Ok(list.into_iter().map(|due| due.user_id).collect())
+    }
+
+    // Locks the user's subscription for the rest of the transaction if it is still due.
+    // Returns None when it is not due (anymore) or another instance is sending it.
+    pub fn lock_if_due(
+        conn: &PgConnection,
+        _user_id: Uuid,
+        now: NaiveDateTime,
+    ) -> Result<Option<Self>, AppError> {
+        let subscription = diesel::sql_query(format!(
+            "SELECT * FROM digest_subscriptions WHERE user_id = $2 AND {} FOR UPDATE SKIP LOCKED",
+            IS_DUE
+        ))
+        .bind::<Timestamp, _>(now)
+        .bind::<SqlUuid, _>(_user_id)
+        .load::<Self>(conn)?
+        .pop();
+        Ok(subscription)
+    }
+
+    pub fn mark_sent(
+        conn: &PgConnection,
+        _user_id: Uuid,
+        now: NaiveDateTime,
+    ) -> Result<(), AppError> {
+        let _ = diesel::update(digest_subscriptions::table.find(_user_id))
+            .set(digest_subscriptions::last_sent_at.eq(now))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn frequency(&self) -> Result<DigestFrequency, AppError> {
+        DigestFrequency::parse(&self.frequency)
+    }
+}
+
+#[derive(Identifiable, Queryable, Associations, Debug, Clone)]
+#[belongs_to(User, foreign_key = "user_id")]
+#[belongs_to(Article, foreign_key = "article_id")]
+#[primary_key(user_id, article_id)]
+#[table_name = "digest_articles"]
+pub struct DigestArticle {
+    pub user_id: Uuid,
+    pub article_id: Uuid,
+    pub sent_at: NaiveDateTime,
+}
+
+impl DigestArticle {
+    pub fn record_list(
+        conn: &PgConnection,
+        _user_id: Uuid,
+        article_ids: &[Uuid],
+    ) -> Result<(), AppError> {
+        let records = article_ids
+            .iter()
+            .map(|_article_id| {
+                (
+                    digest_articles::user_id.eq(_user_id),
+                    digest_articles::article_id.eq(*_article_id),
+                )
+            })
+            .collect::<Vec<_>>();
+        let _ = diesel::insert_into(digest_articles::table)
+            .values(&records)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(())
+    }
+
+    // Which of `article_ids` already went out in one of the user's digests.
+    pub fn fetch_sent_ids(
+        conn: &PgConnection,
+        _user_id: Uuid,
+        article_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, AppError> {
+        let list = digest_articles::table
+            .filter(digest_articles::user_id.eq(_user_id))
+            .filter(digest_articles::article_id.eq_any(article_ids))
+            .select(digest_articles::article_id)
+            .load::<Uuid>(conn)?;
+        Ok(list)
+    }
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct UpdateDigestRequest {
+    pub digest: InnerDigest,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct InnerDigest {
+    // "daily" or "weekly"; null unsubscribes.
+    pub frequency: Option<String>,
+}
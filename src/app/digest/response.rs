@@ -0,0 +1,32 @@
+use crate::app::digest::model::DigestSubscription;
+use crate::utils::date::Iso8601;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct DigestResponse {
+    pub digest: DigestContent,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestContent {
+    // "daily", "weekly", or null when not subscribed.
+    pub frequency: Option<String>,
+    pub last_sent_at: Option<Iso8601>,
+}
+
+impl From<Option<DigestSubscription>> for DigestResponse {
+    fn from(subscription: Option<DigestSubscription>) -> Self {
+        let digest = match subscription {
+            Some(subscription) => DigestContent {
+                frequency: Some(subscription.frequency),
+                last_sent_at: subscription.last_sent_at.map(Iso8601),
+            },
+            None => DigestContent {
+                frequency: None,
+                last_sent_at: None,
+            },
+        };
+        Self { digest }
+    }
+}
@@ -0,0 +1,32 @@
+use super::service;
+use crate::error::AppError;
+use crate::utils::db::DbPool;
+use crate::utils::mailer::Mailer;
+use actix_web::rt;
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Sends the email digests that are due, for the lifetime of the server.
+pub fn spawn(pool: DbPool, mailer: Arc<dyn Mailer>, base_url: String, interval: Duration) {
+    rt::spawn(async move {
+        loop {
+            let pool = pool.clone();
+            let mailer = mailer.clone();
+            let base_url = base_url.clone();
+            let sent =
+                actix_web::web::block(move || send_due(&pool, mailer.as_ref(), &base_url)).await;
+            match sent {
+                Ok(Ok(0)) | Err(_) => {}
+                Ok(Ok(sent)) => info!("sent {} digest email(s)", sent),
+                Ok(Err(err)) => error!("failed to send digests: {}", err),
+            }
+            rt::time::sleep(interval).await;
+        }
+    });
+}
+
+fn send_due(pool: &DbPool, mailer: &dyn Mailer, base_url: &str) -> Result<usize, AppError> {
+    let conn = pool.get()?;
+    service::send_due_digests(&conn, mailer, base_url, Utc::now().naive_utc())
+}
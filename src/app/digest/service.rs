@@ -0,0 +1,222 @@
+use super::model::{DigestArticle, DigestFrequency, DigestSubscription};
+use crate::app::article::service::{fetch_digest_articles, FetchDigestArticles};
+use crate::app::user::model::User;
+use crate::constants;
+use crate::error::AppError;
+use crate::utils::db;
+use crate::utils::mailer::{Email, Mailer};
+use askama::Template;
+use chrono::NaiveDateTime;
+use diesel::pg::PgConnection;
+use uuid::Uuid;
+
+pub fn fetch_digest(
+    conn: &PgConnection,
+    me: &User,
+) -> Result<Option<DigestSubscription>, AppError> {
+    DigestSubscription::find(conn, me.id)
+}
+
+pub struct UpdateDigestService {
+    pub me: User,
+    // None unsubscribes.
+    pub frequency: Option<String>,
+    pub now: NaiveDateTime,
+}
+
+pub fn update_digest(
+    conn: &PgConnection,
+    params: &UpdateDigestService,
+) -> Result<Option<DigestSubscription>, AppError> {
+    match &params.frequency {
+        Some(frequency) => {
+            let frequency = DigestFrequency::parse(frequency)?;
+            let subscription =
+                DigestSubscription::upsert(conn, params.me.id, frequency, params.now)?;
+            Ok(Some(subscription))
+        }
+        None => {
+            DigestSubscription::delete(conn, params.me.id)?;
+            Ok(None)
+        }
+    }
+}
+
+pub struct DigestItem {
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub url: String,
+}
+
+#[derive(Template)]
+#[template(path = "digest.html")]
+struct DigestHtml<'a> {
+    username: &'a str,
+    frequency: &'a str,
+    period: &'a str,
+    articles: &'a [DigestItem],
+    more_count: i64,
+    feed_url: &'a str,
+    settings_url: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "digest.txt")]
+struct DigestText<'a> {
+    username: &'a str,
+    frequency: &'a str,
+    period: &'a str,
+    articles: &'a [DigestItem],
+    more_count: i64,
+    feed_url: &'a str,
+    settings_url: &'a str,
+}
+
+pub struct Digest {
+    pub email: Email,
+    pub article_ids: Vec<Uuid>,
+}
+
+// Builds the user's digest from the articles of the authors they follow: everything
+// published within one period before the last digest (or before now for the first) that no
+// earlier digest contained. None when there is nothing new.
+pub fn build_digest(
+    conn: &PgConnection,
+    user: &User,
+    subscription: &DigestSubscription,
+    base_url: &str,
+    now: NaiveDateTime,
+) -> Result<Option<Digest>, AppError> {
+    let frequency = subscription.frequency()?;
+    // The window reaches back past the last digest: a scheduled article keeps its scheduled
+    // `published_at` but only shows up once the scheduler publishes it, which can be after
+    // a digest went out. Articles sent before are filtered out below.
+    let published_since = subscription.last_sent_at.unwrap_or(now) - frequency.period();
+    let (list, articles_count) = fetch_digest_articles(
+        conn,
+        &FetchDigestArticles {
+            me: user.to_owned(),
+            published_since,
+            limit: constants::DIGEST_MAX_ARTICLES,
+        },
+    )?;
+    let fetched_ids = list
+        .iter()
+        .map(|((article, _, _), _)| article.id)
+        .collect::<Vec<_>>();
+    let sent_ids = DigestArticle::fetch_sent_ids(conn, user.id, &fetched_ids)?;
+    let base_url = base_url.trim_end_matches('/');
+    let (article_ids, articles): (Vec<_>, Vec<_>) = list
+        .into_iter()
+        .filter(|((article, _, _), _)| !sent_ids.contains(&article.id))
+        .map(|((article, profile, _), _)| {
+            let item = DigestItem {
+                url: format!("{}/article/{}", base_url, article.slug),
+                title: article.title,
+                description: article.description,
+                author: profile.username,
+            };
+            (article.id, item)
+        })
+        .unzip();
+    if articles.is_empty() {
+        return Ok(None);
+    }
+
+    let feed_url = format!("{}/", base_url);
+    let settings_url = format!("{}/settings", base_url);
+    let period = match frequency {
+        DigestFrequency::Daily => "today",
+        DigestFrequency::Weekly => "this week",
+    };
+    let more_count = articles_count - fetched_ids.len() as i64;
+    let render_error = |err: askama::Error| {
+        error!("failed to render digest: {}", err);
+        AppError::InternalServerError
+    };
+    let html = DigestHtml {
+        username: &user.username,
+        frequency: frequency.as_str(),
+        period,
+        articles: &articles,
+        more_count,
+        feed_url: &feed_url,
+        settings_url: &settings_url,
+    }
+    .render()
+    .map_err(render_error)?;
+    let text = DigestText {
+        username: &user.username,
+        frequency: frequency.as_str(),
+        period,
+        articles: &articles,
+        more_count,
+        feed_url: &feed_url,
+        settings_url: &settings_url,
+    }
+    .render()
+    .map_err(render_error)?;
+    let subject = match articles.as_slice() {
+        [only] => format!("New on Conduit: {}", only.title),
+        _ => format!("{} new articles from people you follow", articles.len()),
+    };
+
+    Ok(Some(Digest {
+        email: Email {
+            to: user.email.to_owned(),
+            subject,
+            text,
+            html,
+        },
+        article_ids,
+    }))
+}
+
+// Sends the digest of one user if it is due. The articles are recorded as sent in the
+// same transaction, so a failed send leaves everything to be retried on the next run.
+fn send_digest(
+    conn: &PgConnection,
+    mailer: &dyn Mailer,
+    base_url: &str,
+    user_id: Uuid,
+    now: NaiveDateTime,
+) -> Result<bool, AppError> {
+    db::unit_of_work(conn, || {
+        let subscription = match DigestSubscription::lock_if_due(conn, user_id, now)? {
+            Some(subscription) => subscription,
+            None => return Ok(false),
+        };
+        let user = User::find_by_id(conn, user_id)?;
+        let digest = build_digest(conn, &user, &subscription, base_url, now)?;
+        // An empty digest still counts as sent, so the next window starts from now.
+        DigestSubscription::mark_sent(conn, user_id, now)?;
+        match digest {
+            Some(digest) => {
+                DigestArticle::record_list(conn, user_id, &digest.article_ids)?;
+                mailer.send(&digest.email)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    })
+}
+
+// Sends every digest that is due. A failing user is logged and retried on the next run
+// without holding up the others. Returns how many emails went out.
+pub fn send_due_digests(
+    conn: &PgConnection,
+    mailer: &dyn Mailer,
+    base_url: &str,
+    now: NaiveDateTime,
+) -> Result<usize, AppError> {
+    let mut sent = 0;
+    for user_id in DigestSubscription::fetch_due_user_ids(conn, now)? {
+        match send_digest(conn, mailer, base_url, user_id, now) {
+            Ok(true) => sent += 1,
+            Ok(false) => {}
+            Err(err) => error!("failed to send the digest of user {}: {}", user_id, err),
+        }
+    }
+    Ok(sent)
+}
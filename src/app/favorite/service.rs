@@ -1,11 +1,17 @@
-use crate::app::article::model::{Article, FetchBySlugAndAuthorId};
+use crate::app::article::model::Article;
+use crate::app::article::response::SingleArticleResponse;
 use crate::app::article::service::{fetch_article, FetchArticle};
 use crate::app::favorite::model::{Favorite, FavoriteInfo, FavorteAction, UnfavoriteAction};
+use crate::app::notification::{self, service::NotificationEvent};
 use crate::app::profile::model::Profile;
+use crate::app::stream::{self, hub::StreamEvent};
 use crate::app::tag::model::Tag;
 use crate::app::user::model::User;
+use crate::app::webhook::{self, model::WebhookEvent};
 use crate::error::AppError;
+use crate::utils::db;
 use diesel::pg::PgConnection;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
 pub struct FavoriteService {
@@ -16,28 +22,40 @@ pub fn favorite(
     conn: &PgConnection,
     params: &FavoriteService,
 ) -> Result<(Article, Profile, FavoriteInfo, Vec<Tag>), AppError> {
-    let article = Article::fetch_by_slug_and_author_id(
-        conn,
-        &FetchBySlugAndAuthorId {
-            slug: params.article_title_slug.to_owned(),
-            author_id: params.me.id,
-        },
-    )?;
-    let _ = Favorite::favorite(
-        conn,
-        &FavorteAction {
-            user_id: params.me.id,
-            article_id: article.id,
-        },
-    )?;
-    let item = fetch_article(
-        conn,
-        &FetchArticle {
-            article_id: article.id,
-            me: params.me.to_owned(),
-        },
-    )?;
-    Ok(item)
+    db::unit_of_work(conn, || {
+        let article = Article::fetch_published_by_slug(conn, &params.article_title_slug)?;
+        let _ = Favorite::favorite(
+            conn,
+            &FavorteAction {
+                user_id: params.me.id,
+                article_id: article.id,
+            },
+        )?;
+        notification::service::notify(
+            conn,
+            NotificationEvent::Favorited {
+                user_id: params.me.id,
+                article: &article,
+            },
+        )?;
+        #[cfg(test)]
+        db::fail_point("favorite::favorite::fetch")?;
+        let item = fetch_article(
+            conn,
+            &FetchArticle {
+                article_id: article.id,
+                me: params.me.to_owned(),
+            },
+        )?;
+        publish_favorites_count(conn, &item.0, &item.2)?;
+        webhook::service::enqueue(
+            conn,
+            article.author_id,
+            WebhookEvent::FavoriteCreated,
+            || Ok(SingleArticleResponse::from(item.clone())),
+        )?;
+        Ok(item)
+    })
 }
 
 pub struct UnfavoriteService {
@@ -48,52 +66,142 @@ pub fn unfavorite(
     conn: &PgConnection,
     params: &UnfavoriteService,
 ) -> Result<(Article, Profile, FavoriteInfo, Vec<Tag>), AppError> {
-    let article = Article::fetch_by_slug_and_author_id(
-        conn,
-        &FetchBySlugAndAuthorId {
-            slug: params.article_title_slug.to_owned(),
-            author_id: params.me.id,
-        },
-    )?;
-    let _ = Favorite::unfavorite(
-        conn,
-        &UnfavoriteAction {
-            user_id: params.me.id,
-            article_id: article.id,
-        },
-    )?;
-    let item = fetch_article(
+    db::unit_of_work(conn, || {
+        let article = Article::fetch_published_by_slug(conn, &params.article_title_slug)?;
+        let _ = Favorite::unfavorite(
+            conn,
+            &UnfavoriteAction {
+                user_id: params.me.id,
+                article_id: article.id,
+            },
+        )?;
+        let item = fetch_article(
+            conn,
+            &FetchArticle {
+                article_id: article.id,
+                me: params.me.to_owned(),
+            },
+        )?;
+        publish_favorites_count(conn, &item.0, &item.2)?;
+        Ok(item)
+    })
+}
+
+fn publish_favorites_count(
+    conn: &PgConnection,
+    article: &Article,
+    favorite_info: &FavoriteInfo,
+) -> Result<(), AppError> {
+    stream::hub::publish(
         conn,
-        &FetchArticle {
-            article_id: article.id,
-            me: params.me.to_owned(),
+        StreamEvent::Favorites {
+            article_slug: article.slug.to_owned(),
+            favorites_count: favorite_info.favorites_count,
         },
-    )?;
-    Ok(item)
+    )
 }
 
-pub fn fetch_favorites_count_by_article_id(
+// Favorite counts for a whole page of articles, plus whether `viewer_id` favorited each of
+// them, in at most two queries. Articles nobody favorited get a zero count.
+pub fn fetch_favorite_infos(
     conn: &PgConnection,
-    _article_id: Uuid,
-) -> Result<i64, AppError> {
+    viewer_id: Option<Uuid>,
+    article_ids: &[Uuid],
+) -> Result<HashMap<Uuid, FavoriteInfo>, AppError> {
     use crate::schema::favorites;
     use diesel::prelude::*;
-    let favorites_count = favorites::table
-        .filter(favorites::article_id.eq_all(_article_id))
-        .select(diesel::dsl::count(favorites::created_at))
-        .first::<i64>(conn)?;
-    Ok(favorites_count)
+    let favorites_counts = favorites::table
+        .filter(favorites::article_id.eq_any(article_ids))
+        .group_by(favorites::article_id)
+        // NOTE: diesel 1.x cannot mix aggregates and grouped columns in a select.
+        .select((
+            favorites::article_id,
+            diesel::dsl::sql::<diesel::sql_types::BigInt>("COUNT(*)"),
+        ))
+        .load::<(Uuid, i64)>(conn)?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    let favorited_article_ids = match viewer_id {
+        Some(viewer_id) => favorites::table
+            .filter(favorites::user_id.eq(viewer_id))
+            .filter(favorites::article_id.eq_any(article_ids))
+            .select(favorites::article_id)
+            .load::<Uuid>(conn)?
+            .into_iter()
+            .collect::<HashSet<_>>(),
+        None => HashSet::new(),
+    };
+
+    let favorite_infos = article_ids
+        .iter()
+        .map(|article_id| {
+            let favorite_info = FavoriteInfo {
+                is_favorited: favorited_article_ids.contains(article_id),
+                favorites_count: favorites_counts.get(article_id).copied().unwrap_or(0),
+            };
+            (*article_id, favorite_info)
+        })
+        .collect();
+    Ok(favorite_infos)
 }
 
-pub fn fetch_favorited_article_ids_by_user_id(
+pub fn fetch_favorite_info(
     conn: &PgConnection,
-    user_id: Uuid,
-) -> Result<Vec<Uuid>, AppError> {
+    viewer_id: Option<Uuid>,
+    article_id: Uuid,
+) -> Result<FavoriteInfo, AppError> {
+    let mut favorite_infos = fetch_favorite_infos(conn, viewer_id, &[article_id])?;
+    favorite_infos
+        .remove(&article_id)
+        .ok_or(AppError::InternalServerError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::article::model::CreateArticle;
     use crate::schema::favorites;
+    use crate::utils::db::testing;
     use diesel::prelude::*;
-    let favorited_article_ids = favorites::table
-        .filter(favorites::user_id.eq(user_id))
-        .select(favorites::article_id)
-        .get_results::<Uuid>(conn)?;
-    Ok(favorited_article_ids)
+
+    #[test]
+    fn favorite_rolls_back_when_a_later_step_fails() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let me = testing::insert_user(&conn, "uow_favoriter");
+        let article = Article::create(
+            &conn,
+            &CreateArticle {
+                author_id: me.id,
+                slug: "uow-favorite".to_string(),
+                slug_base: "uow-favorite".to_string(),
+                title: "Favorite".to_string(),
+                description: "description".to_string(),
+                body: "body".to_string(),
+                status: "published".to_string(),
+                published_at: None,
+            },
+        )
+        .unwrap();
+        {
+            let _armed = testing::arm_fail_point("favorite::favorite::fetch");
+            let result = favorite(
+                &conn,
+                &FavoriteService {
+                    me: me.to_owned(),
+                    article_title_slug: article.slug.to_owned(),
+                },
+            );
+            assert!(result.is_err());
+        }
+        let count = favorites::table
+            .filter(favorites::article_id.eq(article.id))
+            .count()
+            .get_result::<i64>(&conn)
+            .unwrap();
+        assert_eq!(0, count);
+    }
 }
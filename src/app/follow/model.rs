@@ -4,6 +4,7 @@ use crate::schema::follows;
 use chrono::NaiveDateTime;
 use diesel::pg::PgConnection;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use uuid::Uuid;
 
 #[derive(Queryable, Associations, Clone, Serialize, Deserialize)]
@@ -25,6 +26,40 @@ impl Follow {
         Ok(())
     }
 
+    // Which of `candidate_ids` the follower follows, in one query.
+    pub fn fetch_followee_ids(
+        conn: &PgConnection,
+        _follower_id: Uuid,
+        candidate_ids: &[Uuid],
+    ) -> Result<HashSet<Uuid>, AppError> {
+        use crate::schema::follows::dsl::*;
+        use diesel::prelude::*;
+        let followee_ids = follows
+            .filter(follower_id.eq(_follower_id))
+            .filter(followee_id.eq_any(candidate_ids))
+            .select(followee_id)
+            .load::<Uuid>(conn)?
+            .into_iter()
+            .collect();
+        Ok(followee_ids)
+    }
+
+    // Everyone the follower follows.
+    pub fn fetch_all_followee_ids(
+        conn: &PgConnection,
+        _follower_id: Uuid,
+    ) -> Result<HashSet<Uuid>, AppError> {
+        use crate::schema::follows::dsl::*;
+        use diesel::prelude::*;
+        let followee_ids = follows
+            .filter(follower_id.eq(_follower_id))
+            .select(followee_id)
+            .load::<Uuid>(conn)?
+            .into_iter()
+            .collect();
+        Ok(followee_ids)
+    }
+
     pub fn delete_follow(conn: &PgConnection, params: &DeleteFollow) -> Result<(), AppError> {
         use crate::schema::follows::dsl::*;
         use diesel::prelude::*;
@@ -0,0 +1,31 @@
+use super::{response::MultipleMentionsResponse, service};
+use crate::error::AppError;
+use crate::middleware::auth;
+use crate::middleware::state::AppState;
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct MentionsListQueryParameter {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+pub async fn index(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    params: web::Query<MentionsListQueryParameter>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let list = service::fetch_mentions_list(
+        &conn,
+        &service::FetchMentionsList {
+            me: auth_user,
+            offset: params.offset.unwrap_or(0).max(0),
+            limit: state.config.pagination.limit(params.limit),
+        },
+    )?;
+    let res = MultipleMentionsResponse::from(list);
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -0,0 +1,140 @@
+use crate::app::article::model::Article;
+use crate::app::comment::model::Comment;
+use crate::app::user::model::User;
+use crate::error::AppError;
+use crate::schema::mentions;
+use chrono::NaiveDateTime;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+#[derive(Identifiable, Queryable, Associations, Debug, Serialize, Deserialize, Clone)]
+#[belongs_to(Article, foreign_key = "article_id")]
+#[belongs_to(User, foreign_key = "author_id")]
+#[table_name = "mentions"]
+pub struct Mention {
+    pub id: Uuid,
+    // The mentioned user.
+    pub user_id: Uuid,
+    // Who wrote the mention.
+    pub author_id: Uuid,
+    pub article_id: Uuid,
+    // None when the mention is in the article's body.
+    pub comment_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "mentions"]
+struct NewMention {
+    user_id: Uuid,
+    author_id: Uuid,
+    article_id: Uuid,
+    comment_id: Option<Uuid>,
+}
+
+impl Mention {
+    // Returns the users the article mentioned before.
+    pub fn replace_for_article(
+        conn: &PgConnection,
+        article: &Article,
+        user_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, AppError> {
+        let previous = diesel::delete(
+            mentions::table
+                .filter(mentions::article_id.eq(article.id))
+                .filter(mentions::comment_id.is_null()),
+        )
+        .returning(mentions::user_id)
+        .get_results::<Uuid>(conn)?;
+        Self::insert_list(conn, article.author_id, article.id, None, user_ids)?;
+        Ok(previous)
+    }
+
+    // Returns the users the comment mentioned before.
+    pub fn replace_for_comment(
+        conn: &PgConnection,
+        comment: &Comment,
+        user_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, AppError> {
+        let previous = diesel::delete(mentions::table.filter(mentions::comment_id.eq(comment.id)))
+            .returning(mentions::user_id)
+            .get_results::<Uuid>(conn)?;
+        Self::insert_list(
+            conn,
+            comment.author_id,
+            comment.article_id,
+            Some(comment.id),
+            user_ids,
+        )?;
+        Ok(previous)
+    }
+
+    fn insert_list(
+        conn: &PgConnection,
+        _author_id: Uuid,
+        _article_id: Uuid,
+        _comment_id: Option<Uuid>,
+        user_ids: &[Uuid],
+    ) -> Result<(), AppError> {
+        let records = user_ids
+            .iter()
+            .map(|_user_id| NewMention {
+                user_id: *_user_id,
+                author_id: _author_id,
+                article_id: _article_id,
+                comment_id: _comment_id,
+            })
+            .collect::<Vec<_>>();
+        if records.is_empty() {
+            return Ok(());
+        }
+        let _ = diesel::insert_into(mentions::table)
+            .values(&records)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    // Usernames mentioned in each of the articles' bodies, in one query.
+    pub fn fetch_usernames_by_article_ids(
+        conn: &PgConnection,
+        article_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, HashSet<String>>, AppError> {
+        use crate::schema::users;
+        let rows = mentions::table
+            .inner_join(users::table.on(users::id.eq(mentions::user_id)))
+            .filter(mentions::article_id.eq_any(article_ids))
+            .filter(mentions::comment_id.is_null())
+            .select((mentions::article_id, users::username))
+            .load::<(Uuid, String)>(conn)?;
+        Ok(group(rows))
+    }
+
+    // Usernames mentioned in each of the comments, in one query.
+    pub fn fetch_usernames_by_comment_ids(
+        conn: &PgConnection,
+        comment_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, HashSet<String>>, AppError> {
+        use crate::schema::users;
+        let rows = mentions::table
+            .inner_join(users::table.on(users::id.eq(mentions::user_id)))
+            .filter(mentions::comment_id.eq_any(comment_ids))
+            .select((mentions::comment_id, users::username))
+            .load::<(Option<Uuid>, String)>(conn)?;
+        let rows = rows
+            .into_iter()
+            .filter_map(|(_comment_id, username)| _comment_id.map(|id| (id, username)))
+            .collect();
+        Ok(group(rows))
+    }
+}
+
+fn group(rows: Vec<(Uuid, String)>) -> HashMap<Uuid, HashSet<String>> {
+    let mut grouped = HashMap::<Uuid, HashSet<String>>::new();
+    for (id, username) in rows {
+        grouped.entry(id).or_default().insert(username);
+    }
+    grouped
+}
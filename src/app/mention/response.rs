@@ -0,0 +1,72 @@
+use crate::app::mention::service::MentionsList;
+use crate::utils::date::Iso8601;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultipleMentionsResponse {
+    pub mentions: Vec<MentionContent>,
+    pub mentions_count: i64,
+}
+
+impl From<(MentionsList, i64)> for MultipleMentionsResponse {
+    fn from((list, mentions_count): (MentionsList, i64)) -> Self {
+        let mentions = list
+            .into_iter()
+            .map(|(mention, profile, article, comment)| MentionContent {
+                id: mention.id,
+                created_at: Iso8601(mention.created_at),
+                author: AuthorContent {
+                    username: profile.username,
+                    bio: profile.bio,
+                    image: profile.image,
+                    following: profile.following,
+                },
+                article: ArticleContent {
+                    slug: article.slug,
+                    title: article.title,
+                },
+                comment: comment.map(|comment| CommentContent {
+                    id: comment.id,
+                    body: comment.body,
+                }),
+            })
+            .collect();
+        Self {
+            mentions,
+            mentions_count,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MentionContent {
+    pub id: Uuid,
+    pub created_at: Iso8601,
+    pub author: AuthorContent,
+    pub article: ArticleContent,
+    // None when the mention is in the article's body.
+    pub comment: Option<CommentContent>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AuthorContent {
+    pub username: String,
+    pub bio: Option<String>,
+    pub image: Option<String>,
+    pub following: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ArticleContent {
+    pub slug: String,
+    pub title: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CommentContent {
+    pub id: Uuid,
+    pub body: String,
+}
@@ -0,0 +1,205 @@
+use super::model::Mention;
+use crate::app::article::model::{Article, ArticleStatus};
+use crate::app::comment::model::Comment;
+use crate::app::follow::model::Follow;
+use crate::app::notification::{self, service::NotificationEvent};
+use crate::app::profile::model::Profile;
+use crate::app::user::model::User;
+use crate::error::AppError;
+use crate::utils::mention::{self, MentionRange};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+// Ids of the existing users mentioned in `text`. Unknown usernames are ignored.
+fn resolve_user_ids(conn: &PgConnection, text: &str) -> Result<Vec<Uuid>, AppError> {
+    use crate::schema::users;
+    let usernames = mention::parse(text)
+        .into_iter()
+        .map(|range| range.username)
+        .collect::<HashSet<_>>();
+    if usernames.is_empty() {
+        return Ok(vec![]);
+    }
+    let ids = users::table
+        .filter(users::username.eq_any(usernames))
+        .select(users::id)
+        .load::<Uuid>(conn)?;
+    Ok(ids)
+}
+
+// Tells the users who weren't mentioned before. Editing a text doesn't notify again.
+fn notify_mentioned(
+    conn: &PgConnection,
+    author_id: Uuid,
+    article_id: Uuid,
+    comment_id: Option<Uuid>,
+    user_ids: &[Uuid],
+    previous: &[Uuid],
+) -> Result<(), AppError> {
+    for user_id in user_ids.iter().filter(|id| !previous.contains(id)) {
+        notification::service::notify(
+            conn,
+            NotificationEvent::Mentioned {
+                author_id,
+                user_id: *user_id,
+                article_id,
+                comment_id,
+            },
+        )?;
+    }
+    Ok(())
+}
+
+// Re-reads the mentions in the article's body. Call it whenever the body may have changed,
+// and when the article gets published: until then nobody is told about content they can't
+// open, and the notification goes out when it can be read.
+pub fn sync_article_mentions(conn: &PgConnection, article: &Article) -> Result<(), AppError> {
+    if !article.is_published() {
+        return Ok(());
+    }
+    let user_ids = resolve_user_ids(conn, &article.body)?;
+    let previous = Mention::replace_for_article(conn, article, &user_ids)?;
+    notify_mentioned(
+        conn,
+        article.author_id,
+        article.id,
+        None,
+        &user_ids,
+        &previous,
+    )
+}
+
+// Re-reads the mentions in the comment. Deleted comments mention nobody.
+pub fn sync_comment_mentions(conn: &PgConnection, comment: &Comment) -> Result<(), AppError> {
+    let user_ids = if comment.is_deleted() {
+        vec![]
+    } else {
+        resolve_user_ids(conn, &comment.body)?
+    };
+    let previous = Mention::replace_for_comment(conn, comment, &user_ids)?;
+    notify_mentioned(
+        conn,
+        comment.author_id,
+        comment.article_id,
+        Some(comment.id),
+        &user_ids,
+        &previous,
+    )
+}
+
+fn resolved_ranges(text: &str, usernames: Option<&HashSet<String>>) -> Vec<MentionRange> {
+    match usernames {
+        Some(usernames) => mention::parse(text)
+            .into_iter()
+            .filter(|range| usernames.contains(&range.username))
+            .collect(),
+        None => vec![],
+    }
+}
+
+// Mention ranges in each article's body, in one query.
+pub fn fetch_article_mention_ranges(
+    conn: &PgConnection,
+    articles: &[&Article],
+) -> Result<HashMap<Uuid, Vec<MentionRange>>, AppError> {
+    let article_ids = articles
+        .iter()
+        .map(|article| article.id)
+        .collect::<Vec<_>>();
+    let usernames = Mention::fetch_usernames_by_article_ids(conn, &article_ids)?;
+    let ranges = articles
+        .iter()
+        .map(|article| {
+            let ranges = resolved_ranges(&article.body, usernames.get(&article.id));
+            (article.id, ranges)
+        })
+        .collect();
+    Ok(ranges)
+}
+
+// Mention ranges in each comment, in one query.
+pub fn fetch_comment_mention_ranges(
+    conn: &PgConnection,
+    comments: &[&Comment],
+) -> Result<HashMap<Uuid, Vec<MentionRange>>, AppError> {
+    let comment_ids = comments
+        .iter()
+        .map(|comment| comment.id)
+        .collect::<Vec<_>>();
+    let usernames = Mention::fetch_usernames_by_comment_ids(conn, &comment_ids)?;
+    let ranges = comments
+        .iter()
+        .map(|comment| {
+            let ranges = resolved_ranges(&comment.body, usernames.get(&comment.id));
+            (comment.id, ranges)
+        })
+        .collect();
+    Ok(ranges)
+}
+
+pub struct FetchMentionsList {
+    pub me: User,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+pub type MentionsList = Vec<(Mention, Profile, Article, Option<Comment>)>;
+
+// Where `me` was mentioned by others, newest first. Mentions in unpublished articles and
+// in deleted or hidden comments are left out.
+pub fn fetch_mentions_list(
+    conn: &PgConnection,
+    params: &FetchMentionsList,
+) -> Result<(MentionsList, i64), AppError> {
+    use crate::schema::{articles, comments, mentions, users};
+    let list = mentions::table
+        .inner_join(users::table)
+        .inner_join(articles::table)
+        .left_join(comments::table)
+        .filter(mentions::user_id.eq(params.me.id))
+        .filter(mentions::author_id.ne(params.me.id))
+        .filter(articles::status.eq(ArticleStatus::Published.as_str()))
+        .filter(comments::deleted_at.is_null())
+        .filter(comments::hidden_at.is_null())
+        .select((
+            mentions::all_columns,
+            users::all_columns,
+            articles::all_columns,
+            comments::all_columns.nullable(),
+        ))
+        .order((mentions::created_at.desc(), mentions::id.desc()))
+        .offset(params.offset)
+        .limit(params.limit)
+        .load::<(Mention, User, Article, Option<Comment>)>(conn)?;
+    let mentions_count = mentions::table
+        .inner_join(articles::table)
+        .left_join(comments::table)
+        .filter(mentions::user_id.eq(params.me.id))
+        .filter(mentions::author_id.ne(params.me.id))
+        .filter(articles::status.eq(ArticleStatus::Published.as_str()))
+        .filter(comments::deleted_at.is_null())
+        .filter(comments::hidden_at.is_null())
+        .count()
+        .get_result::<i64>(conn)?;
+
+    let author_ids = list
+        .iter()
+        .map(|(_, author, _, _)| author.id)
+        .collect::<Vec<_>>();
+    let followee_ids = Follow::fetch_followee_ids(conn, params.me.id, &author_ids)?;
+    let list = list
+        .into_iter()
+        .map(|(_mention, author, article, comment)| {
+            let profile = Profile {
+                following: followee_ids.contains(&author.id),
+                username: author.username,
+                bio: author.bio,
+                image: author.image,
+            };
+            (_mention, profile, article, comment)
+        })
+        .collect();
+    Ok((list, mentions_count))
+}
@@ -1,8 +1,14 @@
 pub mod article;
 pub mod comment;
+pub mod digest;
 pub mod favorite;
 pub mod follow;
+pub mod mention;
+pub mod notification;
 pub mod profile;
+pub mod revision;
+pub mod stream;
 pub mod tag;
 pub mod user;
+pub mod webhook;
 pub mod healthcheck;
\ No newline at end of file
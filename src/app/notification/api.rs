@@ -0,0 +1,60 @@
+use super::{
+    response::{MultipleNotificationsResponse, UnreadCountResponse},
+    service,
+};
+use crate::error::AppError;
+use crate::middleware::auth;
+use crate::middleware::state::AppState;
+use crate::utils::uuid;
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+
+type NotificationIdSlug = String;
+
+#[derive(Deserialize)]
+pub struct NotificationsListQueryParameter {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+pub async fn index(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    params: web::Query<NotificationsListQueryParameter>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let list = service::fetch_notifications_list(
+        &conn,
+        &service::FetchNotificationsList {
+            me: auth_user,
+            offset: params.offset.unwrap_or(0).max(0),
+            limit: state.config.pagination.limit(params.limit),
+        },
+    )?;
+    let res = MultipleNotificationsResponse::from(list);
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn read(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<NotificationIdSlug>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let id = uuid::parse(&path.into_inner())?;
+    let unread_count =
+        service::mark_read(&conn, &service::MarkNotificationRead { me: auth_user, id })?;
+    Ok(HttpResponse::Ok().json(UnreadCountResponse { unread_count }))
+}
+
+pub async fn read_all(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let unread_count = service::mark_all_read(&conn, &auth_user)?;
+    Ok(HttpResponse::Ok().json(UnreadCountResponse { unread_count }))
+}
@@ -0,0 +1,181 @@
+use crate::error::AppError;
+use crate::schema::notifications;
+use chrono::NaiveDateTime;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Bool, Nullable, Text, Timestamp, Uuid as SqlUuid};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    Follow,
+    Favorite,
+    Comment,
+    Mention,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::Follow => "follow",
+            NotificationKind::Favorite => "favorite",
+            NotificationKind::Comment => "comment",
+            NotificationKind::Mention => "mention",
+        }
+    }
+}
+
+#[derive(Identifiable, Queryable, Debug, Clone)]
+#[table_name = "notifications"]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub actor_id: Uuid,
+    pub kind: String,
+    pub article_id: Option<Uuid>,
+    pub comment_id: Option<Uuid>,
+    pub group_key: String,
+    pub read_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "notifications"]
+pub struct NewNotification {
+    pub user_id: Uuid,
+    pub actor_id: Uuid,
+    pub kind: String,
+    pub article_id: Option<Uuid>,
+    pub comment_id: Option<Uuid>,
+    pub group_key: String,
+}
+
+// Unread notifications sharing a group key collapse into one group, and so do read ones.
+// `id` is the group's latest notification, which also stands for the whole group when
+// marking it read.
+#[derive(QueryableByName, Debug, Clone)]
+pub struct NotificationGroup {
+    #[sql_type = "SqlUuid"]
+    pub id: Uuid,
+    #[sql_type = "Text"]
+    pub kind: String,
+    #[sql_type = "Nullable<SqlUuid>"]
+    pub article_id: Option<Uuid>,
+    #[sql_type = "Nullable<SqlUuid>"]
+    pub comment_id: Option<Uuid>,
+    #[sql_type = "BigInt"]
+    pub events_count: i64,
+    #[sql_type = "BigInt"]
+    pub actors_count: i64,
+    // Latest first, with repeats.
+    #[sql_type = "Array<SqlUuid>"]
+    pub actor_ids: Vec<Uuid>,
+    #[sql_type = "Bool"]
+    pub unread: bool,
+    #[sql_type = "Timestamp"]
+    pub latest_at: NaiveDateTime,
+}
+
+#[derive(QueryableByName)]
+struct GroupsCount {
+    #[sql_type = "BigInt"]
+    groups_count: i64,
+    #[sql_type = "BigInt"]
+    unread_count: i64,
+}
+
+// Notifications the user can still see: those about unpublished articles and deleted or
+// hidden comments are left out, without being removed.
+const VISIBLE_NOTIFICATIONS_SQL: &str = r#"
+WITH visible AS (
+    SELECT n.*
+    FROM notifications n
+    LEFT JOIN articles a ON a.id = n.article_id
+    LEFT JOIN comments c ON c.id = n.comment_id
+    WHERE n.user_id = $1
+      AND (n.article_id IS NULL OR a.status = 'published')
+      AND c.deleted_at IS NULL
+      AND c.hidden_at IS NULL
+), groups AS (
+    SELECT
+        (ARRAY_AGG(id ORDER BY created_at DESC, id DESC))[1] AS id,
+        MIN(kind) AS kind,
+        (ARRAY_AGG(article_id ORDER BY created_at DESC, id DESC))[1] AS article_id,
+        (ARRAY_AGG(comment_id ORDER BY created_at DESC, id DESC))[1] AS comment_id,
+        COUNT(*) AS events_count,
+        COUNT(DISTINCT actor_id) AS actors_count,
+        ARRAY_AGG(actor_id ORDER BY created_at DESC, id DESC) AS actor_ids,
+        read_at IS NULL AS unread,
+        MAX(created_at) AS latest_at
+    FROM visible
+    GROUP BY group_key, read_at IS NULL
+)
+"#;
+
+impl Notification {
+    pub fn create(conn: &PgConnection, record: &NewNotification) -> Result<(), AppError> {
+        let _ = diesel::insert_into(notifications::table)
+            .values(record)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn fetch_groups(
+        conn: &PgConnection,
+        _user_id: Uuid,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<NotificationGroup>, AppError> {
+        let query = format!(
+            "{} SELECT * FROM groups ORDER BY latest_at DESC, id DESC OFFSET $2 LIMIT $3",
+            VISIBLE_NOTIFICATIONS_SQL
+        );
+        let list = diesel::sql_query(query)
+            .bind::<SqlUuid, _>(_user_id)
+            .bind::<BigInt, _>(offset)
+            .bind::<BigInt, _>(limit)
+            .load::<NotificationGroup>(conn)?;
+        Ok(list)
+    }
+
+    // How many groups there are in total, and how many of them are unread.
+    pub fn count_groups(conn: &PgConnection, _user_id: Uuid) -> Result<(i64, i64), AppError> {
+        let query = format!(
+            "{} SELECT COUNT(*) AS groups_count, COUNT(*) FILTER (WHERE unread) AS unread_count FROM groups",
+            VISIBLE_NOTIFICATIONS_SQL
+        );
+        let count = diesel::sql_query(query)
+            .bind::<SqlUuid, _>(_user_id)
+            .get_result::<GroupsCount>(conn)?;
+        Ok((count.groups_count, count.unread_count))
+    }
+
+    // Marks the group `_id` stands for as read, up to and including `_id`.
+    pub fn mark_group_read(conn: &PgConnection, _user_id: Uuid, _id: Uuid) -> Result<(), AppError> {
+        let notification = notifications::table
+            .filter(notifications::id.eq(_id))
+            .filter(notifications::user_id.eq(_user_id))
+            .first::<Notification>(conn)?;
+        let _ = diesel::update(
+            notifications::table
+                .filter(notifications::user_id.eq(_user_id))
+                .filter(notifications::group_key.eq(&notification.group_key))
+                .filter(notifications::created_at.le(notification.created_at))
+                .filter(notifications::read_at.is_null()),
+        )
+        .set(notifications::read_at.eq(diesel::dsl::now.nullable()))
+        .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn mark_all_read(conn: &PgConnection, _user_id: Uuid) -> Result<(), AppError> {
+        let _ = diesel::update(
+            notifications::table
+                .filter(notifications::user_id.eq(_user_id))
+                .filter(notifications::read_at.is_null()),
+        )
+        .set(notifications::read_at.eq(diesel::dsl::now.nullable()))
+        .execute(conn)?;
+        Ok(())
+    }
+}
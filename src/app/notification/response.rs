@@ -0,0 +1,92 @@
+use crate::app::notification::service::NotificationsList;
+use crate::utils::date::Iso8601;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultipleNotificationsResponse {
+    pub notifications: Vec<NotificationContent>,
+    pub notifications_count: i64,
+    pub unread_count: i64,
+}
+
+impl From<(NotificationsList, i64, i64)> for MultipleNotificationsResponse {
+    fn from((list, notifications_count, unread_count): (NotificationsList, i64, i64)) -> Self {
+        let notifications = list
+            .into_iter()
+            .map(|(group, actors, article, comment)| NotificationContent {
+                id: group.id,
+                kind: group.kind,
+                unread: group.unread,
+                created_at: Iso8601(group.latest_at),
+                events_count: group.events_count,
+                actors_count: group.actors_count,
+                actors: actors
+                    .into_iter()
+                    .map(|profile| ActorContent {
+                        username: profile.username,
+                        bio: profile.bio,
+                        image: profile.image,
+                        following: profile.following,
+                    })
+                    .collect(),
+                article: article.map(|article| ArticleContent {
+                    slug: article.slug,
+                    title: article.title,
+                }),
+                comment: comment.map(|comment| CommentContent {
+                    id: comment.id,
+                    body: comment.body,
+                }),
+            })
+            .collect();
+        Self {
+            notifications,
+            notifications_count,
+            unread_count,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationContent {
+    // Latest notification of the group. Marking it read marks the whole group.
+    pub id: Uuid,
+    pub kind: String,
+    pub unread: bool,
+    pub created_at: Iso8601,
+    pub events_count: i64,
+    pub actors_count: i64,
+    // The latest few distinct actors, newest first.
+    pub actors: Vec<ActorContent>,
+    pub article: Option<ArticleContent>,
+    pub comment: Option<CommentContent>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ActorContent {
+    pub username: String,
+    pub bio: Option<String>,
+    pub image: Option<String>,
+    pub following: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ArticleContent {
+    pub slug: String,
+    pub title: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CommentContent {
+    pub id: Uuid,
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreadCountResponse {
+    pub unread_count: i64,
+}
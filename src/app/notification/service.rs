@@ -0,0 +1,203 @@
+use super::model::{NewNotification, Notification, NotificationGroup, NotificationKind};
+use crate::app::article::model::Article;
+use crate::app::comment::model::Comment;
+use crate::app::follow::model::Follow;
+use crate::app::profile::model::Profile;
+use crate::app::stream::{self, hub::StreamEvent};
+use crate::app::user::model::User;
+use crate::error::AppError;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// How many of a group's actors are listed by name; the rest only count.
+const LISTED_ACTORS: usize = 3;
+
+pub enum NotificationEvent<'a> {
+    Followed {
+        follower_id: Uuid,
+        followee_id: Uuid,
+    },
+    Favorited {
+        user_id: Uuid,
+        article: &'a Article,
+    },
+    Commented {
+        comment: &'a Comment,
+        article: &'a Article,
+    },
+    Mentioned {
+        author_id: Uuid,
+        user_id: Uuid,
+        article_id: Uuid,
+        comment_id: Option<Uuid>,
+    },
+}
+
+// Records the event for whoever should hear about it. Nobody is notified of their own
+// actions.
+pub fn notify(conn: &PgConnection, event: NotificationEvent) -> Result<(), AppError> {
+    let record = match event {
+        NotificationEvent::Followed {
+            follower_id,
+            followee_id,
+        } => NewNotification {
+            user_id: followee_id,
+            actor_id: follower_id,
+            kind: NotificationKind::Follow.as_str().to_string(),
+            article_id: None,
+            comment_id: None,
+            group_key: "follow".to_string(),
+        },
+        NotificationEvent::Favorited { user_id, article } => NewNotification {
+            user_id: article.author_id,
+            actor_id: user_id,
+            kind: NotificationKind::Favorite.as_str().to_string(),
+            article_id: Some(article.id),
+            comment_id: None,
+            group_key: format!("favorite:{}", article.id),
+        },
+        NotificationEvent::Commented { comment, article } => NewNotification {
+            user_id: article.author_id,
+            actor_id: comment.author_id,
+            kind: NotificationKind::Comment.as_str().to_string(),
+            article_id: Some(article.id),
+            comment_id: Some(comment.id),
+            group_key: format!("comment:{}", article.id),
+        },
+        NotificationEvent::Mentioned {
+            author_id,
+            user_id,
+            article_id,
+            comment_id,
+        } => NewNotification {
+            user_id,
+            actor_id: author_id,
+            kind: NotificationKind::Mention.as_str().to_string(),
+            article_id: Some(article_id),
+            comment_id,
+            group_key: match comment_id {
+                Some(comment_id) => format!("mention:{}:{}", article_id, comment_id),
+                None => format!("mention:{}", article_id),
+            },
+        },
+    };
+    if record.user_id == record.actor_id {
+        return Ok(());
+    }
+    Notification::create(conn, &record)?;
+    stream::hub::publish(
+        conn,
+        StreamEvent::Notification {
+            user_id: record.user_id,
+            kind: record.kind,
+        },
+    )
+}
+
+pub struct FetchNotificationsList {
+    pub me: User,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+// A group with its latest few actors, and the article and comment it is about.
+pub type NotificationsList = Vec<(
+    NotificationGroup,
+    Vec<Profile>,
+    Option<Article>,
+    Option<Comment>,
+)>;
+
+// Returns a page of notification groups, newest first, with the total number of groups
+// and how many of them are unread.
+pub fn fetch_notifications_list(
+    conn: &PgConnection,
+    params: &FetchNotificationsList,
+) -> Result<(NotificationsList, i64, i64), AppError> {
+    use crate::schema::{articles, comments, users};
+    let groups = Notification::fetch_groups(conn, params.me.id, params.offset, params.limit)?;
+    let (groups_count, unread_count) = Notification::count_groups(conn, params.me.id)?;
+
+    let listed_actor_ids = groups
+        .iter()
+        .map(|group| {
+            let mut actor_ids = group.actor_ids.to_owned();
+            // Keeps each actor's latest appearance.
+            let mut seen = std::collections::HashSet::new();
+            actor_ids.retain(|actor_id| seen.insert(*actor_id));
+            actor_ids.truncate(LISTED_ACTORS);
+            actor_ids
+        })
+        .collect::<Vec<_>>();
+    let all_actor_ids = listed_actor_ids.concat();
+    let followee_ids = Follow::fetch_followee_ids(conn, params.me.id, &all_actor_ids)?;
+    let actors = users::table
+        .filter(users::id.eq_any(&all_actor_ids))
+        .load::<User>(conn)?
+        .into_iter()
+        .map(|user| {
+            let profile = Profile {
+                following: followee_ids.contains(&user.id),
+                username: user.username,
+                bio: user.bio,
+                image: user.image,
+            };
+            (user.id, profile)
+        })
+        .collect::<HashMap<_, _>>();
+    let article_ids = groups
+        .iter()
+        .filter_map(|group| group.article_id)
+        .collect::<Vec<_>>();
+    let _articles = articles::table
+        .filter(articles::id.eq_any(article_ids))
+        .load::<Article>(conn)?
+        .into_iter()
+        .map(|article| (article.id, article))
+        .collect::<HashMap<_, _>>();
+    let comment_ids = groups
+        .iter()
+        .filter_map(|group| group.comment_id)
+        .collect::<Vec<_>>();
+    let _comments = comments::table
+        .filter(comments::id.eq_any(comment_ids))
+        .load::<Comment>(conn)?
+        .into_iter()
+        .map(|comment| (comment.id, comment))
+        .collect::<HashMap<_, _>>();
+
+    let list = groups
+        .into_iter()
+        .zip(listed_actor_ids)
+        .map(|(group, actor_ids)| {
+            let group_actors = actor_ids
+                .iter()
+                .filter_map(|actor_id| actors.get(actor_id).cloned())
+                .collect();
+            let article = group.article_id.and_then(|id| _articles.get(&id).cloned());
+            let comment = group.comment_id.and_then(|id| _comments.get(&id).cloned());
+            (group, group_actors, article, comment)
+        })
+        .collect();
+    Ok((list, groups_count, unread_count))
+}
+
+pub struct MarkNotificationRead {
+    pub me: User,
+    pub id: Uuid,
+}
+
+// Marks the group the notification belongs to as read and returns the new unread count.
+pub fn mark_read(conn: &PgConnection, params: &MarkNotificationRead) -> Result<i64, AppError> {
+    Notification::mark_group_read(conn, params.me.id, params.id)?;
+    let (_, unread_count) = Notification::count_groups(conn, params.me.id)?;
+    Ok(unread_count)
+}
+
+pub fn mark_all_read(conn: &PgConnection, me: &User) -> Result<i64, AppError> {
+    Notification::mark_all_read(conn, me.id)?;
+    let (_, unread_count) = Notification::count_groups(conn, me.id)?;
+    Ok(unread_count)
+}
@@ -1,9 +1,10 @@
-use super::response::ProfileResponse;
+use super::response::{MultipleProfilesResponse, ProfileResponse};
 use super::service;
 use crate::error::AppError;
 use crate::middleware::auth::access_auth_user;
 use crate::middleware::state::AppState;
 use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
 
 type UsernameSlug = String;
 
@@ -34,7 +35,13 @@ pub async fn follow(
     let auth_user = access_auth_user(&req)?;
     let conn = state.get_conn()?;
     let username = path.into_inner();
-    let profile = auth_user.follow(&conn, &username)?;
+    let profile = service::follow(
+        &conn,
+        &service::FollowService {
+            me: auth_user,
+            username,
+        },
+    )?;
     let res = ProfileResponse::from(profile);
     Ok(HttpResponse::Ok().json(res))
 }
@@ -47,7 +54,37 @@ pub async fn unfollow(
     let auth_user = access_auth_user(&req)?;
     let conn = state.get_conn()?;
     let username = path.into_inner();
-    let profile = auth_user.unfollow(&conn, &username)?;
+    let profile = service::unfollow(
+        &conn,
+        &service::UnfollowService {
+            me: auth_user,
+            username,
+        },
+    )?;
     let res = ProfileResponse::from(profile);
     Ok(HttpResponse::Ok().json(res))
 }
+
+#[derive(Deserialize)]
+pub struct SuggestionsQueryParameter {
+    limit: Option<i64>,
+}
+
+pub async fn suggestions(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    params: web::Query<SuggestionsQueryParameter>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let limit = params.limit.unwrap_or(10).clamp(1, 50);
+    let profiles = service::fetch_suggestions(
+        &conn,
+        &service::FetchSuggestions {
+            me: auth_user,
+            limit,
+        },
+    )?;
+    let res = MultipleProfilesResponse::from(profiles);
+    Ok(HttpResponse::Ok().json(res))
+}
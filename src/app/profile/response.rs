@@ -14,14 +14,33 @@ pub struct ProfileContent {
     pub following: bool,
 }
 
-impl From<ProfileModel> for ProfileResponse {
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MultipleProfilesResponse {
+    pub profiles: Vec<ProfileContent>,
+}
+
+impl From<ProfileModel> for ProfileContent {
     fn from(profile_model: ProfileModel) -> Self {
-        let profile = ProfileContent {
+        ProfileContent {
             username: profile_model.username,
             bio: profile_model.bio,
             image: profile_model.image,
             following: profile_model.following,
-        };
+        }
+    }
+}
+
+impl From<Vec<ProfileModel>> for MultipleProfilesResponse {
+    fn from(list: Vec<ProfileModel>) -> Self {
+        MultipleProfilesResponse {
+            profiles: list.into_iter().map(ProfileContent::from).collect(),
+        }
+    }
+}
+
+impl From<ProfileModel> for ProfileResponse {
+    fn from(profile_model: ProfileModel) -> Self {
+        let profile = ProfileContent::from(profile_model);
         ProfileResponse { profile }
     }
 }
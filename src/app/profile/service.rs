@@ -1,6 +1,10 @@
 use super::model::Profile;
+use crate::app::follow::model::{DeleteFollow, Follow, NewFollow};
+use crate::app::notification::{self, service::NotificationEvent};
+use crate::app::stream::{self, hub::StreamEvent};
 use crate::app::user::model::User;
 use crate::error::AppError;
+use crate::utils::db;
 use diesel::pg::PgConnection;
 use uuid::Uuid;
 
@@ -17,46 +21,316 @@ pub fn fetch_by_name(
     let profile = fetch_profile_by_id(
         conn,
         &FetchProfileById {
-            user: me.to_owned(),
+            me: Some(me.to_owned()),
             id: followee.id,
         },
     )?;
     Ok(profile)
 }
 
+// Profile of the user `id` as seen by `me`; `following` is always false for anonymous readers.
 pub struct FetchProfileById {
-    pub user: User,
+    pub me: Option<User>,
     pub id: Uuid,
 }
 pub fn fetch_profile_by_id(
     conn: &PgConnection,
     params: &FetchProfileById,
 ) -> Result<Profile, AppError> {
-    let FetchProfileById { user, id } = params;
-    let is_following = user.is_following(conn, id);
+    let FetchProfileById { me, id } = params;
+    let user = User::find_by_id(conn, *id)?;
+    let is_following = me
+        .as_ref()
+        .map(|me| me.is_following(conn, id))
+        .unwrap_or(false);
     let profile = Profile {
-        username: user.username.to_owned(),
-        bio: user.bio.to_owned(),
-        image: user.image.to_owned(),
+        username: user.username,
+        bio: user.bio,
+        image: user.image,
         following: is_following,
     };
     Ok(profile)
 }
 
-pub struct ConverUserToProfile<'a> {
-    pub user: &'a User,
-    pub me: &'a Option<User>,
+pub struct FollowService {
+    pub me: User,
+    pub username: String,
 }
-pub fn conver_user_to_profile(conn: &PgConnection, params: &ConverUserToProfile) -> Profile {
-    let following = match params.me.as_ref() {
-        Some(me) => me.is_following(conn, &params.user.id),
-        None => false,
-    };
-    
-    Profile {
-        username: params.user.username.to_owned(),
-        bio: params.user.bio.to_owned(),
-        image: params.user.image.to_owned(),
-        following,
+pub fn follow(conn: &PgConnection, params: &FollowService) -> Result<Profile, AppError> {
+    db::unit_of_work(conn, || {
+        let followee = User::find_by_username(conn, &params.username)?;
+        Follow::create_follow(
+            conn,
+            &NewFollow {
+                follower_id: params.me.id,
+                followee_id: followee.id,
+            },
+        )?;
+        notification::service::notify(
+            conn,
+            NotificationEvent::Followed {
+                follower_id: params.me.id,
+                followee_id: followee.id,
+            },
+        )?;
+        stream::hub::publish(
+            conn,
+            StreamEvent::Follow {
+                follower_id: params.me.id,
+                followee_id: followee.id,
+                following: true,
+            },
+        )?;
+        #[cfg(test)]
+        db::fail_point("profile::follow::profile")?;
+        Ok(Profile {
+            username: followee.username,
+            bio: followee.bio,
+            image: followee.image,
+            following: true,
+        })
+    })
+}
+
+pub struct UnfollowService {
+    pub me: User,
+    pub username: String,
+}
+pub fn unfollow(conn: &PgConnection, params: &UnfollowService) -> Result<Profile, AppError> {
+    db::unit_of_work(conn, || {
+        let followee = User::find_by_username(conn, &params.username)?;
+        Follow::delete_follow(
+            conn,
+            &DeleteFollow {
+                follower_id: params.me.id,
+                followee_id: followee.id,
+            },
+        )?;
+        stream::hub::publish(
+            conn,
+            StreamEvent::Follow {
+                follower_id: params.me.id,
+                followee_id: followee.id,
+                following: false,
+            },
+        )?;
+        Ok(Profile {
+            username: followee.username,
+            bio: followee.bio,
+            image: followee.image,
+            following: false,
+        })
+    })
+}
+
+#[derive(QueryableByName)]
+struct SuggestionRow {
+    #[sql_type = "diesel::sql_types::Text"]
+    username: String,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Text>"]
+    bio: Option<String>,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Text>"]
+    image: Option<String>,
+}
+
+// Candidates are scored by three signals:
+// - co-follow: how many of the people `me` follows also follow the candidate
+// - tag overlap: how many distinct tags the candidate writes about that `me` has favorited
+// - activity: how many articles the candidate published in the last 30 days (capped)
+// Candidates without any published article and without a co-follow signal are not suggested.
+const SUGGESTIONS_QUERY: &str = r#"
+WITH my_follows AS (
+    SELECT followee_id FROM follows WHERE follower_id = $1
+),
+co_follows AS (
+    SELECT followee_id AS user_id, COUNT(*) AS score
+    FROM follows
+    WHERE follower_id IN (SELECT followee_id FROM my_follows)
+    GROUP BY followee_id
+),
+my_favorited_tags AS (
+    SELECT DISTINCT tags.name
+    FROM favorites
+    INNER JOIN tags ON tags.article_id = favorites.article_id
+    WHERE favorites.user_id = $1
+),
+tag_overlap AS (
+    SELECT articles.author_id AS user_id, COUNT(DISTINCT tags.name) AS score
+    FROM articles
+    INNER JOIN tags ON tags.article_id = articles.id
+    WHERE articles.status = 'published'
+      AND tags.name IN (SELECT name FROM my_favorited_tags)
+    GROUP BY articles.author_id
+),
+activity AS (
+    SELECT author_id AS user_id, COUNT(*) AS score
+    FROM articles
+    WHERE status = 'published'
+      AND published_at > NOW() - INTERVAL '30 days'
+    GROUP BY author_id
+)
+SELECT users.username, users.bio, users.image
+FROM users
+LEFT JOIN co_follows ON co_follows.user_id = users.id
+LEFT JOIN tag_overlap ON tag_overlap.user_id = users.id
+LEFT JOIN activity ON activity.user_id = users.id
+WHERE users.id <> $1
+  AND users.disabled_at IS NULL
+  AND users.id NOT IN (SELECT followee_id FROM my_follows)
+  AND (
+    co_follows.score IS NOT NULL
+    OR EXISTS (
+        SELECT 1 FROM articles
+        WHERE articles.author_id = users.id AND articles.status = 'published'
+    )
+  )
+ORDER BY
+    3 * COALESCE(co_follows.score, 0)
+    + 2 * COALESCE(tag_overlap.score, 0)
+    + LEAST(COALESCE(activity.score, 0), 5) DESC,
+    users.created_at DESC
+LIMIT $2
+"#;
+
+pub struct FetchSuggestions {
+    pub me: User,
+    pub limit: i64,
+}
+pub fn fetch_suggestions(
+    conn: &PgConnection,
+    params: &FetchSuggestions,
+) -> Result<Vec<Profile>, AppError> {
+    use diesel::prelude::*;
+    use diesel::sql_types::{BigInt, Uuid as SqlUuid};
+    let rows = diesel::sql_query(SUGGESTIONS_QUERY)
+        .bind::<SqlUuid, _>(params.me.id)
+        .bind::<BigInt, _>(params.limit)
+        .load::<SuggestionRow>(conn)?;
+
+    let profiles = rows
+        .into_iter()
+        .map(|row| Profile {
+            username: row.username,
+            bio: row.bio,
+            image: row.image,
+            following: false, // NOTE: already-followed authors are excluded by the query
+        })
+        .collect();
+    Ok(profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::article::model::Article;
+    use crate::app::article::service::{create, CreateArticleService};
+    use crate::app::favorite::model::{Favorite, FavorteAction};
+    use crate::utils::db::testing;
+
+    fn add_follow(conn: &PgConnection, follower: &User, followee: &User) {
+        Follow::create_follow(
+            conn,
+            &NewFollow {
+                follower_id: follower.id,
+                followee_id: followee.id,
+            },
+        )
+        .unwrap();
+    }
+
+    fn add_article(conn: &PgConnection, author: &User, tags: &[&str], status: &str) -> Article {
+        let (article, _, _, _) = create(
+            conn,
+            &CreateArticleService {
+                title: format!("{} writes", author.username),
+                description: "description".to_string(),
+                body: "body".to_string(),
+                tag_list: Some(tags.iter().map(|tag| tag.to_string()).collect()),
+                status: Some(status.to_string()),
+                publish_at: None,
+                me: author.to_owned(),
+            },
+        )
+        .unwrap();
+        article
+    }
+
+    #[test]
+    fn suggestions_rank_co_follows_and_favorited_tags() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let me = testing::insert_user(&conn, "sg_me");
+        let friend = testing::insert_user(&conn, "sg_friend");
+        let other_friend = testing::insert_user(&conn, "sg_other_friend");
+        let followed = testing::insert_user(&conn, "sg_followed");
+        let popular = testing::insert_user(&conn, "sg_popular");
+        let niche = testing::insert_user(&conn, "sg_niche");
+        let tagger = testing::insert_user(&conn, "sg_tagger");
+        let writer = testing::insert_user(&conn, "sg_writer");
+        let drafter = testing::insert_user(&conn, "sg_drafter");
+        for followee in [&friend, &other_friend, &followed] {
+            add_follow(&conn, &me, followee);
+        }
+        for followee in [&me, &followed, &popular, &niche] {
+            add_follow(&conn, &friend, followee);
+        }
+        for followee in [&me, &followed, &popular] {
+            add_follow(&conn, &other_friend, followee);
+        }
+        let favorited = add_article(&conn, &followed, &["rust", "web"], "published");
+        Favorite::favorite(
+            &conn,
+            &FavorteAction {
+                user_id: me.id,
+                article_id: favorited.id,
+            },
+        )
+        .unwrap();
+        add_article(&conn, &tagger, &["rust", "web"], "published");
+        add_article(&conn, &writer, &["go"], "published");
+        add_article(&conn, &drafter, &["rust", "web"], "draft");
+
+        let suggestions = fetch_suggestions(
+            &conn,
+            &FetchSuggestions {
+                me: me.to_owned(),
+                limit: 100,
+            },
+        )
+        .unwrap()
+        .into_iter()
+        .map(|profile| profile.username)
+        .filter(|username| username.starts_with("sg_"))
+        .collect::<Vec<_>>();
+        // popular: two co-follows; tagger: two favorited tags and a recent article;
+        // niche: one co-follow; writer: a recent article only. Drafts count for nothing.
+        assert_eq!(
+            vec!["sg_popular", "sg_tagger", "sg_niche", "sg_writer"],
+            suggestions
+        );
+    }
+
+    #[test]
+    fn follow_rolls_back_when_a_later_step_fails() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let me = testing::insert_user(&conn, "uow_follower");
+        let followee = testing::insert_user(&conn, "uow_followee");
+        {
+            let _armed = testing::arm_fail_point("profile::follow::profile");
+            let result = follow(
+                &conn,
+                &FollowService {
+                    me: me.to_owned(),
+                    username: followee.username.to_owned(),
+                },
+            );
+            assert!(result.is_err());
+        }
+        assert!(!me.is_following(&conn, &followee.id));
     }
 }
@@ -0,0 +1,102 @@
+use super::response::{MultipleRevisionsResponse, RevisionDiffResponse, SingleRevisionResponse};
+use super::service;
+use crate::app::article::response::SingleArticleResponse;
+use crate::error::AppError;
+use crate::middleware::auth;
+use crate::middleware::state::AppState;
+use crate::utils::uuid;
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+
+type ArticleIdSlug = String;
+type RevisionIdSlug = String;
+
+pub async fn index(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<ArticleIdSlug>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let article_title_slug = path.into_inner();
+    let list = service::fetch_revisions(
+        &conn,
+        &service::FetchRevisions {
+            me: auth_user,
+            article_title_slug,
+        },
+    )?;
+    let res = MultipleRevisionsResponse::from(list);
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn show(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(ArticleIdSlug, RevisionIdSlug)>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let (article_title_slug, revision_id) = path.into_inner();
+    let revision_id = uuid::parse(&revision_id)?;
+    let item = service::fetch_revision(
+        &conn,
+        &service::FetchRevision {
+            me: auth_user,
+            article_title_slug,
+            revision_id,
+        },
+    )?;
+    let res = SingleRevisionResponse::from(item);
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[derive(Deserialize)]
+pub struct DiffQueryParameter {
+    from: Option<String>,
+}
+
+pub async fn diff(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(ArticleIdSlug, RevisionIdSlug)>,
+    params: web::Query<DiffQueryParameter>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let (article_title_slug, revision_id) = path.into_inner();
+    let revision_id = uuid::parse(&revision_id)?;
+    let from_revision_id = params.from.as_deref().map(uuid::parse).transpose()?;
+    let diff = service::diff_revisions(
+        &conn,
+        &service::DiffRevisions {
+            me: auth_user,
+            article_title_slug,
+            revision_id,
+            from_revision_id,
+        },
+    )?;
+    let res = RevisionDiffResponse { diff };
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn restore(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(ArticleIdSlug, RevisionIdSlug)>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let (article_title_slug, revision_id) = path.into_inner();
+    let revision_id = uuid::parse(&revision_id)?;
+    let (article, profile, favorite_info, tag_list) = service::restore_revision(
+        &conn,
+        &service::RestoreRevision {
+            me: auth_user,
+            article_title_slug,
+            revision_id,
+        },
+    )?;
+    let res = SingleArticleResponse::from((article, profile, favorite_info, tag_list));
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -0,0 +1,269 @@
+use crate::app::article::model::Article;
+use crate::app::user::model::User;
+use crate::error::AppError;
+use crate::schema::article_revisions;
+use crate::utils::markdown;
+use chrono::NaiveDateTime;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+#[derive(Identifiable, Queryable, Associations, Debug, Serialize, Deserialize, Clone)]
+#[belongs_to(Article, foreign_key = "article_id")]
+#[belongs_to(User, foreign_key = "author_id")]
+#[table_name = "article_revisions"]
+pub struct Revision {
+    pub id: Uuid,
+    pub article_id: Uuid,
+    pub author_id: Uuid,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub body_html: Option<String>,
+    pub toc: Option<JsonValue>,
+}
+
+impl Revision {
+    // Snapshots the current content of `article` as edited by `_author_id`, together with
+    // its rendering, so that reads never have to write.
+    pub fn record(
+        conn: &PgConnection,
+        article: &Article,
+        _author_id: Uuid,
+    ) -> Result<Self, AppError> {
+        let rendered = markdown::render(&article.body);
+        let revision = diesel::insert_into(article_revisions::table)
+            .values(&NewRevision {
+                article_id: article.id,
+                author_id: _author_id,
+                title: &article.title,
+                description: &article.description,
+                body: &article.body,
+                body_html: &rendered.html,
+                toc: Self::toc_to_json(&rendered.toc)?,
+            })
+            .get_result::<Self>(conn)?;
+        Ok(revision)
+    }
+
+    fn toc_to_json(toc: &[markdown::TocEntry]) -> Result<JsonValue, AppError> {
+        serde_json::to_value(toc).map_err(|_| AppError::InternalServerError)
+    }
+
+    pub fn fetch_list_by_article_id(
+        conn: &PgConnection,
+        _article_id: Uuid,
+    ) -> Result<Vec<(Self, User)>, AppError> {
+        use crate::schema::users;
+        let list = article_revisions::table
+            .inner_join(users::table)
+            .filter(article_revisions::article_id.eq(_article_id))
+            .order((
+                article_revisions::created_at.desc(),
+                article_revisions::id.desc(),
+            ))
+            .load::<(Self, User)>(conn)?;
+        Ok(list)
+    }
+
+    pub fn fetch_by_id_and_article_id(
+        conn: &PgConnection,
+        _id: Uuid,
+        _article_id: Uuid,
+    ) -> Result<(Self, User), AppError> {
+        use crate::schema::users;
+        let item = article_revisions::table
+            .inner_join(users::table)
+            .filter(article_revisions::id.eq(_id))
+            .filter(article_revisions::article_id.eq(_article_id))
+            .first::<(Self, User)>(conn)?;
+        Ok(item)
+    }
+
+    // The newest revision of each article, i.e. the one matching its current content.
+    pub fn fetch_latest_by_article_ids(
+        conn: &PgConnection,
+        article_ids: &[Uuid],
+    ) -> Result<Vec<Self>, AppError> {
+        let list = article_revisions::table
+            .filter(article_revisions::article_id.eq_any(article_ids))
+            .distinct_on(article_revisions::article_id)
+            .order((
+                article_revisions::article_id,
+                article_revisions::created_at.desc(),
+                article_revisions::id.desc(),
+            ))
+            .load::<Self>(conn)?;
+        Ok(list)
+    }
+
+    // Revisions without a cached rendering, for `conduit reindex` to fill in.
+    pub fn fetch_unrendered(conn: &PgConnection, limit: i64) -> Result<Vec<Self>, AppError> {
+        let list = article_revisions::table
+            .filter(article_revisions::body_html.is_null())
+            .order(article_revisions::id)
+            .limit(limit)
+            .load::<Self>(conn)?;
+        Ok(list)
+    }
+
+    pub fn save_rendered(
+        conn: &PgConnection,
+        _id: Uuid,
+        rendered: &markdown::Rendered,
+    ) -> Result<(), AppError> {
+        let _ = diesel::update(article_revisions::table.find(_id))
+            .set((
+                article_revisions::body_html.eq(&rendered.html),
+                article_revisions::toc.eq(Self::toc_to_json(&rendered.toc)?),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    // The rendering cached when this revision was recorded. Revisions whose cache is missing
+    // are rendered on the fly until `conduit reindex` fills it in.
+    pub fn rendered(&self) -> markdown::Rendered {
+        let cached_toc = self
+            .toc
+            .clone()
+            .and_then(|toc| serde_json::from_value::<Vec<markdown::TocEntry>>(toc).ok());
+        match (&self.body_html, cached_toc) {
+            (Some(html), Some(toc)) => markdown::Rendered {
+                html: html.to_owned(),
+                toc,
+            },
+            _ => markdown::render(&self.body),
+        }
+    }
+
+    // The revision recorded right before `revision`, if any.
+    pub fn fetch_previous(conn: &PgConnection, revision: &Self) -> Result<Option<Self>, AppError> {
+        let item = article_revisions::table
+            .filter(article_revisions::article_id.eq(revision.article_id))
+            .filter(
+                article_revisions::created_at.lt(revision.created_at).or(
+                    article_revisions::created_at
+                        .eq(revision.created_at)
+                        .and(article_revisions::id.lt(revision.id)),
+                ),
+            )
+            .order((
+                article_revisions::created_at.desc(),
+                article_revisions::id.desc(),
+            ))
+            .first::<Self>(conn)
+            .optional()?;
+        Ok(item)
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "article_revisions"]
+pub struct NewRevision<'a> {
+    pub article_id: Uuid,
+    pub author_id: Uuid,
+    pub title: &'a str,
+    pub description: &'a str,
+    pub body: &'a str,
+    pub body_html: &'a str,
+    pub toc: JsonValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub op: LineOp,
+    pub line: String,
+}
+
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    use similar::{ChangeTag, TextDiff};
+    // NOTE: a missing trailing newline would otherwise make the last line look changed.
+    let with_trailing_newline = |text: &str| {
+        if text.is_empty() || text.ends_with('\n') {
+            text.to_string()
+        } else {
+            format!("{}\n", text)
+        }
+    };
+    let (old, new) = (with_trailing_newline(old), with_trailing_newline(new));
+    TextDiff::from_lines(&old, &new)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            op: match change.tag() {
+                ChangeTag::Equal => LineOp::Equal,
+                ChangeTag::Insert => LineOp::Insert,
+                ChangeTag::Delete => LineOp::Delete,
+            },
+            line: change.value().trim_end_matches('\n').to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::article::service::{create, CreateArticleService};
+    use crate::utils::db::testing;
+
+    #[test]
+    fn revisions_are_rendered_when_recorded() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let me = testing::insert_user(&conn, "render_author");
+        let (article, _, _, _) = create(
+            &conn,
+            &CreateArticleService {
+                title: "Rendered".to_string(),
+                description: "description".to_string(),
+                body: "# Intro\n\nbody".to_string(),
+                tag_list: None,
+                status: None,
+                publish_at: None,
+                me,
+            },
+        )
+        .unwrap();
+        let revision = Revision::fetch_latest_by_article_ids(&conn, &[article.id])
+            .unwrap()
+            .remove(0);
+        assert_eq!(
+            Some(markdown::render(&article.body).html),
+            revision.body_html
+        );
+        assert_eq!("user-content-intro", revision.rendered().toc[0].id);
+    }
+
+    #[test]
+    fn diff_lines_marks_changed_lines() {
+        let diff = diff_lines("a\nb\nc", "a\nB\nc\nd\n");
+        let ops = diff
+            .iter()
+            .map(|item| (item.op, item.line.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (LineOp::Equal, "a"),
+                (LineOp::Delete, "b"),
+                (LineOp::Insert, "B"),
+                (LineOp::Equal, "c"),
+                (LineOp::Insert, "d"),
+            ],
+            ops
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use super::model::{DiffLine, Revision};
+use crate::app::user::model::User;
+use crate::utils::date::Iso8601;
+use serde::{Deserialize, Serialize};
+use std::convert::From;
+use uuid::Uuid;
+
+#[derive(Deserialize, Serialize)]
+pub struct SingleRevisionResponse {
+    pub revision: RevisionContent,
+}
+
+impl From<(Revision, User)> for SingleRevisionResponse {
+    fn from(item: (Revision, User)) -> Self {
+        Self {
+            revision: RevisionContent::from(item),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultipleRevisionsResponse {
+    pub revisions: Vec<RevisionContent>,
+    pub revisions_count: i64,
+}
+
+impl From<Vec<(Revision, User)>> for MultipleRevisionsResponse {
+    fn from(list: Vec<(Revision, User)>) -> Self {
+        let revisions = list
+            .into_iter()
+            .map(RevisionContent::from)
+            .collect::<Vec<_>>();
+        Self {
+            revisions_count: revisions.len() as i64,
+            revisions,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionContent {
+    pub id: Uuid,
+    pub title: String,
+    pub description: String,
+    pub body: String,
+    pub created_at: Iso8601,
+    pub author: RevisionAuthor,
+}
+
+impl From<(Revision, User)> for RevisionContent {
+    fn from((revision, user): (Revision, User)) -> Self {
+        Self {
+            id: revision.id,
+            title: revision.title,
+            description: revision.description,
+            body: revision.body,
+            created_at: Iso8601(revision.created_at),
+            author: RevisionAuthor {
+                username: user.username,
+                bio: user.bio,
+                image: user.image,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RevisionAuthor {
+    pub username: String,
+    pub bio: Option<String>,
+    pub image: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RevisionDiffResponse {
+    pub diff: RevisionDiff,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RevisionDiff {
+    pub from: Option<Uuid>,
+    pub to: Uuid,
+    pub title: Vec<DiffLine>,
+    pub description: Vec<DiffLine>,
+    pub body: Vec<DiffLine>,
+}
@@ -0,0 +1,127 @@
+use super::model::{diff_lines, Revision};
+use super::response::RevisionDiff;
+use crate::app::article::model::Article;
+use crate::app::article::service::{update_article, UpdateArticleService};
+use crate::app::favorite::model::FavoriteInfo;
+use crate::app::profile::model::Profile;
+use crate::app::tag::model::Tag;
+use crate::app::user::model::User;
+use crate::error::AppError;
+use crate::utils::db;
+use diesel::pg::PgConnection;
+use serde_json::json;
+use uuid::Uuid;
+
+// Revisions are readable by whoever can read the article itself.
+fn fetch_readable_article(
+    conn: &PgConnection,
+    article_title_slug: &str,
+    me: &User,
+) -> Result<Article, AppError> {
+    let article = Article::fetch_by_slug(conn, article_title_slug)?;
+    if !article.is_published() && article.author_id != me.id {
+        return Err(AppError::NotFound(
+            json!({ "error": "requested record was not found" }),
+        ));
+    }
+    Ok(article)
+}
+
+pub struct FetchRevisions {
+    pub me: User,
+    pub article_title_slug: String,
+}
+pub fn fetch_revisions(
+    conn: &PgConnection,
+    params: &FetchRevisions,
+) -> Result<Vec<(Revision, User)>, AppError> {
+    let article = fetch_readable_article(conn, &params.article_title_slug, &params.me)?;
+    Revision::fetch_list_by_article_id(conn, article.id)
+}
+
+pub struct FetchRevision {
+    pub me: User,
+    pub article_title_slug: String,
+    pub revision_id: Uuid,
+}
+pub fn fetch_revision(
+    conn: &PgConnection,
+    params: &FetchRevision,
+) -> Result<(Revision, User), AppError> {
+    let article = fetch_readable_article(conn, &params.article_title_slug, &params.me)?;
+    Revision::fetch_by_id_and_article_id(conn, params.revision_id, article.id)
+}
+
+pub struct DiffRevisions {
+    pub me: User,
+    pub article_title_slug: String,
+    pub revision_id: Uuid,
+    // Defaults to the revision recorded right before `revision_id`.
+    pub from_revision_id: Option<Uuid>,
+}
+pub fn diff_revisions(
+    conn: &PgConnection,
+    params: &DiffRevisions,
+) -> Result<RevisionDiff, AppError> {
+    let article = fetch_readable_article(conn, &params.article_title_slug, &params.me)?;
+    let (to, _) = Revision::fetch_by_id_and_article_id(conn, params.revision_id, article.id)?;
+    let from = match params.from_revision_id {
+        Some(from_id) => Some(Revision::fetch_by_id_and_article_id(conn, from_id, article.id)?.0),
+        None => Revision::fetch_previous(conn, &to)?,
+    };
+
+    let (from_id, from_title, from_description, from_body) = match &from {
+        Some(from) => (
+            Some(from.id),
+            from.title.as_str(),
+            from.description.as_str(),
+            from.body.as_str(),
+        ),
+        None => (None, "", "", ""),
+    };
+    Ok(RevisionDiff {
+        from: from_id,
+        to: to.id,
+        title: diff_lines(from_title, &to.title),
+        description: diff_lines(from_description, &to.description),
+        body: diff_lines(from_body, &to.body),
+    })
+}
+
+pub struct RestoreRevision {
+    pub me: User,
+    pub article_title_slug: String,
+    pub revision_id: Uuid,
+}
+pub fn restore_revision(
+    conn: &PgConnection,
+    params: &RestoreRevision,
+) -> Result<(Article, Profile, FavoriteInfo, Vec<Tag>), AppError> {
+    db::unit_of_work(conn, || {
+        let article = Article::fetch_by_slug(conn, &params.article_title_slug)?;
+        if article.author_id != params.me.id {
+            return Err(AppError::Forbidden(
+                json!({ "error": "only the author can restore a revision" }),
+            ));
+        }
+        let (revision, _) =
+            Revision::fetch_by_id_and_article_id(conn, params.revision_id, article.id)?;
+
+        // NOTE: restoring is a regular edit, so it records a new revision on top.
+        update_article(
+            conn,
+            &UpdateArticleService {
+                me: params.me.to_owned(),
+                article_title_slug: params.article_title_slug.to_owned(),
+                title: Some(revision.title),
+                description: Some(revision.description),
+                body: Some(revision.body),
+                status: None,
+                publish_at: None,
+                tag_list: None,
+                add_tags: None,
+                remove_tags: None,
+            },
+        )
+    })
+}
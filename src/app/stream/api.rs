@@ -0,0 +1,162 @@
+use super::hub::{self, Subscription};
+use crate::app::follow::model::Follow;
+use crate::error::AppError;
+use crate::middleware::auth;
+use crate::middleware::state::AppState;
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::web::Bytes;
+use actix_web::{rt, web, HttpRequest, HttpResponse};
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{interval_at, Instant, Interval};
+
+// How often an idle connection gets a keep-alive so proxies don't close it.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Deserialize)]
+pub struct StreamQueryParameter {
+    // Comma-separated slugs of the articles the client is showing.
+    articles: Option<String>,
+}
+
+// Call after subscribing to the hub so no follow made in between is missed.
+fn subscription_for(
+    state: &AppState,
+    req: &HttpRequest,
+    articles: &Option<String>,
+) -> Result<Subscription, AppError> {
+    let auth_user = auth::access_auth_user(req)?;
+    let conn = state.get_conn()?;
+    let followee_ids = Follow::fetch_all_followee_ids(&conn, auth_user.id)?;
+    let articles = articles
+        .iter()
+        .flat_map(|articles| articles.split(','))
+        .map(str::trim)
+        .filter(|slug| !slug.is_empty())
+        .map(str::to_string)
+        .collect();
+    Ok(Subscription {
+        user_id: auth_user.id,
+        articles,
+        followee_ids,
+    })
+}
+
+fn keep_alive() -> Interval {
+    interval_at(Instant::now() + KEEP_ALIVE, KEEP_ALIVE)
+}
+
+// Server-Sent Events. Each event is named after its type and carries a JSON `data` line.
+pub async fn index(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    params: web::Query<StreamQueryParameter>,
+) -> Result<HttpResponse, AppError> {
+    let receiver = hub::hub().subscribe();
+    let subscription = subscription_for(&state, &req, &params.articles)?;
+    let events = futures::stream::unfold(
+        (subscription, receiver, keep_alive()),
+        |(mut subscription, mut receiver, mut keep_alive)| async move {
+            let chunk = loop {
+                tokio::select! {
+                    _ = keep_alive.tick() => break Some(": keep-alive\n\n".to_string()),
+                    received = receiver.recv() => match received {
+                        Ok(event) => {
+                            if subscription.accepts(&event) {
+                                break Some(format!("event: {}\ndata: {}\n\n", event.name(), event.data()));
+                            }
+                        }
+                        // The client missed events and should refetch what it shows.
+                        Err(RecvError::Lagged(missed)) => {
+                            break Some(format!("event: lagged\ndata: {}\n\n", json!({ "missed": missed })));
+                        }
+                        Err(RecvError::Closed) => break None,
+                    },
+                }
+            };
+            chunk.map(|chunk| {
+                let chunk = Ok::<_, AppError>(Bytes::from(chunk));
+                (chunk, (subscription, receiver, keep_alive))
+            })
+        },
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .streaming(events))
+}
+
+// Messages a WebSocket client sends to follow the articles it shows as they change.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum ClientMessage {
+    Subscribe(Vec<String>),
+    Unsubscribe(Vec<String>),
+}
+
+// The same events over a WebSocket, as `{"event": ..., "data": ...}` text messages.
+pub async fn socket(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    params: web::Query<StreamQueryParameter>,
+    body: web::Payload,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut receiver = hub::hub().subscribe();
+    let mut subscription = subscription_for(&state, &req, &params.articles)?;
+    let (res, mut session, mut messages) = actix_ws::handle(&req, body)?;
+    rt::spawn(async move {
+        let mut keep_alive = keep_alive();
+        loop {
+            tokio::select! {
+                _ = keep_alive.tick() => {
+                    if session.ping(b"").await.is_err() {
+                        break;
+                    }
+                }
+                message = messages.next() => match message {
+                    Some(Ok(actix_ws::Message::Text(text))) => {
+                        match serde_json::from_str::<ClientMessage>(&text) {
+                            Ok(ClientMessage::Subscribe(slugs)) => subscription.articles.extend(slugs),
+                            Ok(ClientMessage::Unsubscribe(slugs)) => {
+                                for slug in slugs {
+                                    subscription.articles.remove(&slug);
+                                }
+                            }
+                            Err(err) => warn!("ignoring unknown stream message: {}", err),
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(_))) | None => break,
+                    Some(Ok(_)) => {}
+                    Some(Err(_err)) => break,
+                },
+                received = receiver.recv() => {
+                    let text = match received {
+                        Ok(event) => {
+                            if !subscription.accepts(&event) {
+                                continue;
+                            }
+                            json!({ "event": event.name(), "data": event.data() })
+                        }
+                        Err(RecvError::Lagged(missed)) => {
+                            json!({ "event": "lagged", "data": { "missed": missed } })
+                        }
+                        Err(RecvError::Closed) => break,
+                    };
+                    if session.text(text.to_string()).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        let _ = session.close(None).await;
+    });
+    Ok(res)
+}
@@ -0,0 +1,51 @@
+use super::hub::{self, StreamEvent, CHANNEL};
+use actix_web::rt;
+use futures::StreamExt;
+use std::time::Duration;
+use tokio_postgres::{AsyncMessage, NoTls};
+
+// How long to wait before listening again after the connection drops.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// Relays events NOTIFY'd by any server instance to this instance's hub. Runs for the
+// lifetime of the server and reconnects when the connection is lost.
+pub fn spawn(database_url: String) {
+    rt::spawn(async move {
+        loop {
+            if let Err(err) = listen(&database_url).await {
+                error!("stream bridge lost its connection: {}", err);
+            }
+            rt::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+async fn listen(database_url: &str) -> Result<(), tokio_postgres::Error> {
+    let (client, mut connection) = tokio_postgres::connect(database_url, NoTls).await?;
+    let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+    // The connection only makes progress while its messages are polled, so LISTEN runs
+    // alongside the relay loop.
+    let query = format!("LISTEN {}", CHANNEL);
+    let mut subscribing = Box::pin(client.batch_execute(&query));
+    let mut is_subscribed = false;
+    loop {
+        tokio::select! {
+            result = &mut subscribing, if !is_subscribed => {
+                result?;
+                is_subscribed = true;
+                info!("stream bridge is listening on {}", CHANNEL);
+            }
+            message = messages.next() => match message {
+                Some(Ok(AsyncMessage::Notification(notification))) => {
+                    match serde_json::from_str::<StreamEvent>(notification.payload()) {
+                        Ok(event) => hub::hub().dispatch(event),
+                        Err(err) => warn!("ignoring malformed stream event: {}", err),
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err),
+                None => return Ok(()),
+            }
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::error::AppError;
+use crate::utils::db;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+// Postgres channel the bridge publishes on and listens to.
+pub const CHANNEL: &str = "conduit_stream";
+
+// How many events a slow subscriber may fall behind before it starts missing them.
+const CAPACITY: usize = 256;
+
+// Something connected clients may want to hear about. Events are hints: they carry just
+// enough for a client to decide what to refetch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum StreamEvent {
+    Comment {
+        article_slug: String,
+        comment_id: Uuid,
+    },
+    Favorites {
+        article_slug: String,
+        favorites_count: i64,
+    },
+    Feed {
+        article_slug: String,
+        author_id: Uuid,
+    },
+    // Keeps subscriptions of the follower up to date; never sent to clients.
+    Follow {
+        follower_id: Uuid,
+        followee_id: Uuid,
+        following: bool,
+    },
+    Notification {
+        user_id: Uuid,
+        kind: String,
+    },
+}
+
+impl StreamEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StreamEvent::Comment { .. } => "comment",
+            StreamEvent::Favorites { .. } => "favorites",
+            StreamEvent::Feed { .. } => "feed",
+            StreamEvent::Follow { .. } => "follow",
+            StreamEvent::Notification { .. } => "notification",
+        }
+    }
+
+    // What the client gets to see. Internal ids stay on the server.
+    pub fn data(&self) -> Value {
+        match self {
+            StreamEvent::Comment {
+                article_slug,
+                comment_id,
+            } => json!({ "article": article_slug, "commentId": comment_id }),
+            StreamEvent::Favorites {
+                article_slug,
+                favorites_count,
+            } => json!({ "article": article_slug, "favoritesCount": favorites_count }),
+            StreamEvent::Feed { article_slug, .. } => json!({ "article": article_slug }),
+            StreamEvent::Follow { .. } => json!({}),
+            StreamEvent::Notification { kind, .. } => json!({ "kind": kind }),
+        }
+    }
+}
+
+// Which events one connection receives.
+pub struct Subscription {
+    pub user_id: Uuid,
+    // Slugs of the articles the client is showing.
+    pub articles: HashSet<String>,
+    // Authors whose new articles reach the client. Loaded once when the client connects
+    // and kept current from `Follow` events, so deciding never takes a query.
+    pub followee_ids: HashSet<Uuid>,
+}
+
+impl Subscription {
+    pub fn accepts(&mut self, event: &StreamEvent) -> bool {
+        match event {
+            StreamEvent::Comment { article_slug, .. }
+            | StreamEvent::Favorites { article_slug, .. } => self.articles.contains(article_slug),
+            StreamEvent::Feed { author_id, .. } => self.followee_ids.contains(author_id),
+            StreamEvent::Follow {
+                follower_id,
+                followee_id,
+                following,
+            } => {
+                if *follower_id == self.user_id {
+                    if *following {
+                        self.followee_ids.insert(*followee_id);
+                    } else {
+                        self.followee_ids.remove(followee_id);
+                    }
+                }
+                false
+            }
+            StreamEvent::Notification { user_id, .. } => *user_id == self.user_id,
+        }
+    }
+}
+
+// Fans events out to every subscriber in this process. With the Postgres bridge on, events
+// go through NOTIFY instead and come back to every instance's hub from the listener.
+pub struct Hub {
+    sender: broadcast::Sender<StreamEvent>,
+    bridged: bool,
+}
+
+static HUB: OnceLock<Hub> = OnceLock::new();
+
+impl Hub {
+    fn new(bridged: bool) -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        Self { sender, bridged }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StreamEvent> {
+        self.sender.subscribe()
+    }
+
+    // Hands the event to local subscribers. Nobody listening is fine.
+    pub fn dispatch(&self, event: StreamEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn is_bridged(&self) -> bool {
+        self.bridged
+    }
+}
+
+// Sets up the process-wide hub. Call it once at startup, before serving; without it the hub
+// is local only.
+pub fn install(bridged: bool) -> &'static Hub {
+    if HUB.set(Hub::new(bridged)).is_err() {
+        warn!("stream hub was already set up");
+    }
+    hub()
+}
+
+pub fn hub() -> &'static Hub {
+    HUB.get_or_init(|| Hub::new(false))
+}
+
+// Publishes from inside a service. Either way the event only goes out once the change is
+// committed, and never if it is rolled back: when bridged, the NOTIFY joins the caller's
+// transaction; otherwise the dispatch waits for the unit of work to commit.
+pub fn publish(conn: &PgConnection, event: StreamEvent) -> Result<(), AppError> {
+    let hub = hub();
+    if !hub.is_bridged() {
+        db::after_commit(move || hub.dispatch(event));
+        return Ok(());
+    }
+    let payload = serde_json::to_string(&event).map_err(|_err| AppError::InternalServerError)?;
+    let _ = diesel::sql_query("SELECT pg_notify($1, $2)")
+        .bind::<Text, _>(CHANNEL)
+        .bind::<Text, _>(payload)
+        .execute(conn)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscriptions_receive_their_articles_and_notifications() {
+        let me = Uuid::new_v4();
+        let mut subscription = Subscription {
+            user_id: me,
+            articles: ["watched".to_string()].into_iter().collect(),
+            followee_ids: HashSet::new(),
+        };
+        let comment_on = |slug: &str| StreamEvent::Comment {
+            article_slug: slug.to_string(),
+            comment_id: Uuid::new_v4(),
+        };
+        let notification_for = |user_id| StreamEvent::Notification {
+            user_id,
+            kind: "follow".to_string(),
+        };
+        assert!(subscription.accepts(&comment_on("watched")));
+        assert!(!subscription.accepts(&comment_on("other")));
+        assert!(subscription.accepts(&notification_for(me)));
+        assert!(!subscription.accepts(&notification_for(Uuid::new_v4())));
+    }
+
+    #[test]
+    fn feed_events_follow_the_subscribers_follows() {
+        let me = Uuid::new_v4();
+        let author_id = Uuid::new_v4();
+        let mut subscription = Subscription {
+            user_id: me,
+            articles: HashSet::new(),
+            followee_ids: HashSet::new(),
+        };
+        let published = StreamEvent::Feed {
+            article_slug: "new".to_string(),
+            author_id,
+        };
+        let follow = |follower_id, following| StreamEvent::Follow {
+            follower_id,
+            followee_id: author_id,
+            following,
+        };
+        assert!(!subscription.accepts(&published));
+        assert!(!subscription.accepts(&follow(Uuid::new_v4(), true)));
+        assert!(!subscription.accepts(&published));
+        assert!(!subscription.accepts(&follow(me, true)));
+        assert!(subscription.accepts(&published));
+        assert!(!subscription.accepts(&follow(me, false)));
+        assert!(!subscription.accepts(&published));
+    }
+
+    #[test]
+    fn events_survive_the_bridge_but_hide_ids_from_clients() {
+        let author_id = Uuid::new_v4();
+        let event = StreamEvent::Feed {
+            article_slug: "new".to_string(),
+            author_id,
+        };
+        let payload = serde_json::to_string(&event).unwrap();
+        assert_eq!(event, serde_json::from_str(&payload).unwrap());
+        assert_eq!(json!({ "article": "new" }), event.data());
+    }
+}
@@ -1,8 +1,12 @@
 extern crate serde_json;
-use super::model::Tag;
-use super::response::TagsResponse;
+use super::model::{DeleteTagFollow, NewTagFollow, Tag, TagFollow};
+use super::response::{TagResponse, TagsResponse};
+use crate::middleware::auth;
 use crate::{error::AppError, middleware::state::AppState};
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde_json::json;
+
+type TagNameSlug = String;
 
 pub async fn index(state: web::Data<AppState>) -> Result<HttpResponse, AppError> {
     let conn = state.get_conn()?;
@@ -10,3 +14,44 @@ pub async fn index(state: web::Data<AppState>) -> Result<HttpResponse, AppError>
     let res = TagsResponse::from(list);
     Ok(HttpResponse::Ok().json(res))
 }
+
+pub async fn follow(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<TagNameSlug>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let tag_name = path.into_inner();
+    if !Tag::exists_by_name(&conn, &tag_name)? {
+        return Err(AppError::NotFound(json!({"error": "tag was not found"})));
+    }
+    TagFollow::create(
+        &conn,
+        &NewTagFollow {
+            user_id: auth_user.id,
+            tag_name: tag_name.clone(),
+        },
+    )?;
+    let res = TagResponse::from((tag_name, true));
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn unfollow(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<TagNameSlug>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let tag_name = path.into_inner();
+    TagFollow::delete(
+        &conn,
+        &DeleteTagFollow {
+            user_id: auth_user.id,
+            tag_name: tag_name.clone(),
+        },
+    )?;
+    let res = TagResponse::from((tag_name, false));
+    Ok(HttpResponse::Ok().json(res))
+}
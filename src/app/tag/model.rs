@@ -1,6 +1,7 @@
-use crate::app::article::model::Article;
+use crate::app::article::model::{Article, ArticleStatus};
+use crate::app::user::model::User;
 use crate::error::AppError;
-use crate::schema::tags;
+use crate::schema::{tag_follows, tags};
 use chrono::NaiveDateTime;
 use diesel::pg::PgConnection;
 use diesel::Insertable;
@@ -36,8 +37,13 @@ impl Tag {
     pub fn fetch_list(conn: &PgConnection) -> Result<Vec<Self>, AppError> {
         use crate::schema;
         use diesel::prelude::*;
+        use schema::articles;
         use schema::tags::dsl::*;
-        let list = tags.load::<Self>(conn)?;
+        let list = tags
+            .inner_join(articles::table)
+            .filter(articles::status.eq(ArticleStatus::Published.as_str()))
+            .select(tags::all_columns())
+            .load::<Self>(conn)?;
         Ok(list)
     }
 
@@ -50,6 +56,79 @@ impl Tag {
 
         Ok(tags_list)
     }
+
+    // Adds the given names to the article, skipping the ones it already has.
+    pub fn add_list(
+        conn: &PgConnection,
+        _article_id: Uuid,
+        names: &[String],
+    ) -> Result<(), AppError> {
+        use crate::schema::tags::dsl::*;
+        let records = names
+            .iter()
+            .map(|_name| NewTag {
+                name: _name,
+                article_id: &_article_id,
+            })
+            .collect::<Vec<_>>();
+        let _ = diesel::insert_into(tags)
+            .values(records)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn delete_list(
+        conn: &PgConnection,
+        _article_id: Uuid,
+        names: &[String],
+    ) -> Result<(), AppError> {
+        use crate::schema::tags::dsl::*;
+        let _ = diesel::delete(
+            tags.filter(article_id.eq(_article_id))
+                .filter(name.eq_any(names)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn replace_list(
+        conn: &PgConnection,
+        _article_id: Uuid,
+        names: &[String],
+    ) -> Result<(), AppError> {
+        use crate::schema::tags::dsl::*;
+        let _ = diesel::delete(
+            tags.filter(article_id.eq(_article_id))
+                .filter(diesel::dsl::not(name.eq_any(names))),
+        )
+        .execute(conn)?;
+        Self::add_list(conn, _article_id, names)
+    }
+
+    // Trims names, drops empty ones and removes duplicates while keeping the given order.
+    pub fn normalize_names(names: &[String]) -> Vec<String> {
+        let mut normalized: Vec<String> = vec![];
+        for _name in names.iter().map(|_name| _name.trim()) {
+            if !_name.is_empty() && !normalized.iter().any(|seen| seen == _name) {
+                normalized.push(_name.to_owned());
+            }
+        }
+        normalized
+    }
+
+    // Whether any published article carries the tag; tags only used on drafts stay private.
+    pub fn exists_by_name(conn: &PgConnection, _name: &str) -> Result<bool, AppError> {
+        use crate::schema::articles;
+        use crate::schema::tags::dsl::*;
+        let exists = diesel::select(diesel::dsl::exists(
+            tags.inner_join(articles::table)
+                .filter(name.eq(_name))
+                .filter(articles::status.eq(ArticleStatus::Published.as_str())),
+        ))
+        .get_result::<bool>(conn)?;
+        Ok(exists)
+    }
 }
 
 #[derive(Insertable)]
@@ -58,3 +137,64 @@ pub struct NewTag<'a> {
     pub name: &'a str,
     pub article_id: &'a Uuid,
 }
+
+#[derive(Queryable, Associations, Clone, Serialize, Deserialize)]
+#[belongs_to(User, foreign_key = "user_id")]
+#[table_name = "tag_follows"]
+pub struct TagFollow {
+    pub user_id: Uuid,
+    pub tag_name: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl TagFollow {
+    pub fn create(conn: &PgConnection, params: &NewTagFollow) -> Result<(), AppError> {
+        let _ = diesel::insert_into(tag_follows::table)
+            .values(params)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn delete(conn: &PgConnection, params: &DeleteTagFollow) -> Result<(), AppError> {
+        let _ = diesel::delete(
+            tag_follows::table
+                .filter(tag_follows::user_id.eq(params.user_id))
+                .filter(tag_follows::tag_name.eq(&params.tag_name)),
+        )
+        .execute(conn)?;
+        Ok(())
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "tag_follows"]
+pub struct NewTagFollow {
+    pub user_id: Uuid,
+    pub tag_name: String,
+}
+
+pub struct DeleteTagFollow {
+    pub user_id: Uuid,
+    pub tag_name: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_names_trims_and_dedupes() {
+        let names = vec![
+            " rust ".to_string(),
+            "".to_string(),
+            "web".to_string(),
+            "rust".to_string(),
+        ];
+        assert_eq!(
+            vec!["rust".to_string(), "web".to_string()],
+            Tag::normalize_names(&names)
+        );
+    }
+}
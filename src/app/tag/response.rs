@@ -13,3 +13,22 @@ impl std::convert::From<Vec<Tag>> for TagsResponse {
         TagsResponse { tags: list }
     }
 }
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TagResponse {
+    pub tag: TagContent,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TagContent {
+    pub name: String,
+    pub following: bool,
+}
+
+impl std::convert::From<(String, bool)> for TagResponse {
+    fn from((name, following): (String, bool)) -> Self {
+        TagResponse {
+            tag: TagContent { name, following },
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use super::model::{UpdatableUser, User};
-use super::{request, response::UserResponse};
+use super::{request, response::UserResponse, service};
 use crate::error::AppError;
 use crate::middleware::auth;
 use crate::middleware::state::AppState;
@@ -10,7 +10,12 @@ pub async fn signin(
     form: web::Json<request::Signin>,
 ) -> Result<HttpResponse, AppError> {
     let conn = state.get_conn()?;
-    let (user, token) = User::signin(&conn, &form.user.email, &form.user.password)?;
+    let (user, token) = User::signin(
+        &conn,
+        &form.user.email,
+        &form.user.password,
+        state.config.auth.token_ttl(),
+    )?;
     let res = UserResponse::from((user, token));
     Ok(HttpResponse::Ok().json(res))
 }
@@ -20,19 +25,22 @@ pub async fn signup(
     form: web::Json<request::Signup>,
 ) -> Result<HttpResponse, AppError> {
     let conn = state.get_conn()?;
-    let (user, token) = User::signup(
+    let (user, token) = service::signup(
         &conn,
-        &form.user.email,
-        &form.user.username,
-        &form.user.password,
+        &service::SignupService {
+            email: form.user.email.to_owned(),
+            username: form.user.username.to_owned(),
+            password: form.user.password.to_owned(),
+            token_ttl: state.config.auth.token_ttl(),
+        },
     )?;
     let res = UserResponse::from((user, token));
     Ok(HttpResponse::Ok().json(res))
 }
 
-pub async fn me(req: HttpRequest) -> Result<HttpResponse, AppError> {
+pub async fn me(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
     let user = auth::access_auth_user(&req)?;
-    let token = user.generate_token()?;
+    let token = user.generate_token(state.config.auth.token_ttl())?;
     let res = UserResponse::from((user, token));
     Ok(HttpResponse::Ok().json(res))
 }
@@ -55,7 +63,7 @@ pub async fn update(
             bio: form.user.bio.clone(),
         },
     )?;
-    let token = &user.generate_token()?;
+    let token = &user.generate_token(state.config.auth.token_ttl())?;
     let res = UserResponse::from((user, token.to_string()));
     Ok(HttpResponse::Ok().json(res))
 }
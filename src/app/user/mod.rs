@@ -1,4 +1,5 @@
 pub mod api;
 pub mod model;
 pub mod request;
-pub mod response;
\ No newline at end of file
+pub mod response;
+pub mod service;
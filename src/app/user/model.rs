@@ -1,5 +1,4 @@
-use crate::app::follow::model::{DeleteFollow, Follow, NewFollow};
-use crate::app::profile::model::Profile;
+use crate::app::follow::model::Follow;
 use crate::error::AppError;
 use crate::schema::users;
 use crate::schema::users::dsl::*;
@@ -10,6 +9,7 @@ use chrono::NaiveDateTime;
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use uuid::Uuid;
 
 #[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, Clone, Associations)]
@@ -23,6 +23,23 @@ pub struct User {
     pub image: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub role: String,
+    pub disabled_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    User,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Admin => "admin",
+        }
+    }
 }
 
 type Token = String;
@@ -33,6 +50,7 @@ impl User {
         _email: &'a str,
         _username: &'a str,
         naive_password: &'a str,
+        token_ttl: chrono::Duration,
     ) -> Result<(User, Token), AppError> {
         use diesel::prelude::*;
         let hashed_password = hasher::hash_password(naive_password)?;
@@ -47,7 +65,7 @@ impl User {
             .values(&record)
             .get_result::<User>(conn)?;
 
-        let token = user.generate_token()?;
+        let token = user.generate_token(token_ttl)?;
         Ok((user, token))
     }
 
@@ -55,13 +73,19 @@ impl User {
         conn: &PgConnection,
         _email: &str,
         naive_password: &str,
+        token_ttl: chrono::Duration,
     ) -> Result<(User, Token), AppError> {
         let user = users
             .filter(email.eq(_email))
             .limit(1)
             .first::<User>(conn)?;
-        let _ = hasher::verify(&naive_password, &user.password)?;
-        let token = user.generate_token()?;
+        if !hasher::verify(naive_password, &user.password)? {
+            return Err(AppError::Unauthorized(json!({
+                "error": "Email or password is invalid"
+            })));
+        }
+        user.check_enabled()?;
+        let token = user.generate_token(token_ttl)?;
         Ok((user, token))
     }
 
@@ -82,6 +106,35 @@ impl User {
         Ok(user)
     }
 
+    pub fn set_role(conn: &PgConnection, user_id: Uuid, _role: Role) -> Result<Self, AppError> {
+        let user = diesel::update(users.filter(id.eq(user_id)))
+            .set((role.eq(_role.as_str()), updated_at.eq(Utc::now().naive_utc())))
+            .get_result::<User>(conn)?;
+        Ok(user)
+    }
+
+    // None enables the user again.
+    pub fn set_disabled_at(
+        conn: &PgConnection,
+        user_id: Uuid,
+        _disabled_at: Option<NaiveDateTime>,
+    ) -> Result<Self, AppError> {
+        let user = diesel::update(users.filter(id.eq(user_id)))
+            .set(disabled_at.eq(_disabled_at))
+            .get_result::<User>(conn)?;
+        Ok(user)
+    }
+
+    // Disabled users can neither sign in nor use the tokens they already have.
+    pub fn check_enabled(&self) -> Result<(), AppError> {
+        match self.disabled_at {
+            Some(_) => Err(AppError::Unauthorized(json!({
+                "error": "This account is disabled"
+            }))),
+            None => Ok(()),
+        }
+    }
+
     pub fn find_by_username(conn: &PgConnection, _username: &str) -> Result<Self, AppError> {
         let user = users
             .filter(username.eq(_username))
@@ -90,44 +143,6 @@ impl User {
         Ok(user)
     }
 
-    pub fn follow(&self, conn: &PgConnection, _username: &str) -> Result<Profile, AppError> {
-        let followee = users.filter(username.eq(_username)).first::<User>(conn)?;
-
-        let _ = Follow::create_follow(
-            &conn,
-            &NewFollow {
-                follower_id: self.id,
-                followee_id: followee.id,
-            },
-        )?;
-
-        Ok(Profile {
-            username: self.username.clone(),
-            bio: self.bio.clone(),
-            image: self.image.clone(),
-            following: true,
-        })
-    }
-
-    pub fn unfollow(&self, conn: &PgConnection, _username: &str) -> Result<Profile, AppError> {
-        let followee = users.filter(username.eq(_username)).first::<User>(conn)?;
-
-        let _ = Follow::delete_follow(
-            conn,
-            &DeleteFollow {
-                followee_id: followee.id,
-                follower_id: self.id,
-            },
-        )?;
-
-        Ok(Profile {
-            username: self.username.clone(),
-            bio: self.bio.clone(),
-            image: self.image.clone(),
-            following: false,
-        })
-    }
-
     pub fn is_following(&self, conn: &PgConnection, _followee_id: &Uuid) -> bool {
         use crate::schema::follows::dsl::*;
         let follow = follows
@@ -139,12 +154,12 @@ impl User {
 }
 
 impl User {
-    pub fn generate_token(&self) -> Result<String, AppError> {
+    pub fn generate_token(&self, ttl: chrono::Duration) -> Result<String, AppError> {
         let now = Utc::now();
         let seconds = now.timestamp();
         let nanos = now.timestamp_subsec_nanos();
-        let total_seconds = seconds as i64 + nanos as i64 / 1_000_000_000; // nanosecond -> second
-        let token = token::generate(self.id, total_seconds)?;
+        let total_seconds = seconds + nanos as i64 / 1_000_000_000; // nanosecond -> second
+        let token = token::generate(self.id, total_seconds, ttl.num_seconds())?;
         Ok(token)
     }
 }
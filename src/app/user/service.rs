@@ -0,0 +1,55 @@
+use super::model::User;
+use crate::error::AppError;
+use crate::utils::db;
+use diesel::pg::PgConnection;
+
+type Token = String;
+
+pub struct SignupService {
+    pub email: String,
+    pub username: String,
+    pub password: String,
+    pub token_ttl: chrono::Duration,
+}
+pub fn signup(conn: &PgConnection, params: &SignupService) -> Result<(User, Token), AppError> {
+    db::unit_of_work(conn, || {
+        let (user, token) = User::signup(
+            conn,
+            &params.email,
+            &params.username,
+            &params.password,
+            params.token_ttl,
+        )?;
+        #[cfg(test)]
+        db::fail_point("user::signup::after_insert")?;
+        Ok((user, token))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::db::testing;
+
+    #[test]
+    fn signup_rolls_back_when_a_later_step_fails() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        {
+            let _armed = testing::arm_fail_point("user::signup::after_insert");
+            let result = signup(
+                &conn,
+                &SignupService {
+                    email: "uow_signup@example.com".to_string(),
+                    username: "uow_signup".to_string(),
+                    password: "password".to_string(),
+                    token_ttl: chrono::Duration::days(1),
+                },
+            );
+            assert!(result.is_err());
+        }
+        assert!(User::find_by_username(&conn, "uow_signup").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use super::{
+    request,
+    response::{
+        MultipleDeliveriesResponse, MultipleWebhooksResponse, SingleDeliveryResponse,
+        SingleWebhookResponse,
+    },
+    service,
+};
+use crate::error::AppError;
+use crate::middleware::auth;
+use crate::middleware::state::AppState;
+use crate::utils::uuid;
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+
+type WebhookIdSlug = String;
+type DeliveryIdSlug = String;
+
+pub async fn index(state: web::Data<AppState>, req: HttpRequest) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let list = service::fetch_webhooks(&conn, &auth_user)?;
+    let res = MultipleWebhooksResponse::from(list);
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn create(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    form: web::Json<request::CreateWebhookRequest>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let webhook = service::create_webhook(
+        &conn,
+        &service::CreateWebhookService {
+            me: auth_user,
+            url: form.webhook.url.to_owned(),
+            events: form.webhook.events.to_owned(),
+        },
+    )?;
+    let res = SingleWebhookResponse::with_secret(webhook);
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn delete(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<WebhookIdSlug>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let id = uuid::parse(&path.into_inner())?;
+    service::delete_webhook(&conn, &service::DeleteWebhookService { me: auth_user, id })?;
+    Ok(HttpResponse::Ok().json("Ok"))
+}
+
+#[derive(Deserialize)]
+pub struct DeliveriesListQueryParameter {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+pub async fn deliveries(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<WebhookIdSlug>,
+    params: web::Query<DeliveriesListQueryParameter>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let webhook_id = uuid::parse(&path.into_inner())?;
+    let list = service::fetch_deliveries_list(
+        &conn,
+        &service::FetchDeliveriesList {
+            me: auth_user,
+            webhook_id,
+            offset: params.offset.unwrap_or(0).max(0),
+            limit: state.config.pagination.limit(params.limit),
+        },
+    )?;
+    let res = MultipleDeliveriesResponse::from(list);
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn retry(
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(WebhookIdSlug, DeliveryIdSlug)>,
+) -> Result<HttpResponse, AppError> {
+    let auth_user = auth::access_auth_user(&req)?;
+    let conn = state.get_conn()?;
+    let (webhook_id, delivery_id) = path.into_inner();
+    let delivery = service::retry_delivery(
+        &conn,
+        &service::RetryDeliveryService {
+            me: auth_user,
+            webhook_id: uuid::parse(&webhook_id)?,
+            delivery_id: uuid::parse(&delivery_id)?,
+        },
+    )?;
+    let res = SingleDeliveryResponse::from(delivery);
+    Ok(HttpResponse::Ok().json(res))
+}
@@ -0,0 +1,312 @@
+use crate::app::user::model::User;
+use crate::error::AppError;
+use crate::schema::{webhook_deliveries, webhooks};
+use chrono::NaiveDateTime;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Timestamp};
+use serde_json::json;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    ArticleCreated,
+    ArticleUpdated,
+    ArticleDeleted,
+    CommentCreated,
+    FavoriteCreated,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 5] = [
+        WebhookEvent::ArticleCreated,
+        WebhookEvent::ArticleUpdated,
+        WebhookEvent::ArticleDeleted,
+        WebhookEvent::CommentCreated,
+        WebhookEvent::FavoriteCreated,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::ArticleCreated => "article.created",
+            WebhookEvent::ArticleUpdated => "article.updated",
+            WebhookEvent::ArticleDeleted => "article.deleted",
+            WebhookEvent::CommentCreated => "comment.created",
+            WebhookEvent::FavoriteCreated => "favorite.created",
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, AppError> {
+        Self::ALL
+            .into_iter()
+            .find(|event| event.as_str() == text)
+            .ok_or_else(|| {
+                AppError::UnprocessableEntity(json!({
+                    "error": format!("unknown webhook event: {}", text)
+                }))
+            })
+    }
+}
+
+#[derive(Identifiable, Queryable, Associations, Debug, Clone)]
+#[belongs_to(User, foreign_key = "user_id")]
+#[table_name = "webhooks"]
+pub struct Webhook {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "webhooks"]
+pub struct NewWebhook {
+    pub user_id: Uuid,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+}
+
+impl Webhook {
+    pub fn create(conn: &PgConnection, record: &NewWebhook) -> Result<Self, AppError> {
+        let webhook = diesel::insert_into(webhooks::table)
+            .values(record)
+            .get_result::<Self>(conn)?;
+        Ok(webhook)
+    }
+
+    pub fn fetch_list_by_user_id(
+        conn: &PgConnection,
+        _user_id: Uuid,
+    ) -> Result<Vec<Self>, AppError> {
+        let list = webhooks::table
+            .filter(webhooks::user_id.eq(_user_id))
+            .order(webhooks::created_at.asc())
+            .load::<Self>(conn)?;
+        Ok(list)
+    }
+
+    pub fn fetch_by_id_and_user_id(
+        conn: &PgConnection,
+        _id: Uuid,
+        _user_id: Uuid,
+    ) -> Result<Self, AppError> {
+        let webhook = webhooks::table
+            .filter(webhooks::id.eq(_id))
+            .filter(webhooks::user_id.eq(_user_id))
+            .first::<Self>(conn)?;
+        Ok(webhook)
+    }
+
+    // The user's webhooks that want to hear about `event`.
+    pub fn fetch_subscribed(
+        conn: &PgConnection,
+        _user_id: Uuid,
+        event: WebhookEvent,
+    ) -> Result<Vec<Self>, AppError> {
+        let list = webhooks::table
+            .filter(webhooks::user_id.eq(_user_id))
+            .filter(webhooks::events.contains(vec![event.as_str()]))
+            .load::<Self>(conn)?;
+        Ok(list)
+    }
+
+    pub fn fetch_list_by_ids(conn: &PgConnection, ids: &[Uuid]) -> Result<Vec<Self>, AppError> {
+        let list = webhooks::table
+            .filter(webhooks::id.eq_any(ids))
+            .load::<Self>(conn)?;
+        Ok(list)
+    }
+
+    pub fn delete(conn: &PgConnection, _id: Uuid, _user_id: Uuid) -> Result<(), AppError> {
+        let deleted = diesel::delete(
+            webhooks::table
+                .filter(webhooks::id.eq(_id))
+                .filter(webhooks::user_id.eq(_user_id)),
+        )
+        .execute(conn)?;
+        if deleted == 0 {
+            return Err(diesel::result::Error::NotFound.into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    // Gave up after too many failed attempts. Only a manual retry sends it again.
+    Dead,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Dead => "dead",
+        }
+    }
+}
+
+#[derive(Identifiable, Queryable, QueryableByName, Associations, Debug, Clone)]
+#[belongs_to(Webhook, foreign_key = "webhook_id")]
+#[table_name = "webhook_deliveries"]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_attempt_at: Option<NaiveDateTime>,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "webhook_deliveries"]
+pub struct NewWebhookDelivery {
+    pub webhook_id: Uuid,
+    pub event: String,
+    pub payload: String,
+}
+
+// What came of one attempt to deliver.
+pub struct DeliveryAttempt {
+    pub attempted_at: NaiveDateTime,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    // When to try again after a failure. None gives up.
+    pub retry_at: Option<NaiveDateTime>,
+}
+
+impl WebhookDelivery {
+    pub fn create_list(
+        conn: &PgConnection,
+        records: &[NewWebhookDelivery],
+    ) -> Result<(), AppError> {
+        let _ = diesel::insert_into(webhook_deliveries::table)
+            .values(records)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn fetch_list_by_webhook_id(
+        conn: &PgConnection,
+        _webhook_id: Uuid,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(Vec<Self>, i64), AppError> {
+        let query =
+            webhook_deliveries::table.filter(webhook_deliveries::webhook_id.eq(_webhook_id));
+        let list = query
+            .order((
+                webhook_deliveries::created_at.desc(),
+                webhook_deliveries::id.desc(),
+            ))
+            .offset(offset)
+            .limit(limit)
+            .load::<Self>(conn)?;
+        let count = query.count().get_result::<i64>(conn)?;
+        Ok((list, count))
+    }
+
+    pub fn fetch_by_id_and_webhook_id(
+        conn: &PgConnection,
+        _id: Uuid,
+        _webhook_id: Uuid,
+    ) -> Result<Self, AppError> {
+        let delivery = webhook_deliveries::table
+            .filter(webhook_deliveries::id.eq(_id))
+            .filter(webhook_deliveries::webhook_id.eq(_webhook_id))
+            .first::<Self>(conn)?;
+        Ok(delivery)
+    }
+
+    // Takes up to `limit` pending deliveries that are due, and pushes them back to
+    // `lease_until` so that no other worker sends them meanwhile. Should this worker die
+    // mid-send, they are retried once the lease runs out.
+    pub fn claim_due(
+        conn: &PgConnection,
+        now: NaiveDateTime,
+        lease_until: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<Self>, AppError> {
+        let list = diesel::sql_query(
+            r#"
+UPDATE webhook_deliveries SET next_attempt_at = $2
+WHERE id IN (
+    SELECT id FROM webhook_deliveries
+    WHERE status = 'pending' AND next_attempt_at <= $1
+      AND webhook_id IN (
+          SELECT webhooks.id FROM webhooks
+          INNER JOIN users ON users.id = webhooks.user_id
+          WHERE users.disabled_at IS NULL
+      )
+    ORDER BY next_attempt_at
+    LIMIT $3
+    FOR UPDATE SKIP LOCKED
+)
+RETURNING *
+"#,
+        )
+        .bind::<Timestamp, _>(now)
+        .bind::<Timestamp, _>(lease_until)
+        .bind::<BigInt, _>(limit)
+        .load::<Self>(conn)?;
+        Ok(list)
+    }
+
+    pub fn record_attempt(
+        conn: &PgConnection,
+        _id: Uuid,
+        attempt: &DeliveryAttempt,
+    ) -> Result<(), AppError> {
+        let is_delivered = attempt.error.is_none();
+        let _status = match (is_delivered, attempt.retry_at) {
+            (true, _) => DeliveryStatus::Delivered,
+            (false, Some(_)) => DeliveryStatus::Pending,
+            (false, None) => DeliveryStatus::Dead,
+        };
+        let _ = diesel::update(webhook_deliveries::table.filter(webhook_deliveries::id.eq(_id)))
+            .set((
+                webhook_deliveries::status.eq(_status.as_str()),
+                webhook_deliveries::attempts.eq(webhook_deliveries::attempts + 1),
+                webhook_deliveries::next_attempt_at
+                    .eq(attempt.retry_at.unwrap_or(attempt.attempted_at)),
+                webhook_deliveries::last_attempt_at.eq(attempt.attempted_at),
+                webhook_deliveries::response_status.eq(attempt.response_status),
+                webhook_deliveries::last_error.eq(&attempt.error),
+                webhook_deliveries::delivered_at.eq(is_delivered.then_some(attempt.attempted_at)),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    // Queues a delivery again from scratch, dead or not.
+    pub fn retry(conn: &PgConnection, _id: Uuid, now: NaiveDateTime) -> Result<Self, AppError> {
+        let delivery = diesel::update(
+            webhook_deliveries::table
+                .filter(webhook_deliveries::id.eq(_id))
+                .filter(webhook_deliveries::status.ne(DeliveryStatus::Delivered.as_str())),
+        )
+        .set((
+            webhook_deliveries::status.eq(DeliveryStatus::Pending.as_str()),
+            webhook_deliveries::attempts.eq(0),
+            webhook_deliveries::next_attempt_at.eq(now),
+        ))
+        .get_result::<Self>(conn)
+        .optional()?;
+        delivery.ok_or_else(|| {
+            AppError::UnprocessableEntity(json!({ "error": "the delivery already succeeded" }))
+        })
+    }
+}
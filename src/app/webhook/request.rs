@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub webhook: InnerWebhook,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct InnerWebhook {
+    pub url: String,
+    pub events: Vec<String>,
+}
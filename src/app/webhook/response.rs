@@ -0,0 +1,122 @@
+use crate::app::webhook::model::{DeliveryStatus, Webhook, WebhookDelivery};
+use crate::utils::date::Iso8601;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize)]
+pub struct SingleWebhookResponse {
+    pub webhook: WebhookContent,
+}
+
+impl SingleWebhookResponse {
+    // Right after creation is the only time the secret is shown.
+    pub fn with_secret(webhook: Webhook) -> Self {
+        let secret = webhook.secret.to_owned();
+        let mut content = WebhookContent::from(webhook);
+        content.secret = Some(secret);
+        Self { webhook: content }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MultipleWebhooksResponse {
+    pub webhooks: Vec<WebhookContent>,
+}
+
+impl From<Vec<Webhook>> for MultipleWebhooksResponse {
+    fn from(list: Vec<Webhook>) -> Self {
+        Self {
+            webhooks: list.into_iter().map(WebhookContent::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookContent {
+    pub id: Uuid,
+    pub url: String,
+    pub events: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: Iso8601,
+}
+
+impl From<Webhook> for WebhookContent {
+    fn from(webhook: Webhook) -> Self {
+        Self {
+            id: webhook.id,
+            url: webhook.url,
+            events: webhook.events,
+            secret: None,
+            created_at: Iso8601(webhook.created_at),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SingleDeliveryResponse {
+    pub delivery: DeliveryContent,
+}
+
+impl From<WebhookDelivery> for SingleDeliveryResponse {
+    fn from(delivery: WebhookDelivery) -> Self {
+        Self {
+            delivery: DeliveryContent::from(delivery),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultipleDeliveriesResponse {
+    pub deliveries: Vec<DeliveryContent>,
+    pub deliveries_count: i64,
+}
+
+impl From<(Vec<WebhookDelivery>, i64)> for MultipleDeliveriesResponse {
+    fn from((list, deliveries_count): (Vec<WebhookDelivery>, i64)) -> Self {
+        Self {
+            deliveries: list.into_iter().map(DeliveryContent::from).collect(),
+            deliveries_count,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryContent {
+    pub id: Uuid,
+    pub event: String,
+    // "pending", "delivered" or "dead".
+    pub status: String,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    // When a pending delivery is tried next.
+    pub next_attempt_at: Option<Iso8601>,
+    pub last_attempt_at: Option<Iso8601>,
+    pub delivered_at: Option<Iso8601>,
+    pub created_at: Iso8601,
+    pub payload: Value,
+}
+
+impl From<WebhookDelivery> for DeliveryContent {
+    fn from(delivery: WebhookDelivery) -> Self {
+        let is_pending = delivery.status == DeliveryStatus::Pending.as_str();
+        Self {
+            id: delivery.id,
+            event: delivery.event,
+            status: delivery.status,
+            attempts: delivery.attempts,
+            response_status: delivery.response_status,
+            last_error: delivery.last_error,
+            next_attempt_at: is_pending.then_some(Iso8601(delivery.next_attempt_at)),
+            last_attempt_at: delivery.last_attempt_at.map(Iso8601),
+            delivered_at: delivery.delivered_at.map(Iso8601),
+            created_at: Iso8601(delivery.created_at),
+            payload: serde_json::from_str(&delivery.payload).unwrap_or(Value::Null),
+        }
+    }
+}
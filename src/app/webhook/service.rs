@@ -0,0 +1,228 @@
+use super::model::{
+    DeliveryAttempt, NewWebhook, NewWebhookDelivery, Webhook, WebhookDelivery, WebhookEvent,
+};
+use crate::app::user::model::User;
+use crate::error::AppError;
+use crate::utils::db;
+use actix_web::http::Uri;
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::PgConnection;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::time::Duration;
+use uuid::Uuid;
+
+// The first retry waits this long, and every later one twice as long as the one before.
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(30);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(6 * 60 * 60);
+
+pub struct CreateWebhookService {
+    pub me: User,
+    pub url: String,
+    pub events: Vec<String>,
+}
+
+fn validate_url(url: &str) -> Result<(), AppError> {
+    let is_valid = url.parse::<Uri>().is_ok_and(|uri| {
+        matches!(uri.scheme_str(), Some("http") | Some("https")) && uri.host().is_some()
+    });
+    if !is_valid {
+        return Err(AppError::UnprocessableEntity(json!({
+            "error": "url must be an absolute http or https URL"
+        })));
+    }
+    Ok(())
+}
+
+fn generate_secret() -> String {
+    let bytes = rand::thread_rng().gen::<[u8; 32]>();
+    format!("whsec_{}", hex::encode(bytes))
+}
+
+pub fn create_webhook(
+    conn: &PgConnection,
+    params: &CreateWebhookService,
+) -> Result<Webhook, AppError> {
+    validate_url(&params.url)?;
+    let mut events = params
+        .events
+        .iter()
+        .map(|event| WebhookEvent::parse(event))
+        .collect::<Result<Vec<_>, _>>()?;
+    events.sort_by_key(|event| event.as_str());
+    events.dedup();
+    if events.is_empty() {
+        return Err(AppError::UnprocessableEntity(json!({
+            "error": "a webhook needs at least one event"
+        })));
+    }
+    Webhook::create(
+        conn,
+        &NewWebhook {
+            user_id: params.me.id,
+            url: params.url.to_owned(),
+            secret: generate_secret(),
+            events: events
+                .iter()
+                .map(|event| event.as_str().to_string())
+                .collect(),
+        },
+    )
+}
+
+pub fn fetch_webhooks(conn: &PgConnection, me: &User) -> Result<Vec<Webhook>, AppError> {
+    Webhook::fetch_list_by_user_id(conn, me.id)
+}
+
+pub struct DeleteWebhookService {
+    pub me: User,
+    pub id: Uuid,
+}
+pub fn delete_webhook(conn: &PgConnection, params: &DeleteWebhookService) -> Result<(), AppError> {
+    Webhook::delete(conn, params.id, params.me.id)
+}
+
+pub struct FetchDeliveriesList {
+    pub me: User,
+    pub webhook_id: Uuid,
+    pub offset: i64,
+    pub limit: i64,
+}
+pub fn fetch_deliveries_list(
+    conn: &PgConnection,
+    params: &FetchDeliveriesList,
+) -> Result<(Vec<WebhookDelivery>, i64), AppError> {
+    let webhook = Webhook::fetch_by_id_and_user_id(conn, params.webhook_id, params.me.id)?;
+    WebhookDelivery::fetch_list_by_webhook_id(conn, webhook.id, params.offset, params.limit)
+}
+
+pub struct RetryDeliveryService {
+    pub me: User,
+    pub webhook_id: Uuid,
+    pub delivery_id: Uuid,
+}
+pub fn retry_delivery(
+    conn: &PgConnection,
+    params: &RetryDeliveryService,
+) -> Result<WebhookDelivery, AppError> {
+    db::unit_of_work(conn, || {
+        let webhook = Webhook::fetch_by_id_and_user_id(conn, params.webhook_id, params.me.id)?;
+        let delivery =
+            WebhookDelivery::fetch_by_id_and_webhook_id(conn, params.delivery_id, webhook.id)?;
+        WebhookDelivery::retry(conn, delivery.id, Utc::now().naive_utc())
+    })
+}
+
+// Queues `event` for each of the owner's webhooks that subscribed to it. The payload is the
+// `data` response with the event name added, and is only built when somebody listens. Call
+// it inside the unit of work that makes the change, so both are committed together.
+pub fn enqueue<T, F>(
+    conn: &PgConnection,
+    owner_id: Uuid,
+    event: WebhookEvent,
+    data: F,
+) -> Result<(), AppError>
+where
+    T: Serialize,
+    F: FnOnce() -> Result<T, AppError>,
+{
+    let webhooks = Webhook::fetch_subscribed(conn, owner_id, event)?;
+    if webhooks.is_empty() {
+        return Ok(());
+    }
+    let mut payload =
+        serde_json::to_value(data()?).map_err(|_err| AppError::InternalServerError)?;
+    if let Value::Object(fields) = &mut payload {
+        fields.insert("event".to_string(), json!(event.as_str()));
+    }
+    let payload = payload.to_string();
+    let records = webhooks
+        .iter()
+        .map(|webhook| NewWebhookDelivery {
+            webhook_id: webhook.id,
+            event: event.as_str().to_string(),
+            payload: payload.to_owned(),
+        })
+        .collect::<Vec<_>>();
+    WebhookDelivery::create_list(conn, &records)
+}
+
+// The `X-Conduit-Signature` header: when it was signed, and the hex HMAC-SHA256 of
+// "<timestamp>.<body>" keyed with the webhook's secret. Receivers recompute it to check that
+// the body came from us, and reject old timestamps to stop replays.
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    format!(
+        "t={},v1={}",
+        timestamp,
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+// How long to wait before the next attempt, after `attempts` failed ones.
+pub fn retry_delay(attempts: i32) -> Duration {
+    let doublings = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    RETRY_BASE_DELAY
+        .saturating_mul(2u32.pow(doublings))
+        .min(RETRY_MAX_DELAY)
+}
+
+// What came of sending `delivery` once more: the receiver's status code, or why it couldn't
+// be reached. Anything but a 2xx is retried until `max_attempts` run out.
+pub fn attempt_outcome(
+    delivery: &WebhookDelivery,
+    attempted_at: NaiveDateTime,
+    response: Result<u16, String>,
+    max_attempts: i32,
+) -> DeliveryAttempt {
+    let attempts = delivery.attempts + 1;
+    let (response_status, error) = match response {
+        Ok(status) if (200..300).contains(&status) => (Some(status as i32), None),
+        Ok(status) => (
+            Some(status as i32),
+            Some(format!("receiver answered {}", status)),
+        ),
+        Err(error) => (None, Some(error)),
+    };
+    let retry_at = (error.is_some() && attempts < max_attempts)
+        .then(|| attempted_at + chrono::Duration::from_std(retry_delay(attempts)).unwrap());
+    DeliveryAttempt {
+        attempted_at,
+        response_status,
+        error,
+        retry_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_back_off_exponentially_up_to_a_cap() {
+        assert_eq!(Duration::from_secs(30), retry_delay(1));
+        assert_eq!(Duration::from_secs(60), retry_delay(2));
+        assert_eq!(Duration::from_secs(240), retry_delay(4));
+        assert_eq!(RETRY_MAX_DELAY, retry_delay(30));
+    }
+
+    #[test]
+    fn signatures_cover_the_timestamp_and_body() {
+        let signed = signature("whsec_test", 1700000000, r#"{"event":"article.created"}"#);
+        assert!(signed.starts_with("t=1700000000,v1="));
+        assert_eq!(16 + 64, signed.len());
+        assert_ne!(
+            signed,
+            signature("whsec_test", 1700000001, r#"{"event":"article.created"}"#)
+        );
+        assert_ne!(
+            signed,
+            signature("whsec_other", 1700000000, r#"{"event":"article.created"}"#)
+        );
+    }
+}
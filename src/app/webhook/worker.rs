@@ -0,0 +1,302 @@
+use super::model::{Webhook, WebhookDelivery};
+use super::service;
+use crate::config::WebhooksConfig;
+use crate::error::AppError;
+use crate::utils::db::DbPool;
+use actix_web::{rt, web};
+use chrono::Utc;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+// How many deliveries one pass sends at most.
+const BATCH_SIZE: i64 = 20;
+
+// How long claimed deliveries are hidden from other workers. Comfortably longer than a send
+// can take.
+const LEASE: Duration = Duration::from_secs(60);
+
+// Sends due webhook deliveries in the background, for the lifetime of the server. Several
+// instances can run it side by side: each delivery is claimed by one of them.
+pub fn spawn(pool: DbPool, config: WebhooksConfig) {
+    rt::spawn(async move {
+        let client = client(&config);
+        loop {
+            match deliver_due(&pool, &client).await {
+                // A full batch means more may be waiting.
+                Ok(sent) if sent as i64 == BATCH_SIZE => continue,
+                Ok(_) => {}
+                Err(err) => error!("failed to deliver webhooks: {}", err),
+            }
+            rt::time::sleep(config.poll_interval()).await;
+        }
+    });
+}
+
+// Sends deliveries as `webhooks.*` says. Receivers are registered by users, so by default
+// only public addresses are reached: a webhook must not become a way to probe the server's
+// own network.
+pub struct Client {
+    http: reqwest::Client,
+    public_only: bool,
+    max_attempts: i32,
+}
+
+pub fn client(config: &WebhooksConfig) -> Client {
+    build_client(config, true)
+}
+
+// For tests, whose receivers listen on loopback.
+#[cfg(test)]
+pub fn loopback_client(config: &WebhooksConfig) -> Client {
+    build_client(config, false)
+}
+
+fn build_client(config: &WebhooksConfig, public_only: bool) -> Client {
+    let builder = reqwest::Client::builder()
+        .timeout(config.timeout())
+        .user_agent("Conduit-Webhooks")
+        // A redirect could point anywhere, so a receiver answering one is just a failure.
+        .redirect(redirect::Policy::none())
+        // A proxy would resolve the receiver itself, out of reach of `PublicResolver`.
+        .no_proxy();
+    let builder = if public_only {
+        builder.dns_resolver(Arc::new(PublicResolver))
+    } else {
+        builder
+    };
+    Client {
+        http: builder
+            .build()
+            .expect("failed to build the webhook HTTP client"),
+        public_only,
+        max_attempts: config.max_attempts,
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => is_public_v6(ip),
+    }
+}
+
+// Everything but the special-purpose ranges of the IANA registry.
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    let is_special = a == 0 // "this network", 0.0.0.0/8
+        || ip.is_private()
+        || (a == 100 && (64..128).contains(&b)) // shared address space, 100.64.0.0/10
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || (a == 192 && b == 0 && c == 0) // protocol assignments, 192.0.0.0/24
+        || ip.is_documentation()
+        || (a == 198 && (18..20).contains(&b)) // benchmarking, 198.18.0.0/15
+        || a >= 224; // multicast 224.0.0.0/4, reserved 240.0.0.0/4 and broadcast
+    !is_special
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    let embedded_v4 = |high: u16, low: u16| {
+        let [a, b] = high.to_be_bytes();
+        let [c, d] = low.to_be_bytes();
+        Ipv4Addr::new(a, b, c, d)
+    };
+    match segments {
+        // IPv4-mapped ::ffff:a.b.c.d and IPv4-compatible ::a.b.c.d, which also covers
+        // :: and ::1.
+        [0, 0, 0, 0, 0, 0xffff | 0, high, low] => is_public_v4(embedded_v4(high, low)),
+        // NAT64 64:ff9b::/96 reaches the IPv4 address in its last 32 bits.
+        [0x64, 0xff9b, 0, 0, 0, 0, high, low] => is_public_v4(embedded_v4(high, low)),
+        // 6to4 2002::/16 reaches the IPv4 address in bits 16 to 48.
+        [0x2002, high, low, ..] => is_public_v4(embedded_v4(high, low)),
+        // Local-use NAT64 64:ff9b:1::/48, discard-only 100::/64, Teredo 2001::/32 and
+        // documentation 2001:db8::/32.
+        [0x64, 0xff9b, 1, ..] | [0x100, 0, 0, 0, ..] | [0x2001, 0 | 0xdb8, ..] => false,
+        // Unique local fc00::/7, link-local fe80::/10, site-local fec0::/10 and multicast
+        // ff00::/8.
+        [first, ..] => {
+            let is_local = (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first & 0xffc0) == 0xfec0;
+            !(is_local || first >= 0xff00)
+        }
+    }
+}
+
+// Resolves hosts when connecting, so a name that later resolves elsewhere is checked again.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public(addr.ip()))
+                .collect::<Vec<SocketAddr>>();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn claim_due(pool: &DbPool) -> Result<Vec<(WebhookDelivery, Webhook)>, AppError> {
+    let conn = pool.get()?;
+    let now = Utc::now().naive_utc();
+    let lease_until = now + chrono::Duration::from_std(LEASE).unwrap();
+    let deliveries = WebhookDelivery::claim_due(&conn, now, lease_until, BATCH_SIZE)?;
+    let webhook_ids = deliveries
+        .iter()
+        .map(|delivery| delivery.webhook_id)
+        .collect::<Vec<_>>();
+    let webhooks = Webhook::fetch_list_by_ids(&conn, &webhook_ids)?
+        .into_iter()
+        .map(|webhook| (webhook.id, webhook))
+        .collect::<HashMap<_, _>>();
+    let claimed = deliveries
+        .into_iter()
+        .filter_map(|delivery| {
+            let webhook = webhooks.get(&delivery.webhook_id)?.clone();
+            Some((delivery, webhook))
+        })
+        .collect();
+    Ok(claimed)
+}
+
+async fn send(
+    client: &Client,
+    webhook: &Webhook,
+    delivery: &WebhookDelivery,
+) -> Result<u16, String> {
+    let url = reqwest::Url::parse(&webhook.url).map_err(|err| err.to_string())?;
+    // Hosts given as an IP address are never resolved, so check them here.
+    let literal_ip = url.host_str().and_then(|host| {
+        host.trim_matches(|c| c == '[' || c == ']')
+            .parse::<IpAddr>()
+            .ok()
+    });
+    if let Some(ip) = literal_ip.filter(|ip| client.public_only && !is_public(*ip)) {
+        return Err(format!("{} is not a public address", ip));
+    }
+    let timestamp = Utc::now().timestamp();
+    let res = client
+        .http
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header("X-Conduit-Event", &delivery.event)
+        .header("X-Conduit-Delivery", delivery.id.to_string())
+        .header(
+            "X-Conduit-Signature",
+            service::signature(&webhook.secret, timestamp, &delivery.payload),
+        )
+        .body(delivery.payload.to_owned())
+        .send()
+        .await
+        .map_err(|err| describe(&err))?;
+    Ok(res.status().as_u16())
+}
+
+// reqwest keeps the cause, such as a refused address, out of its own message.
+fn describe(err: &reqwest::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        message = format!("{}: {}", message, cause);
+        source = cause.source();
+    }
+    message
+}
+
+// Sends every delivery that is due, once, and records how each went. Returns how many were
+// attempted.
+pub async fn deliver_due(pool: &DbPool, client: &Client) -> Result<usize, AppError> {
+    let claim_pool = pool.clone();
+    let claimed = web::block(move || claim_due(&claim_pool))
+        .await
+        .map_err(|_err| AppError::InternalServerError)??;
+    let sent = claimed.len();
+    let attempts =
+        futures::future::join_all(claimed.into_iter().map(|(delivery, webhook)| async move {
+            let response = send(client, &webhook, &delivery).await;
+            let attempt = service::attempt_outcome(
+                &delivery,
+                Utc::now().naive_utc(),
+                response,
+                client.max_attempts,
+            );
+            (delivery.id, attempt)
+        }))
+        .await;
+    let pool = pool.clone();
+    web::block(move || -> Result<(), AppError> {
+        let conn = pool.get()?;
+        for (id, attempt) in &attempts {
+            WebhookDelivery::record_attempt(&conn, *id, attempt)?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|_err| AppError::InternalServerError)??;
+    Ok(sent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_public_addresses_are_public() {
+        let public = |ip: &str| is_public(ip.parse().unwrap());
+        for ip in [
+            "93.184.216.34",
+            "2606:2800:220:1::1",
+            "::ffff:93.184.216.34",
+            "64:ff9b::5db8:d822",
+            "2002:5db8:d822::1",
+        ] {
+            assert!(public(ip), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "0.1.2.3",
+            "192.0.0.8",
+            "192.0.2.1",
+            "198.18.0.1",
+            "198.19.255.255",
+            "224.0.0.1",
+            "239.255.255.250",
+            "240.0.0.1",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "fec0::1",
+            "ff02::1",
+            "::ffff:127.0.0.1",
+            "::10.0.0.1",
+            "64:ff9b::10.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+            "64:ff9b:1::1",
+            "2002:a00:1::1",
+            "2002:7f00:1::1",
+            "2001:db8::1",
+            "2001::1",
+            "100::1",
+        ] {
+            assert!(!public(ip), "{}", ip);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use clap::Subcommand;
+use diesel::connection::SimpleConnection;
+use diesel::migration::{Migration, RunMigrationsError};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel_migrations::MigrationConnection;
+use std::path::Path;
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand {
+    /// Apply every pending migration
+    Up,
+    /// Revert the most recently applied migrations
+    Down {
+        /// How many migrations to revert
+        #[arg(long, default_value_t = 1)]
+        steps: usize,
+    },
+    /// List the migrations and whether they are applied
+    Status,
+}
+
+// A migration compiled into the binary; see build.rs.
+pub struct EmbeddedMigration {
+    pub version: &'static str,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+include!(concat!(env!("OUT_DIR"), "/migrations.rs"));
+
+impl Migration for EmbeddedMigration {
+    fn version(&self) -> &str {
+        self.version
+    }
+
+    fn run(&self, conn: &dyn SimpleConnection) -> Result<(), RunMigrationsError> {
+        conn.batch_execute(self.up).map_err(Into::into)
+    }
+
+    fn revert(&self, conn: &dyn SimpleConnection) -> Result<(), RunMigrationsError> {
+        conn.batch_execute(self.down).map_err(Into::into)
+    }
+
+    // Only used to name the migration in diesel's output.
+    fn file_path(&self) -> Option<&Path> {
+        Some(Path::new(self.name))
+    }
+}
+
+pub fn run(conn: &PgConnection, command: MigrateCommand) -> anyhow::Result<()> {
+    match command {
+        MigrateCommand::Up => up(conn, &mut std::io::stdout()),
+        MigrateCommand::Down { steps } => down(conn, steps),
+        MigrateCommand::Status => status(conn),
+    }
+}
+
+pub fn up(conn: &PgConnection, output: &mut dyn std::io::Write) -> anyhow::Result<()> {
+    let migrations = MIGRATIONS
+        .iter()
+        .map(|migration| migration as &dyn Migration);
+    diesel_migrations::run_migrations(conn, migrations, output)?;
+    Ok(())
+}
+
+fn down(conn: &PgConnection, steps: usize) -> anyhow::Result<()> {
+    diesel_migrations::setup_database(conn)?;
+    let mut applied = conn
+        .previously_run_migration_versions()?
+        .into_iter()
+        .collect::<Vec<_>>();
+    applied.sort();
+    for version in applied.iter().rev().take(steps) {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|migration| migration.version == version.as_str())
+            .ok_or_else(|| anyhow::anyhow!("migration {} is not known to this build", version))?;
+        println!("Rolling back migration {}", migration.name);
+        conn.transaction::<_, RunMigrationsError, _>(|| {
+            migration.revert(conn)?;
+            diesel::sql_query("DELETE FROM __diesel_schema_migrations WHERE version = $1")
+                .bind::<Text, _>(migration.version)
+                .execute(conn)?;
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+fn status(conn: &PgConnection) -> anyhow::Result<()> {
+    diesel_migrations::setup_database(conn)?;
+    let applied = conn.previously_run_migration_versions()?;
+    for migration in MIGRATIONS {
+        let state = if applied.contains(migration.version) {
+            "applied"
+        } else {
+            "pending"
+        };
+        println!("{:<8} {}", state, migration.name);
+    }
+    for version in applied.iter().filter(|version| {
+        !MIGRATIONS
+            .iter()
+            .any(|migration| migration.version == version.as_str())
+    }) {
+        println!("{:<8} {} (not known to this build)", "applied", version);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_migration_directory_is_embedded_in_order() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations");
+        let mut names = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        let embedded = MIGRATIONS
+            .iter()
+            .map(|migration| migration.name.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, embedded);
+        assert_eq!("20240101115712", MIGRATIONS[1].version);
+        assert!(MIGRATIONS
+            .windows(2)
+            .all(|pair| pair[0].version < pair[1].version));
+    }
+}
@@ -0,0 +1,97 @@
+pub mod migrate;
+pub mod reindex;
+pub mod seed;
+pub mod user;
+
+use crate::config::Config;
+use clap::{Parser, Subcommand};
+use diesel::pg::PgConnection;
+use diesel::Connection;
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(name = "conduit", about = "Conduit API server", version)]
+pub struct Cli {
+    /// Config file to read [default: conduit.toml, if it exists]
+    #[arg(
+        long,
+        short,
+        global = true,
+        env = "CONDUIT_CONFIG",
+        value_name = "PATH"
+    )]
+    pub config: Option<PathBuf>,
+
+    /// Address to listen on, e.g. 127.0.0.1:8080 (server.bind)
+    #[arg(long, global = true, value_name = "ADDR")]
+    pub bind: Option<String>,
+
+    /// Log filter in env_logger syntax, e.g. info,actix_web=debug (log.filter)
+    #[arg(long, global = true, value_name = "FILTER")]
+    pub log: Option<String>,
+
+    /// Set any config key, e.g. --set pagination.max_limit=50. Can be repeated.
+    #[arg(
+        long = "set",
+        global = true,
+        value_name = "KEY=VALUE",
+        value_parser = parse_override
+    )]
+    pub overrides: Vec<(String, String)>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the API server (the default)
+    Serve,
+    /// Apply, revert or list database migrations
+    Migrate {
+        #[command(subcommand)]
+        command: migrate::MigrateCommand,
+    },
+    /// Fill the database with fake users, articles, comments and follows
+    Seed(seed::SeedArgs),
+    /// Manage user accounts
+    User {
+        #[command(subcommand)]
+        command: user::UserCommand,
+    },
+    /// Rebuild the full-text search index and fill in missing Markdown renderings
+    Reindex,
+}
+
+fn parse_override(text: &str) -> Result<(String, String), String> {
+    let (key, value) = text
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got {}", text))?;
+    Ok((key.trim().to_string(), value.to_string()))
+}
+
+impl Cli {
+    // Config overrides from the command line, the dedicated flags last so they win.
+    pub fn overrides(&self) -> Vec<(String, String)> {
+        let mut overrides = self.overrides.clone();
+        if let Some(bind) = &self.bind {
+            overrides.push(("server.bind".to_string(), bind.to_owned()));
+        }
+        if let Some(log) = &self.log {
+            overrides.push(("log.filter".to_string(), log.to_owned()));
+        }
+        overrides
+    }
+}
+
+// Runs one of the administrative commands, everything but `serve`.
+pub fn run(command: Command, config: &Config) -> anyhow::Result<()> {
+    let conn = PgConnection::establish(&config.database.url)?;
+    match command {
+        Command::Serve => unreachable!("serve is run by main"),
+        Command::Migrate { command } => migrate::run(&conn, command),
+        Command::Seed(args) => seed::run(&conn, config, &args),
+        Command::User { command } => user::run(&conn, config, command),
+        Command::Reindex => reindex::run(&conn),
+    }
+}
@@ -0,0 +1,66 @@
+use crate::app::revision::model::Revision;
+use crate::utils::db;
+use crate::utils::markdown;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Uuid as SqlUuid};
+use uuid::Uuid;
+
+// Articles rewritten per transaction, so that a large table is not locked all at once.
+const BATCH_SIZE: i64 = 500;
+
+#[derive(QueryableByName)]
+struct Reindexed {
+    #[sql_type = "SqlUuid"]
+    id: Uuid,
+}
+
+// Touching the title fires `articles_search_vector_trigger`, which recomputes the vector
+// the same way as on every write.
+const REINDEX_BATCH: &str = r#"
+UPDATE articles SET title = title
+WHERE id IN (
+    SELECT id FROM articles
+    WHERE $1::uuid IS NULL OR id > $1
+    ORDER BY id
+    LIMIT $2
+)
+RETURNING id
+"#;
+
+pub fn run(conn: &PgConnection) -> anyhow::Result<()> {
+    let mut after: Option<Uuid> = None;
+    let mut count = 0;
+    loop {
+        let batch = db::unit_of_work(conn, || {
+            let batch = diesel::sql_query(REINDEX_BATCH)
+                .bind::<Nullable<SqlUuid>, _>(after)
+                .bind::<BigInt, _>(BATCH_SIZE)
+                .load::<Reindexed>(conn)?;
+            Ok(batch)
+        })?;
+        count += batch.len();
+        match batch.iter().map(|reindexed| reindexed.id).max() {
+            Some(last) => after = Some(last),
+            None => break,
+        }
+    }
+    println!("reindexed {} article(s)", count);
+
+    let mut rendered = 0;
+    loop {
+        let batch = db::unit_of_work(conn, || {
+            let batch = Revision::fetch_unrendered(conn, BATCH_SIZE)?;
+            for revision in &batch {
+                Revision::save_rendered(conn, revision.id, &markdown::render(&revision.body))?;
+            }
+            Ok(batch.len())
+        })?;
+        if batch == 0 {
+            break;
+        }
+        rendered += batch;
+    }
+    println!("rendered {} revision(s)", rendered);
+    Ok(())
+}
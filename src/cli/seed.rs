@@ -0,0 +1,241 @@
+use crate::app::article::model::Article;
+use crate::app::user::model::{SignupUser, User};
+use crate::app::{article, comment, favorite, profile};
+use crate::config::Config;
+use crate::schema::{articles, users};
+use crate::utils::{db, hasher};
+use chrono::{Duration, Utc};
+use clap::Args;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use fake::faker::lorem::en::{Paragraphs, Sentence, Sentences};
+use fake::faker::name::en::{FirstName, LastName};
+use fake::Fake;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+// Every seeded user signs in with this password.
+pub const PASSWORD: &str = "password";
+
+const TAGS: [&str; 24] = [
+    "rust",
+    "actix",
+    "diesel",
+    "postgres",
+    "webdev",
+    "api",
+    "design",
+    "testing",
+    "devops",
+    "security",
+    "performance",
+    "databases",
+    "frontend",
+    "career",
+    "opensource",
+    "tutorial",
+    "async",
+    "cloud",
+    "linux",
+    "productivity",
+    "architecture",
+    "beginners",
+    "review",
+    "news",
+];
+
+#[derive(Debug, Args)]
+pub struct SeedArgs {
+    /// How many users to create; articles, comments, follows and favorites grow with it
+    #[arg(long, default_value_t = 20)]
+    pub scale: usize,
+
+    /// Seed for the random generator, to get the same data again on an empty database
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Seeded {
+    pub users: usize,
+    pub articles: usize,
+    pub comments: usize,
+    pub follows: usize,
+    pub favorites: usize,
+}
+
+pub fn run(conn: &PgConnection, config: &Config, args: &SeedArgs) -> anyhow::Result<()> {
+    let seeded = seed(conn, config, args)?;
+    println!(
+        "seeded {} users, {} articles, {} comments, {} follows and {} favorites",
+        seeded.users, seeded.articles, seeded.comments, seeded.follows, seeded.favorites
+    );
+    println!(
+        "every seeded user signs in with the password \"{}\"",
+        PASSWORD
+    );
+    Ok(())
+}
+
+// Creates the data through the same services as the API, so that slugs, revisions, tags and
+// notifications are all in place. Everything is created in one transaction.
+pub fn seed(conn: &PgConnection, config: &Config, args: &SeedArgs) -> anyhow::Result<Seeded> {
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    // Hashing is slow on purpose, so all users share one hash.
+    let hashed_password = hasher::hash_password(PASSWORD)?;
+    let seeded = db::unit_of_work(conn, || {
+        let mut seeded = Seeded::default();
+        let run_id = rng.gen_range(1000..10000);
+        let usernames = (0..args.scale)
+            .map(|index| {
+                let first: String = FirstName().fake_with_rng(&mut rng);
+                let last: String = LastName().fake_with_rng(&mut rng);
+                format!("{}.{}{}{}", first, last, run_id, index)
+                    .to_lowercase()
+                    .replace(|c: char| !c.is_ascii_alphanumeric() && c != '.', "")
+            })
+            .collect::<Vec<_>>();
+        let emails = usernames
+            .iter()
+            .map(|username| format!("{}@example.com", username))
+            .collect::<Vec<_>>();
+        let records = usernames
+            .iter()
+            .zip(&emails)
+            .map(|(username, email)| SignupUser {
+                email,
+                username,
+                password: &hashed_password,
+            })
+            .collect::<Vec<_>>();
+        let users_list = diesel::insert_into(users::table)
+            .values(&records)
+            .get_results::<User>(conn)?;
+        seeded.users = users_list.len();
+
+        let now = Utc::now().naive_utc();
+        let mut articles_list = Vec::<Article>::new();
+        for user in &users_list {
+            for _ in 0..rng.gen_range(0..=6) {
+                let title: String = Sentence(3..8).fake_with_rng(&mut rng);
+                let description: String = Sentences(1..3)
+                    .fake_with_rng::<Vec<String>, _>(&mut rng)
+                    .join(" ");
+                let body = Paragraphs(2..6)
+                    .fake_with_rng::<Vec<String>, _>(&mut rng)
+                    .join("\n\n");
+                let tag_count = rng.gen_range(1..=4);
+                let tag_list = TAGS
+                    .choose_multiple(&mut rng, tag_count)
+                    .map(|tag| tag.to_string())
+                    .collect();
+                let (created, _, _, _) = article::service::create(
+                    conn,
+                    &article::service::CreateArticleService {
+                        title: title.trim_end_matches('.').to_string(),
+                        description,
+                        body,
+                        tag_list: Some(tag_list),
+                        status: Some("published".to_string()),
+                        publish_at: None,
+                        me: user.to_owned(),
+                    },
+                )?;
+                // Spread the articles over the last three months.
+                let published_at = now - Duration::minutes(rng.gen_range(0..60 * 24 * 90));
+                let created = diesel::update(articles::table.find(created.id))
+                    .set((
+                        articles::created_at.eq(published_at),
+                        articles::updated_at.eq(published_at),
+                        articles::published_at.eq(published_at),
+                    ))
+                    .get_result::<Article>(conn)?;
+                articles_list.push(created);
+            }
+        }
+        seeded.articles = articles_list.len();
+
+        for user in &users_list {
+            let others = users_list
+                .iter()
+                .filter(|other| other.id != user.id)
+                .collect::<Vec<_>>();
+            let follow_count = rng.gen_range(0..=others.len().min(8));
+            for followee in others.choose_multiple(&mut rng, follow_count) {
+                profile::service::follow(
+                    conn,
+                    &profile::service::FollowService {
+                        me: user.to_owned(),
+                        username: followee.username.to_owned(),
+                    },
+                )?;
+                seeded.follows += 1;
+            }
+
+            let favorite_count = rng.gen_range(0..=articles_list.len().min(10));
+            for favorited in articles_list.choose_multiple(&mut rng, favorite_count) {
+                favorite::service::favorite(
+                    conn,
+                    &favorite::service::FavoriteService {
+                        me: user.to_owned(),
+                        article_title_slug: favorited.slug.to_owned(),
+                    },
+                )?;
+                seeded.favorites += 1;
+            }
+        }
+
+        for commented in &articles_list {
+            for _ in 0..rng.gen_range(0..=4) {
+                let author = match users_list.choose(&mut rng) {
+                    Some(author) => author,
+                    None => break,
+                };
+                comment::service::create(
+                    conn,
+                    &comment::service::CreateCommentService {
+                        body: Sentences(1..4)
+                            .fake_with_rng::<Vec<String>, _>(&mut rng)
+                            .join(" "),
+                        article_title_slug: commented.slug.to_owned(),
+                        author: author.to_owned(),
+                        parent_id: None,
+                        max_depth: config.comments.max_depth,
+                    },
+                )?;
+                seeded.comments += 1;
+            }
+        }
+        Ok(seeded)
+    })?;
+    Ok(seeded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::db::testing;
+
+    #[test]
+    fn seeding_creates_related_data_at_scale() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let args = SeedArgs {
+            scale: 6,
+            seed: Some(7),
+        };
+        let seeded = seed(&conn, &Config::default(), &args).unwrap();
+        assert_eq!(6, seeded.users);
+        assert!(seeded.articles > 0 && seeded.follows > 0, "{:?}", seeded);
+        let articles_count = articles::table.count().get_result::<i64>(&conn).unwrap();
+        assert!(articles_count >= seeded.articles as i64);
+        let user = users::table.first::<User>(&conn).unwrap();
+        assert!(hasher::verify(PASSWORD, &user.password).unwrap());
+    }
+}
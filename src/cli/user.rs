@@ -0,0 +1,105 @@
+use crate::app::user::model::{Role, UpdatableUser, User};
+use crate::app::user::service;
+use crate::config::Config;
+use crate::utils::{db, hasher};
+use chrono::Utc;
+use clap::Subcommand;
+use diesel::pg::PgConnection;
+use rand::distributions::{Alphanumeric, DistString};
+
+#[derive(Debug, Subcommand)]
+pub enum UserCommand {
+    /// Create a user
+    Create {
+        #[arg(long)]
+        email: String,
+        #[arg(long)]
+        username: String,
+        /// Generated and printed when left out
+        #[arg(long)]
+        password: Option<String>,
+        /// Make the user an admin right away
+        #[arg(long)]
+        admin: bool,
+    },
+    /// Make a user an admin
+    Promote { username: String },
+    /// Lock a user out: they can no longer sign in, and their tokens stop working
+    Disable { username: String },
+    /// Set a new password for a user
+    ResetPassword {
+        username: String,
+        /// Generated and printed when left out
+        #[arg(long)]
+        password: Option<String>,
+    },
+}
+
+fn password_or_generate(password: Option<String>) -> String {
+    password.unwrap_or_else(|| {
+        let password = Alphanumeric.sample_string(&mut rand::thread_rng(), 20);
+        println!("password: {}", password);
+        password
+    })
+}
+
+fn find_user(conn: &PgConnection, username: &str) -> anyhow::Result<User> {
+    User::find_by_username(conn, username)
+        .map_err(|_err| anyhow::anyhow!("no user named {}", username))
+}
+
+pub fn run(conn: &PgConnection, config: &Config, command: UserCommand) -> anyhow::Result<()> {
+    match command {
+        UserCommand::Create {
+            email,
+            username,
+            password,
+            admin,
+        } => {
+            let password = password_or_generate(password);
+            let user = db::unit_of_work(conn, || {
+                let (user, _token) = service::signup(
+                    conn,
+                    &service::SignupService {
+                        email,
+                        username,
+                        password,
+                        token_ttl: config.auth.token_ttl(),
+                    },
+                )?;
+                match admin {
+                    true => User::set_role(conn, user.id, Role::Admin),
+                    false => Ok(user),
+                }
+            })?;
+            println!("created {} ({}, {})", user.username, user.email, user.role);
+        }
+        UserCommand::Promote { username } => {
+            let user = find_user(conn, &username)?;
+            let user = User::set_role(conn, user.id, Role::Admin)?;
+            println!("{} is now an {}", user.username, user.role);
+        }
+        UserCommand::Disable { username } => {
+            let user = find_user(conn, &username)?;
+            let user = User::set_disabled_at(conn, user.id, Some(Utc::now().naive_utc()))?;
+            println!("disabled {}", user.username);
+        }
+        UserCommand::ResetPassword { username, password } => {
+            let user = find_user(conn, &username)?;
+            let password = password_or_generate(password);
+            let user = User::update(
+                conn,
+                user.id,
+                UpdatableUser {
+                    email: None,
+                    username: None,
+                    password: Some(hasher::hash_password(&password)?),
+                    image: None,
+                    bio: None,
+                },
+            )?;
+            println!("reset the password of {}", user.username);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,663 @@
+use crate::constants::env_key;
+use serde::Deserialize;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use std::{env, fs};
+
+// Read when neither `--config` nor `CONDUIT_CONFIG` names a file, if it exists.
+pub const DEFAULT_FILE: &str = "conduit.toml";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("cannot read config file {path}: {message}")]
+    File { path: PathBuf, message: String },
+
+    #[error("invalid value for {key} (from {origin}): {message}")]
+    Value {
+        key: String,
+        origin: String,
+        message: String,
+    },
+
+    #[error("unknown config key {key} (from {origin})")]
+    UnknownKey { key: String, origin: String },
+
+    #[error("invalid config: {0}")]
+    Invalid(String),
+}
+
+// Everything the server can be configured with. Each value comes from, in increasing order
+// of precedence: the defaults below, the config file, environment variables and command-line
+// flags.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub cors: CorsConfig,
+    pub log: LogConfig,
+    pub pagination: PaginationConfig,
+    pub stream: StreamConfig,
+    pub mail: MailConfig,
+    pub comments: CommentsConfig,
+    pub webhooks: WebhooksConfig,
+    pub scheduler: SchedulerConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: String,
+    // Where the frontend lives, for links in emails.
+    pub frontend_url: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0:8080".to_string(),
+            frontend_url: "http://localhost:3000".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub pool_max_size: u32,
+    // Idle connections kept open. None keeps as many as `pool_max_size`.
+    pub pool_min_idle: Option<u32>,
+    // How long to wait for a free connection before giving up.
+    pub connect_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            pool_max_size: 10,
+            pool_min_idle: None,
+            connect_timeout_secs: 30,
+        }
+    }
+}
+
+impl DatabaseConfig {
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub token_ttl_secs: u64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            token_ttl_secs: 60 * 60 * 24,
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn token_ttl(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.token_ttl_secs as i64)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    // Origins allowed to call the API, such as `https://example.com`. Empty allows any.
+    pub allowed_origins: Vec<String>,
+    pub max_age_secs: usize,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            max_age_secs: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    // In `env_logger` syntax, e.g. `info,actix_web=debug`.
+    pub filter: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            filter: "info".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaginationConfig {
+    pub default_limit: i64,
+    pub max_limit: i64,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self {
+            default_limit: 20,
+            max_limit: 100,
+        }
+    }
+}
+
+impl PaginationConfig {
+    // The page size for a requested `limit`, kept within 1..=max_limit.
+    pub fn limit(&self, requested: Option<i64>) -> i64 {
+        requested
+            .unwrap_or(self.default_limit)
+            .clamp(1, self.max_limit)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StreamConfig {
+    // "postgres" relays events between instances through LISTEN/NOTIFY; "none" keeps them
+    // within the instance.
+    pub bridge: StreamBridge,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamBridge {
+    #[default]
+    None,
+    Postgres,
+}
+
+impl FromStr for StreamBridge {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "none" => Ok(StreamBridge::None),
+            "postgres" => Ok(StreamBridge::Postgres),
+            _ => Err("must be one of none or postgres".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MailConfig {
+    // `smtp(s)://user:pass@host:port`, or `file:///some/dir` to write emails to disk. Email
+    // digests are only sent when set.
+    pub url: Option<String>,
+    pub from: String,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            from: "Conduit <noreply@localhost>".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommentsConfig {
+    // How deep replies can nest. Top-level comments are at depth 0, so 0 allows no replies.
+    pub max_depth: i32,
+    // How long after posting a comment its author may still edit it. None allows edits at
+    // any time.
+    pub edit_window_secs: Option<u64>,
+}
+
+impl Default for CommentsConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 5,
+            edit_window_secs: Some(60 * 60),
+        }
+    }
+}
+
+impl CommentsConfig {
+    pub fn edit_window(&self) -> Option<Duration> {
+        self.edit_window_secs.map(Duration::from_secs)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhooksConfig {
+    // How long a receiver gets to answer a delivery.
+    pub timeout_secs: u64,
+    // Upper bound for how long the worker sleeps between looking for due deliveries.
+    pub poll_interval_secs: u64,
+    // Attempts before a delivery is given up on and marked dead.
+    pub max_attempts: i32,
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 10,
+            poll_interval_secs: 5,
+            max_attempts: 8,
+        }
+    }
+}
+
+impl WebhooksConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval_secs)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulerConfig {
+    // Upper bound for how long the publish scheduler sleeps between checks.
+    pub publish_interval_secs: u64,
+    // How often the digest scheduler looks for digests that are due.
+    pub digest_interval_secs: u64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            publish_interval_secs: 5,
+            digest_interval_secs: 60,
+        }
+    }
+}
+
+impl SchedulerConfig {
+    pub fn publish_interval(&self) -> Duration {
+        Duration::from_secs(self.publish_interval_secs)
+    }
+
+    pub fn digest_interval(&self) -> Duration {
+        Duration::from_secs(self.digest_interval_secs)
+    }
+}
+
+// Every key that can be set from the environment or with `--set`. The environment variable
+// is the key upper-cased with dots turned into underscores, prefixed with `CONDUIT_`.
+pub const KEYS: [&str; 22] = [
+    "server.bind",
+    "server.frontend_url",
+    "database.url",
+    "database.pool_max_size",
+    "database.pool_min_idle",
+    "database.connect_timeout_secs",
+    "auth.token_ttl_secs",
+    "cors.allowed_origins",
+    "cors.max_age_secs",
+    "log.filter",
+    "pagination.default_limit",
+    "pagination.max_limit",
+    "stream.bridge",
+    "mail.url",
+    "mail.from",
+    "comments.max_depth",
+    "comments.edit_window_secs",
+    "webhooks.timeout_secs",
+    "webhooks.poll_interval_secs",
+    "webhooks.max_attempts",
+    "scheduler.publish_interval_secs",
+    "scheduler.digest_interval_secs",
+];
+
+// Variables that predate `CONDUIT_*` and are still honored, below those in precedence.
+const LEGACY_ENV: [(&str, &str); 7] = [
+    (env_key::DATABASE_URL, "database.url"),
+    (env_key::FRONTEND_ORIGIN, "cors.allowed_origins"),
+    (env_key::FRONTEND_ORIGIN, "server.frontend_url"),
+    (env_key::STREAM_BRIDGE, "stream.bridge"),
+    (env_key::MAILER_URL, "mail.url"),
+    (env_key::MAIL_FROM, "mail.from"),
+    (env_key::RUST_LOG, "log.filter"),
+];
+
+pub fn env_name(key: &str) -> String {
+    format!("CONDUIT_{}", key.replace('.', "_").to_uppercase())
+}
+
+fn parse<T>(key: &str, source: &str, value: &str) -> Result<T, ConfigError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value.trim().parse::<T>().map_err(|err| ConfigError::Value {
+        key: key.to_string(),
+        origin: source.to_string(),
+        message: err.to_string(),
+    })
+}
+
+// Empty means unset.
+fn parse_optional<T>(key: &str, source: &str, value: &str) -> Result<Option<T>, ConfigError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match value.trim() {
+        "" => Ok(None),
+        value => parse(key, source, value).map(Some),
+    }
+}
+
+// Comma separated.
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl Config {
+    // Builds the config from all layers. `path` is the file given on the command line or
+    // through `CONDUIT_CONFIG`, `overrides` are `key=value` pairs from the command line.
+    pub fn load(path: Option<&Path>, overrides: &[(String, String)]) -> Result<Self, ConfigError> {
+        dotenv::dotenv().ok();
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_FILE).exists() => Self::from_file(Path::new(DEFAULT_FILE))?,
+            None => Self::default(),
+        };
+        config.apply_env(|name| env::var(name).ok())?;
+        for (key, value) in overrides {
+            config.set(key, value, "command line")?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let file_error = |message: String| ConfigError::File {
+            path: path.to_owned(),
+            message,
+        };
+        let text = fs::read_to_string(path).map_err(|err| file_error(err.to_string()))?;
+        toml::from_str(&text).map_err(|err| file_error(err.to_string()))
+    }
+
+    pub fn apply_env<F>(&mut self, var: F) -> Result<(), ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        for (name, key) in LEGACY_ENV {
+            if let Some(value) = var(name) {
+                self.set(key, &value, name)?;
+            }
+        }
+        for key in KEYS {
+            let name = env_name(key);
+            if let Some(value) = var(&name) {
+                self.set(key, &value, &name)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Sets one key from its text form. `source` says where the value came from, for errors.
+    pub fn set(&mut self, key: &str, value: &str, source: &str) -> Result<(), ConfigError> {
+        match key {
+            "server.bind" => self.server.bind = value.trim().to_string(),
+            "server.frontend_url" => self.server.frontend_url = value.trim().to_string(),
+            "database.url" => self.database.url = value.trim().to_string(),
+            "database.pool_max_size" => self.database.pool_max_size = parse(key, source, value)?,
+            "database.pool_min_idle" => {
+                self.database.pool_min_idle = parse_optional(key, source, value)?
+            }
+            "database.connect_timeout_secs" => {
+                self.database.connect_timeout_secs = parse(key, source, value)?
+            }
+            "auth.token_ttl_secs" => self.auth.token_ttl_secs = parse(key, source, value)?,
+            "cors.allowed_origins" => self.cors.allowed_origins = parse_list(value),
+            "cors.max_age_secs" => self.cors.max_age_secs = parse(key, source, value)?,
+            "log.filter" => self.log.filter = value.trim().to_string(),
+            "pagination.default_limit" => {
+                self.pagination.default_limit = parse(key, source, value)?
+            }
+            "pagination.max_limit" => self.pagination.max_limit = parse(key, source, value)?,
+            "stream.bridge" => self.stream.bridge = parse(key, source, value)?,
+            "mail.url" => self.mail.url = parse_optional(key, source, value)?,
+            "mail.from" => self.mail.from = value.trim().to_string(),
+            "comments.max_depth" => self.comments.max_depth = parse(key, source, value)?,
+            "comments.edit_window_secs" => {
+                self.comments.edit_window_secs = parse_optional(key, source, value)?
+            }
+            "webhooks.timeout_secs" => self.webhooks.timeout_secs = parse(key, source, value)?,
+            "webhooks.poll_interval_secs" => {
+                self.webhooks.poll_interval_secs = parse(key, source, value)?
+            }
+            "webhooks.max_attempts" => self.webhooks.max_attempts = parse(key, source, value)?,
+            "scheduler.publish_interval_secs" => {
+                self.scheduler.publish_interval_secs = parse(key, source, value)?
+            }
+            "scheduler.digest_interval_secs" => {
+                self.scheduler.digest_interval_secs = parse(key, source, value)?
+            }
+            _ => {
+                return Err(ConfigError::UnknownKey {
+                    key: key.to_string(),
+                    origin: source.to_string(),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: &str| Err(ConfigError::Invalid(message.to_string()));
+        if self.server.bind.is_empty() {
+            return invalid("server.bind must be set");
+        }
+        if self.database.url.is_empty() {
+            return invalid("database.url must be set, e.g. through DATABASE_URL");
+        }
+        if self.database.pool_max_size == 0 {
+            return invalid("database.pool_max_size must be at least 1");
+        }
+        if self
+            .database
+            .pool_min_idle
+            .is_some_and(|min_idle| min_idle > self.database.pool_max_size)
+        {
+            return invalid("database.pool_min_idle must not exceed database.pool_max_size");
+        }
+        if self.database.connect_timeout_secs == 0 {
+            return invalid("database.connect_timeout_secs must be at least 1");
+        }
+        if self.auth.token_ttl_secs == 0 || self.auth.token_ttl_secs > i64::MAX as u64 / 1000 {
+            return invalid("auth.token_ttl_secs must be a positive number of seconds");
+        }
+        for origin in &self.cors.allowed_origins {
+            let is_http = origin.starts_with("http://") || origin.starts_with("https://");
+            if !is_http || origin.ends_with('/') {
+                return Err(ConfigError::Invalid(format!(
+                    "cors.allowed_origins: {} is not an origin like https://example.com; leave \
+                     the list empty to allow any origin",
+                    origin
+                )));
+            }
+        }
+        if self.pagination.max_limit < 1 {
+            return invalid("pagination.max_limit must be at least 1");
+        }
+        if !(1..=self.pagination.max_limit).contains(&self.pagination.default_limit) {
+            return invalid("pagination.default_limit must be between 1 and pagination.max_limit");
+        }
+        if let Some(url) = &self.mail.url {
+            let schemes = ["file://", "smtp://", "smtps://"];
+            if !schemes.iter().any(|scheme| url.starts_with(scheme)) {
+                return invalid("mail.url must start with file://, smtp:// or smtps://");
+            }
+        }
+        if self.comments.max_depth < 0 {
+            return invalid("comments.max_depth must not be negative");
+        }
+        if self.comments.edit_window_secs == Some(0) {
+            return invalid(
+                "comments.edit_window_secs must be at least 1; leave it empty to allow edits at \
+                 any time",
+            );
+        }
+        if self.webhooks.timeout_secs == 0 {
+            return invalid("webhooks.timeout_secs must be at least 1");
+        }
+        if self.webhooks.poll_interval_secs == 0 {
+            return invalid("webhooks.poll_interval_secs must be at least 1");
+        }
+        if self.webhooks.max_attempts < 1 {
+            return invalid("webhooks.max_attempts must be at least 1");
+        }
+        if self.scheduler.publish_interval_secs == 0 {
+            return invalid("scheduler.publish_interval_secs must be at least 1");
+        }
+        if self.scheduler.digest_interval_secs == 0 {
+            return invalid("scheduler.digest_interval_secs must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config_from(file: &str, vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let mut config = toml::from_str::<Config>(file).map_err(|err| ConfigError::File {
+            path: PathBuf::from("test.toml"),
+            message: err.to_string(),
+        })?;
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+        config.apply_env(|name| vars.get(name).cloned())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[test]
+    fn layers_override_each_other_in_order() {
+        let file = r#"
+            [server]
+            bind = "127.0.0.1:3000"
+
+            [database]
+            url = "postgres://file"
+            pool_max_size = 4
+
+            [pagination]
+            max_limit = 50
+        "#;
+        let mut config = config_from(
+            file,
+            &[
+                ("DATABASE_URL", "postgres://legacy"),
+                ("CONDUIT_DATABASE_URL", "postgres://env"),
+                ("CONDUIT_DATABASE_POOL_MAX_SIZE", "8"),
+                ("FRONTEND_ORIGIN", "https://conduit.test"),
+            ],
+        )
+        .unwrap();
+        config
+            .set("server.bind", "0.0.0.0:80", "command line")
+            .unwrap();
+
+        assert_eq!("0.0.0.0:80", config.server.bind);
+        assert_eq!("postgres://env", config.database.url);
+        assert_eq!(8, config.database.pool_max_size);
+        assert_eq!(vec!["https://conduit.test"], config.cors.allowed_origins);
+        assert_eq!("https://conduit.test", config.server.frontend_url);
+        assert_eq!(50, config.pagination.max_limit);
+        assert_eq!(20, config.pagination.default_limit);
+        assert_eq!(50, config.pagination.limit(Some(1000)));
+        assert_eq!(1, config.pagination.limit(Some(-3)));
+        assert_eq!(
+            Some(Duration::from_secs(3600)),
+            config.comments.edit_window()
+        );
+        config
+            .set("comments.edit_window_secs", "", "command line")
+            .unwrap();
+        assert_eq!(None, config.comments.edit_window());
+    }
+
+    #[test]
+    fn bad_values_are_reported_with_their_source() {
+        let url = [("DATABASE_URL", "postgres://db")];
+        let err = config_from("[database]\npool_size = 3", &url).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field `pool_size`"),
+            "{}",
+            err
+        );
+
+        let err = config_from("", &[url[0], ("CONDUIT_AUTH_TOKEN_TTL_SECS", "a day")]).unwrap_err();
+        assert!(
+            err.to_string().starts_with(
+                "invalid value for auth.token_ttl_secs (from CONDUIT_AUTH_TOKEN_TTL_SECS)"
+            ),
+            "{}",
+            err
+        );
+
+        let err = config_from("", &[url[0], ("FRONTEND_ORIGIN", "*")]).unwrap_err();
+        assert!(err.to_string().contains("cors.allowed_origins"), "{}", err);
+
+        let err = config_from("", &[url[0], ("CONDUIT_COMMENTS_MAX_DEPTH", "-1")]).unwrap_err();
+        assert!(err.to_string().contains("comments.max_depth"), "{}", err);
+
+        let err = config_from("[webhooks]\nmax_attempts = 0", &url).unwrap_err();
+        assert!(err.to_string().contains("webhooks.max_attempts"), "{}", err);
+        let err = config_from(
+            "",
+            &[url[0], ("CONDUIT_SCHEDULER_PUBLISH_INTERVAL_SECS", "0")],
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("scheduler.publish_interval_secs"),
+            "{}",
+            err
+        );
+
+        let err = config_from("", &[]).unwrap_err();
+        assert!(
+            err.to_string().contains("database.url must be set"),
+            "{}",
+            err
+        );
+
+        let mut config = Config::default();
+        let err = config.set("server.port", "80", "command line").unwrap_err();
+        assert_eq!(
+            "unknown config key server.port (from command line)",
+            err.to_string()
+        );
+    }
+}
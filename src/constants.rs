@@ -1,8 +1,14 @@
 pub const AUTHORIZATION: &str = "Authorization";
 
-pub const BIND: &str = "0.0.0.0:8080";
+// Articles listed in one digest email at most. The rest are summed up with a link to the
+// feed.
+pub const DIGEST_MAX_ARTICLES: i64 = 20;
 
 pub mod env_key {
     pub const DATABASE_URL: &str = "DATABASE_URL";
     pub const FRONTEND_ORIGIN: &str = "FRONTEND_ORIGIN";
+    pub const MAILER_URL: &str = "MAILER_URL";
+    pub const MAIL_FROM: &str = "MAIL_FROM";
+    pub const RUST_LOG: &str = "RUST_LOG";
+    pub const STREAM_BRIDGE: &str = "STREAM_BRIDGE";
 }
@@ -1,3 +1,6 @@
+// diesel 1.x `table!`/derive expansions trip this lint on current toolchains.
+#![allow(non_local_definitions)]
+
 #[macro_use]
 extern crate diesel;
 
@@ -6,7 +9,11 @@ extern crate log;
 
 use actix_web::middleware::Logger;
 use actix_web::{App, HttpServer};
+use clap::Parser;
+use std::sync::Arc;
 mod app;
+mod cli;
+mod config;
 mod constants;
 mod error;
 mod middleware;
@@ -16,24 +23,83 @@ mod utils;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    println!("start conduit server...");
-    std::env::set_var("RUST_LOG", "actix_web=trace");
-    env_logger::init();
+    let mut cli = cli::Cli::parse();
+    let config = match config::Config::load(cli.config.as_deref(), &cli.overrides()) {
+        Ok(config) => Arc::new(config),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    env_logger::Builder::new()
+        .parse_filters(&config.log.filter)
+        .init();
+
+    match cli.command.take().unwrap_or(cli::Command::Serve) {
+        cli::Command::Serve => serve(config).await,
+        command => {
+            if let Err(err) = cli::run(command, &config) {
+                eprintln!("error: {:#}", err);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn serve(config: Arc<config::Config>) -> std::io::Result<()> {
+    info!("start conduit server on {}", config.server.bind);
 
     let state = {
-        let pool = utils::db::establish_connection();
-        middleware::state::AppState { pool }
+        let pool = match utils::db::establish_connection(&config.database) {
+            Ok(pool) => pool,
+            Err(err) => {
+                eprintln!("cannot connect to the database: {}", err);
+                std::process::exit(1);
+            }
+        };
+        middleware::state::AppState {
+            pool,
+            config: config.clone(),
+        }
     };
 
+    app::article::scheduler::spawn(state.pool.clone(), config.scheduler.publish_interval());
+    app::webhook::worker::spawn(state.pool.clone(), config.webhooks.clone());
+
+    // Digests are only sent when a mailer is configured.
+    if let Some(mailer_url) = &config.mail.url {
+        let mailer = match utils::mailer::from_url(mailer_url, &config.mail.from) {
+            Ok(mailer) => mailer,
+            Err(_err) => {
+                eprintln!("invalid config: mail.url or mail.from cannot be used");
+                std::process::exit(2);
+            }
+        };
+        app::digest::scheduler::spawn(
+            state.pool.clone(),
+            mailer,
+            config.server.frontend_url.to_owned(),
+            config.scheduler.digest_interval(),
+        );
+    }
+
+    // Instances only see each other's stream events through the Postgres bridge.
+    let is_stream_bridged = config.stream.bridge == config::StreamBridge::Postgres;
+    app::stream::hub::install(is_stream_bridged);
+    if is_stream_bridged {
+        app::stream::bridge::spawn(config.database.url.to_owned());
+    }
+
     HttpServer::new(move || {
         App::new()
             .wrap(Logger::default())
             .app_data(actix_web::web::Data::new(state.clone()))
-            .wrap(middleware::cors::cors())
+            .wrap(middleware::cors::cors(&state.config.cors))
             .wrap(middleware::auth::Authentication)
             .configure(routes::api)
     })
-    .bind(constants::BIND)?
+    .bind(&config.server.bind)?
     .run()
     .await
 }
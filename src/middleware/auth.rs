@@ -7,10 +7,7 @@ use actix_web::HttpMessage;
 use actix_web::{
     body::EitherBody,
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
-    http::{
-        header::{HeaderName, HeaderValue},
-        Method,
-    },
+    http::Method,
     web::Data,
     Error, HttpRequest, HttpResponse,
 };
@@ -67,6 +64,11 @@ where
 
     fn call(&self, mut req: ServiceRequest) -> Self::Future {
         let is_verified = if should_skip_verification(&req) {
+            // NOTE: public routes still get to know who is reading when a token is sent,
+            // so responses can be tailored to the viewer. A bad token just means anonymous.
+            if req.headers().contains_key(constants::AUTHORIZATION) {
+                verify_and_insert_auth_user(&mut req);
+            }
             true
         } else {
             verify_and_insert_auth_user(&mut req)
@@ -101,6 +103,7 @@ fn should_skip_verification(req: &ServiceRequest) -> bool {
 
 fn find_auth_user(conn: &PgConnection, user_id: Uuid) -> Result<User, AppError> {
     let user = User::find_by_id(conn, user_id)?;
+    user.check_enabled()?;
     Ok(user)
 }
 
@@ -108,12 +111,6 @@ fn find_auth_user(conn: &PgConnection, user_id: Uuid) -> Result<User, AppError>
 const TOKEN_IDENTIFIER: &str = "Token";
 
 fn verify_and_insert_auth_user(req: &mut ServiceRequest) -> bool {
-    // TODO: Does it need?
-    req.headers_mut().append(
-        HeaderName::from_static("content-length"),
-        HeaderValue::from_static("true"),
-    );
-
     if let Some(authen_header) = req.headers().get(constants::AUTHORIZATION) {
         info!("Parsing authorization header...");
         if let Ok(authen_str) = authen_header.to_str() {
@@ -187,7 +184,7 @@ impl IgnoreAuthRoute {
         };
         let path_set = expect_path.iter().zip(this_path.iter());
         for (expect_path, this_path) in path_set {
-            if IgnoreAuthRoute::is_slug_path(*expect_path) {
+            if IgnoreAuthRoute::is_slug_path(expect_path) {
                 continue;
             }
             if expect_path != this_path {
@@ -208,27 +205,7 @@ impl IgnoreAuthRoute {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use actix_web::http::Method;
-    #[test]
-    fn is_match_path_and_method_test() {
-        let route = IgnoreAuthRoute {
-            path: "/api/healthcheck",
-            method: Method::GET,
-        };
-        assert!(route.is_match_path_and_method("/api/healthcheck", &Method::GET));
-
-        let route = IgnoreAuthRoute {
-            path: "/api/{this-is-slug}/healthcheck",
-            method: Method::POST,
-        };
-        assert!(route.is_match_path_and_method("/api/1234/healthcheck", &Method::POST));
-    }
-}
-
-const IGNORE_AUTH_ROUTES: [IgnoreAuthRoute; 6] = [
+const IGNORE_AUTH_ROUTES: [IgnoreAuthRoute; 7] = [
     IgnoreAuthRoute {
         path: "/api/healthcheck",
         method: Method::GET,
@@ -249,8 +226,32 @@ const IGNORE_AUTH_ROUTES: [IgnoreAuthRoute; 6] = [
         path: "/api/articles",
         method: Method::GET,
     },
+    IgnoreAuthRoute {
+        path: "/api/articles/{article_title_slug}",
+        method: Method::GET,
+    },
     IgnoreAuthRoute {
         path: "/api/articles/{article_title_slug}/comments",
         method: Method::GET,
     },
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::Method;
+    #[test]
+    fn is_match_path_and_method_test() {
+        let route = IgnoreAuthRoute {
+            path: "/api/healthcheck",
+            method: Method::GET,
+        };
+        assert!(route.is_match_path_and_method("/api/healthcheck", &Method::GET));
+
+        let route = IgnoreAuthRoute {
+            path: "/api/{this-is-slug}/healthcheck",
+            method: Method::POST,
+        };
+        assert!(route.is_match_path_and_method("/api/1234/healthcheck", &Method::POST));
+    }
+}
@@ -1,15 +1,17 @@
-use crate::constants::env_key;
+use crate::config::CorsConfig;
 use actix_cors::Cors;
 use actix_web::http;
-use std::env;
 
-pub fn cors() -> Cors {
-    let frontend_origin = env::var(env_key::FRONTEND_ORIGIN).unwrap_or_else(|_| "*".to_string());
-
-    Cors::default()
-        .allowed_origin(&frontend_origin)
-        .allowed_origin_fn(|origin, _req_head| origin.as_bytes().ends_with(b".rust-lang.org"))
-        .allowed_headers(vec![http::header::AUTHORIZATION, http::header::ACCEPT])
+pub fn cors(config: &CorsConfig) -> Cors {
+    let cors = if config.allowed_origins.is_empty() {
+        Cors::default().allow_any_origin()
+    } else {
+        config
+            .allowed_origins
+            .iter()
+            .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+    };
+    cors.allowed_headers(vec![http::header::AUTHORIZATION, http::header::ACCEPT])
         .allowed_header(http::header::CONTENT_TYPE)
-        .max_age(3600)
+        .max_age(config.max_age_secs)
 }
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::convert::From;
 
+#[allow(dead_code)]
 #[derive(Deserialize, Serialize)]
 pub struct ErrorResponse {
     pub errors: Inner,
@@ -16,6 +17,7 @@ impl From<&str> for ErrorResponse {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize, Serialize)]
 pub struct Inner {
     body: Vec<String>,
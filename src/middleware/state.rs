@@ -1,13 +1,16 @@
+use crate::config::Config;
 use crate::error::AppError;
 use crate::utils;
 use diesel::pg::PgConnection;
 use diesel::r2d2::{ConnectionManager, PooledConnection};
+use std::sync::Arc;
 
 type AppConn = PooledConnection<ConnectionManager<PgConnection>>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: utils::db::DbPool,
+    pub config: Arc<Config>,
 }
 
 impl AppState {
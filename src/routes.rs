@@ -6,19 +6,57 @@ pub fn api(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api")
             .service(web::scope("/healthcheck").route("", get().to(app::healthcheck::api::index)))
-            .service(web::scope("/tags").route("", get().to(app::tag::api::index)))
+            .service(
+                web::scope("/tags")
+                    .route("", get().to(app::tag::api::index))
+                    .route("/{name}/follow", post().to(app::tag::api::follow))
+                    .route("/{name}/follow", delete().to(app::tag::api::unfollow)),
+            )
             .service(
                 web::scope("/users")
                     .route("/login", post().to(app::user::api::signin))
                     .route("", post().to(app::user::api::signup)),
             )
+            .service(
+                web::scope("/stream")
+                    .route("", get().to(app::stream::api::index))
+                    .route("/ws", get().to(app::stream::api::socket)),
+            )
             .service(
                 web::scope("/user")
                     .route("", get().to(app::user::api::me))
-                    .route("", put().to(app::user::api::update)),
+                    .route("", put().to(app::user::api::update))
+                    .route("/digest", get().to(app::digest::api::show))
+                    .route("/digest", put().to(app::digest::api::update))
+                    .route("/drafts", get().to(app::article::api::drafts))
+                    .route("/mentions", get().to(app::mention::api::index))
+                    .route("/notifications", get().to(app::notification::api::index))
+                    .route(
+                        "/notifications/read",
+                        post().to(app::notification::api::read_all),
+                    )
+                    .route(
+                        "/notifications/{notification_id}/read",
+                        post().to(app::notification::api::read),
+                    )
+                    .route("/webhooks", get().to(app::webhook::api::index))
+                    .route("/webhooks", post().to(app::webhook::api::create))
+                    .route(
+                        "/webhooks/{webhook_id}",
+                        delete().to(app::webhook::api::delete),
+                    )
+                    .route(
+                        "/webhooks/{webhook_id}/deliveries",
+                        get().to(app::webhook::api::deliveries),
+                    )
+                    .route(
+                        "/webhooks/{webhook_id}/deliveries/{delivery_id}/retry",
+                        post().to(app::webhook::api::retry),
+                    ),
             )
             .service(
                 web::scope("/profiles")
+                    .route("/suggestions", get().to(app::profile::api::suggestions))
                     .route("/{username}", get().to(app::profile::api::show))
                     .route("/{username}/follow", post().to(app::profile::api::follow))
                     .route(
@@ -41,13 +79,1546 @@ pub fn api(cfg: &mut web::ServiceConfig) {
                                     .route("", post().to(app::favorite::api::favorite))
                                     .route("", delete().to(app::favorite::api::unfavorite)),
                             )
+                            .service(
+                                web::scope("/revisions")
+                                    .route("", get().to(app::revision::api::index))
+                                    .route("/{revision_id}", get().to(app::revision::api::show))
+                                    .route(
+                                        "/{revision_id}/diff",
+                                        get().to(app::revision::api::diff),
+                                    )
+                                    .route(
+                                        "/{revision_id}/restore",
+                                        post().to(app::revision::api::restore),
+                                    ),
+                            )
                             .service(
                                 web::scope("/comments")
                                     .route("", get().to(app::comment::api::index))
                                     .route("", post().to(app::comment::api::create))
-                                    .route("/{comment_id}", delete().to(app::comment::api::delete)),
+                                    .route(
+                                        "/settings",
+                                        put().to(app::comment::api::update_settings),
+                                    )
+                                    .route("/{comment_id}", put().to(app::comment::api::update))
+                                    .route("/{comment_id}", delete().to(app::comment::api::delete))
+                                    .route("/{comment_id}/hide", post().to(app::comment::api::hide))
+                                    .route(
+                                        "/{comment_id}/hide",
+                                        delete().to(app::comment::api::unhide),
+                                    ),
                             ),
                     ),
             ),
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::user::model::User;
+    use crate::config::Config;
+    use crate::middleware;
+    use crate::middleware::state::AppState;
+    use crate::utils::db::testing;
+    use actix_web::body::MessageBody;
+    use actix_web::dev::{Service, ServiceResponse};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use diesel::RunQueryDsl;
+    use serde_json::{json, Value};
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    // Alice writes the articles; Bob is the one interacting with them.
+    fn setup() -> Option<(AppState, String, String)> {
+        let pool = testing::pool()?;
+        let state = AppState {
+            pool,
+            config: Arc::new(Config::default()),
+        };
+        let alice = add_user(&state, "alice");
+        let bob = add_user(&state, "bob");
+        Some((state, alice, bob))
+    }
+
+    fn add_user(state: &AppState, username: &str) -> String {
+        let conn = state.pool.get().unwrap();
+        testing::insert_user(&conn, username)
+            .generate_token(state.config.auth.token_ttl())
+            .unwrap()
+    }
+
+    fn as_user(req: test::TestRequest, token: &str) -> test::TestRequest {
+        req.insert_header(("Authorization", format!("Token {}", token)))
+    }
+
+    async fn call<S, R, B>(app: &S, req: R) -> (StatusCode, Value)
+    where
+        S: Service<R, Response = ServiceResponse<B>, Error = actix_web::Error>,
+        B: MessageBody,
+    {
+        let res = test::call_service(app, req).await;
+        let status = res.status();
+        let body = test::read_body(res).await;
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    // The next Server-Sent Event off a streaming response, as its name and data.
+    async fn next_event<B: MessageBody + Unpin>(body: &mut B) -> (String, Value) {
+        let poll = futures::future::poll_fn(|cx| Pin::new(&mut *body).poll_next(cx));
+        let chunk = tokio::time::timeout(Duration::from_secs(5), poll)
+            .await
+            .expect("no event within 5 seconds")
+            .and_then(Result::ok)
+            .expect("the stream ended");
+        let text = String::from_utf8(chunk.to_vec()).unwrap();
+        let field = |name: &str| {
+            text.lines()
+                .find_map(|line| line.strip_prefix(name))
+                .unwrap()
+                .to_string()
+        };
+        (
+            field("event: "),
+            serde_json::from_str(&field("data: ")).unwrap(),
+        )
+    }
+
+    macro_rules! init_app {
+        ($state:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($state))
+                    .wrap(middleware::auth::Authentication)
+                    .configure(api),
+            )
+            .await
+        };
+    }
+
+    fn create_article(title: &str, status: &str) -> test::TestRequest {
+        create_article_with_body(title, "b", status)
+    }
+
+    fn create_article_with_body(title: &str, body: &str, status: &str) -> test::TestRequest {
+        test::TestRequest::post()
+            .uri("/api/articles")
+            .set_json(json!({ "article": {
+                "title": title,
+                "description": "d",
+                "body": body,
+                "status": status,
+            }}))
+    }
+
+    fn create_comment(slug: &str, body: &str) -> test::TestRequest {
+        test::TestRequest::post()
+            .uri(&format!("/api/articles/{}/comments", slug))
+            .set_json(json!({ "comment": { "body": body } }))
+    }
+
+    fn reply_to(slug: &str, parent_id: &str, body: &str) -> test::TestRequest {
+        test::TestRequest::post()
+            .uri(&format!("/api/articles/{}/comments", slug))
+            .set_json(json!({ "comment": { "body": body, "parentId": parent_id } }))
+    }
+
+    #[actix_web::test]
+    async fn followed_tags_merge_into_the_feed_without_duplicates() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let app = init_app!(state);
+        for (title, tag) in [("Tagged rust", "rust"), ("Tagged go", "go")] {
+            let req = as_user(test::TestRequest::post(), &alice)
+                .uri("/api/articles")
+                .set_json(json!({ "article": {
+                    "title": title,
+                    "description": "d",
+                    "body": "b",
+                    "tagList": [tag],
+                }}));
+            let (status, _) = call(&app, req.to_request()).await;
+            assert_eq!(StatusCode::OK, status);
+        }
+        let feed = || as_user(test::TestRequest::get(), &bob).uri("/api/articles/feed");
+        let titles = |body: Value| {
+            let mut titles = body["articles"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|article| article["title"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>();
+            titles.sort();
+            (titles, body["articlesCount"].as_i64().unwrap())
+        };
+        let follow = |path: &str| as_user(test::TestRequest::post(), &bob).uri(path);
+        let unfollow = |path: &str| as_user(test::TestRequest::delete(), &bob).uri(path);
+
+        let (status, _) = call(&app, follow("/api/tags/unknown/follow").to_request()).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+        // A tag only used on a draft is not revealed by following it.
+        let req = as_user(test::TestRequest::post(), &alice)
+            .uri("/api/articles")
+            .set_json(json!({ "article": {
+                "title": "Secret plans",
+                "description": "d",
+                "body": "b",
+                "status": "draft",
+                "tagList": ["secret"],
+            }}));
+        call(&app, req.to_request()).await;
+        let (status, _) = call(&app, follow("/api/tags/secret/follow").to_request()).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+        let (status, body) = call(&app, follow("/api/tags/rust/follow").to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(true, body["tag"]["following"]);
+        let (_, body) = call(&app, feed().to_request()).await;
+        assert_eq!((vec!["Tagged rust".to_owned()], 1), titles(body));
+
+        // Following the author as well must not list the rust article twice.
+        call(&app, follow("/api/profiles/alice/follow").to_request()).await;
+        let (_, body) = call(&app, feed().to_request()).await;
+        let both = vec!["Tagged go".to_owned(), "Tagged rust".to_owned()];
+        assert_eq!((both, 2), titles(body));
+
+        call(&app, unfollow("/api/profiles/alice/follow").to_request()).await;
+        let (_, body) = call(&app, feed().to_request()).await;
+        assert_eq!((vec!["Tagged rust".to_owned()], 1), titles(body));
+        let (_, body) = call(&app, unfollow("/api/tags/rust/follow").to_request()).await;
+        assert_eq!(false, body["tag"]["following"]);
+        let (_, body) = call(&app, feed().to_request()).await;
+        assert_eq!((vec![], 0), titles(body));
+    }
+
+    #[actix_web::test]
+    async fn slugs_only_change_when_the_title_does() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let app = init_app!(state);
+        let req = as_user(create_article("Hello world", "published"), &bob);
+        let (_, body) = call(&app, req.to_request()).await;
+        assert_eq!("hello-world", body["article"]["slug"]);
+        let req = as_user(create_article("Hello world", "published"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+        assert!(slug.starts_with("hello-world-"));
+
+        let retitle = |title: &str| {
+            as_user(test::TestRequest::put(), &alice)
+                .uri(&format!("/api/articles/{}", slug))
+                .set_json(json!({ "article": { "title": title } }))
+        };
+        for title in ["Hello world", "Hello world", "Hello, World!"] {
+            let (status, body) = call(&app, retitle(title).to_request()).await;
+            assert_eq!(StatusCode::OK, status);
+            assert_eq!(slug, body["article"]["slug"]);
+        }
+
+        let (_, body) = call(&app, retitle("Goodbye world").to_request()).await;
+        assert_eq!("goodbye-world", body["article"]["slug"]);
+        let req = test::TestRequest::get().uri(&format!("/api/articles/{}", slug));
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(StatusCode::MOVED_PERMANENTLY, res.status());
+        assert_eq!(
+            "/api/articles/goodbye-world",
+            res.headers().get("Location").unwrap()
+        );
+
+        // A last word that merely looks like a generated suffix is still part of the title.
+        let req = as_user(create_article("Hello decade", "published"), &bob);
+        let (_, body) = call(&app, req.to_request()).await;
+        assert_eq!("hello-decade", body["article"]["slug"]);
+        let req = as_user(test::TestRequest::put(), &bob)
+            .uri("/api/articles/hello-decade")
+            .set_json(json!({ "article": { "title": "Hello" } }));
+        let (_, body) = call(&app, req.to_request()).await;
+        assert_eq!("hello", body["article"]["slug"]);
+    }
+
+    #[actix_web::test]
+    async fn authors_can_browse_diff_and_restore_revisions() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let app = init_app!(state);
+        let req = as_user(
+            create_article_with_body("History", "one", "published"),
+            &alice,
+        );
+        let (_, body) = call(&app, req.to_request()).await;
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+        let req = as_user(test::TestRequest::put(), &alice)
+            .uri(&format!("/api/articles/{}", slug))
+            .set_json(json!({ "article": { "body": "two" } }));
+        call(&app, req.to_request()).await;
+
+        let revisions = format!("/api/articles/{}/revisions", slug);
+        let list = || test::TestRequest::get().uri(&revisions);
+        let (status, body) = call(&app, as_user(list(), &alice).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(2, body["revisionsCount"]);
+        // NOTE: both revisions share a timestamp inside the test transaction, so look them
+        // up by content rather than relying on their order.
+        let revision_id = |body: &Value, text: &str| {
+            body["revisions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|revision| revision["body"] == text)
+                .map(|revision| revision["id"].as_str().unwrap().to_owned())
+                .unwrap()
+        };
+        let (first, second) = (revision_id(&body, "one"), revision_id(&body, "two"));
+
+        let req = test::TestRequest::get().uri(&format!("{}/{}", revisions, first));
+        let (status, body) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("one", body["revision"]["body"]);
+        assert_eq!("alice", body["revision"]["author"]["username"]);
+
+        let diff = format!("{}/{}/diff?from={}", revisions, second, first);
+        let req = test::TestRequest::get().uri(&diff);
+        let (status, body) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(
+            json!([{ "op": "delete", "line": "one" }, { "op": "insert", "line": "two" }]),
+            body["diff"]["body"]
+        );
+
+        // Readers of a published article can browse its history but not restore it.
+        let restore = || test::TestRequest::post().uri(&format!("{}/{}/restore", revisions, first));
+        let (status, _) = call(&app, as_user(list(), &bob).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        let (status, _) = call(&app, as_user(restore(), &bob).to_request()).await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+
+        let (status, body) = call(&app, as_user(restore(), &alice).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("one", body["article"]["body"]);
+        assert_eq!(slug, body["article"]["slug"]);
+        let (_, body) = call(&app, as_user(list(), &alice).to_request()).await;
+        assert_eq!(3, body["revisionsCount"]);
+
+        // Once the article is a draft again, its history is hidden from everyone else.
+        let req = as_user(test::TestRequest::put(), &alice)
+            .uri(&format!("/api/articles/{}", slug))
+            .set_json(json!({ "article": { "status": "draft" } }));
+        call(&app, req.to_request()).await;
+        let (status, _) = call(&app, as_user(list(), &bob).to_request()).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+        let req = test::TestRequest::get().uri(&format!("{}/{}", revisions, first));
+        let (status, _) = call(&app, as_user(req, &bob).to_request()).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+    }
+
+    #[actix_web::test]
+    async fn cursors_page_forwards_and_backwards() {
+        let (state, alice, _) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let app = init_app!(state);
+        for index in 0..5 {
+            let req = as_user(
+                create_article(&format!("Paged {}", index), "published"),
+                &alice,
+            );
+            call(&app, req.to_request()).await;
+        }
+        let page = |query: String| {
+            test::TestRequest::get().uri(&format!("/api/articles?author=alice&limit=2{}", query))
+        };
+        let slugs = |body: &Value| {
+            body["articles"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|article| article["slug"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+        let cursor = |body: &Value, name: &str| body[name].as_str().map(str::to_owned);
+
+        let (_, first) = call(&app, page(String::new()).to_request()).await;
+        assert_eq!(None, cursor(&first, "prevCursor"));
+        let next = cursor(&first, "nextCursor").unwrap();
+        let (_, second) = call(&app, page(format!("&after={}", next)).to_request()).await;
+        let next = cursor(&second, "nextCursor").unwrap();
+        let (status, third) = call(&app, page(format!("&after={}", next)).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(1, slugs(&third).len());
+        assert_eq!(None, cursor(&third, "nextCursor"));
+        let mut seen = [slugs(&first), slugs(&second), slugs(&third)].concat();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(5, seen.len());
+
+        // Walking back with `before` returns the same pages in the same order.
+        let prev = cursor(&third, "prevCursor").unwrap();
+        let (_, back) = call(&app, page(format!("&before={}", prev)).to_request()).await;
+        assert_eq!(slugs(&second), slugs(&back));
+        assert_eq!(cursor(&second, "nextCursor"), cursor(&back, "nextCursor"));
+        let prev = cursor(&back, "prevCursor").unwrap();
+        let (_, back) = call(&app, page(format!("&before={}", prev)).to_request()).await;
+        assert_eq!(slugs(&first), slugs(&back));
+        assert_eq!(None, cursor(&back, "prevCursor"));
+        assert_eq!(cursor(&first, "nextCursor"), cursor(&back, "nextCursor"));
+
+        let (status, _) = call(&app, page("&after=garbage".to_owned()).to_request()).await;
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+    }
+
+    #[actix_web::test]
+    async fn updating_tag_list_replaces_and_dedupes_tags() {
+        let (state, alice, _) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let app = init_app!(state);
+        let req = as_user(test::TestRequest::post(), &alice)
+            .uri("/api/articles")
+            .set_json(json!({ "article": {
+                "title": "Tagged",
+                "description": "d",
+                "body": "b",
+                "tagList": ["rust", "web"],
+            }}));
+        let (_, body) = call(&app, req.to_request()).await;
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+        let update = |article: Value| {
+            as_user(test::TestRequest::put(), &alice)
+                .uri(&format!("/api/articles/{}", slug))
+                .set_json(json!({ "article": article }))
+        };
+        let tags = |body: Value| {
+            let mut tags = body["article"]["tagList"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|tag| tag.as_str().unwrap().to_owned())
+                .collect::<Vec<_>>();
+            tags.sort();
+            tags
+        };
+
+        let req = update(json!({ "tagList": ["go", " go ", "", "rust", "rust"] }));
+        let (status, body) = call(&app, req.to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(vec!["go", "rust"], tags(body));
+
+        let (_, body) = call(&app, update(json!({ "body": "new body" })).to_request()).await;
+        assert_eq!(vec!["go", "rust"], tags(body));
+
+        let req = update(json!({ "addTags": ["web", "go"], "removeTags": ["rust"] }));
+        let (_, body) = call(&app, req.to_request()).await;
+        assert_eq!(vec!["go", "web"], tags(body));
+
+        let req = update(json!({ "tagList": ["rust"], "addTags": ["web"] }));
+        let (status, _) = call(&app, req.to_request()).await;
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+
+        let req = update(json!({ "addTags": ["rust", "go"], "removeTags": [" go "] }));
+        let (status, body) = call(&app, req.to_request()).await;
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+        assert_eq!("tags cannot be both added and removed: go", body["error"]);
+        let (_, body) = call(&app, update(json!({})).to_request()).await;
+        assert_eq!(vec!["go", "web"], tags(body));
+    }
+
+    #[actix_web::test]
+    async fn users_can_comment_on_other_users_articles() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let carol = add_user(&state, "carol");
+        let app = init_app!(state);
+        let req = as_user(create_article("Cross user", "published"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+
+        let req = as_user(create_comment(&slug, "nice"), &bob);
+        let (status, body) = call(&app, req.to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("bob", body["comment"]["author"]["username"]);
+        let comment = format!(
+            "/api/articles/{}/comments/{}",
+            slug,
+            body["comment"]["id"].as_str().unwrap()
+        );
+
+        // Other readers cannot delete it.
+        let delete = || test::TestRequest::delete().uri(&comment);
+        let (status, _) = call(&app, as_user(delete(), &carol).to_request()).await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+        let (status, _) = call(&app, as_user(delete(), &bob).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        let (status, _) = call(&app, as_user(delete(), &bob).to_request()).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+    }
+
+    #[actix_web::test]
+    async fn users_can_favorite_other_users_articles() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let app = init_app!(state);
+        let req = as_user(create_article("Cross user", "published"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+        let favorite = format!("/api/articles/{}/favorite", slug);
+
+        let req = as_user(test::TestRequest::post().uri(&favorite), &bob);
+        let (status, body) = call(&app, req.to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(true, body["article"]["favorited"]);
+        assert_eq!(1, body["article"]["favoritesCount"]);
+
+        // Bob's favorite counts for Alice too, but she hasn't favorited it herself.
+        let req = test::TestRequest::get().uri(&format!("/api/articles/{}", slug));
+        let (_, body) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(false, body["article"]["favorited"]);
+        assert_eq!(1, body["article"]["favoritesCount"]);
+
+        let req = as_user(test::TestRequest::delete().uri(&favorite), &bob);
+        let (status, body) = call(&app, req.to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(false, body["article"]["favorited"]);
+        assert_eq!(0, body["article"]["favoritesCount"]);
+    }
+
+    #[actix_web::test]
+    async fn drafts_cannot_be_favorited_or_commented_by_others() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let pool = state.pool.clone();
+        let app = init_app!(state);
+        let req = as_user(create_article("Draft", "draft"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+
+        let req = test::TestRequest::post().uri(&format!("/api/articles/{}/favorite", slug));
+        let (status, _) = call(&app, as_user(req, &bob).to_request()).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+        let req = as_user(create_comment(&slug, "first"), &bob);
+        let (status, _) = call(&app, req.to_request()).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+
+        // Only the author lists the draft, and a negative offset reads as the first page.
+        let drafts = || test::TestRequest::get().uri("/api/user/drafts?offset=-1");
+        let (status, body) = call(&app, as_user(drafts(), &alice).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(slug, body["articles"][0]["slug"]);
+        let (_, body) = call(&app, as_user(drafts(), &bob).to_request()).await;
+        assert_eq!(0, body["articlesCount"]);
+
+        // Editing a draft brings it back to the top.
+        let req = as_user(create_article("Older draft", "draft"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        let older_slug = body["article"]["slug"].as_str().unwrap().to_owned();
+        diesel::sql_query(
+            "UPDATE articles SET updated_at = updated_at - CASE title \
+             WHEN 'Older draft' THEN INTERVAL '2 hours' ELSE INTERVAL '1 hour' END",
+        )
+        .execute(&pool.get().unwrap())
+        .unwrap();
+        let (_, body) = call(&app, as_user(drafts(), &alice).to_request()).await;
+        assert_eq!(slug, body["articles"][0]["slug"]);
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/articles/{}", older_slug))
+            .set_json(json!({ "article": { "body": "more" } }));
+        let (status, _) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        let (_, body) = call(&app, as_user(drafts(), &alice).to_request()).await;
+        assert_eq!(older_slug, body["articles"][0]["slug"]);
+        assert_eq!(slug, body["articles"][1]["slug"]);
+    }
+
+    #[actix_web::test]
+    async fn comments_are_listed_per_article_and_paged() {
+        let (mut state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        // Comments are only paged on request, whatever the default page size.
+        let mut config = Config::default();
+        config.pagination.default_limit = 2;
+        state.config = Arc::new(config);
+        let pool = state.pool.clone();
+        let app = init_app!(state);
+        let mut slugs = vec![];
+        for title in ["First", "Second"] {
+            let req = as_user(create_article(title, "published"), &alice);
+            let (_, body) = call(&app, req.to_request()).await;
+            slugs.push(body["article"]["slug"].as_str().unwrap().to_owned());
+        }
+        for body in ["one", "two", "three"] {
+            let req = as_user(create_comment(&slugs[0], body), &bob);
+            call(&app, req.to_request()).await;
+        }
+        let req = as_user(create_comment(&slugs[1], "elsewhere"), &bob);
+        call(&app, req.to_request()).await;
+        // Everything above happened in one transaction, at the same CURRENT_TIMESTAMP.
+        diesel::sql_query(
+            "UPDATE comments SET created_at = created_at + CASE body \
+             WHEN 'two' THEN INTERVAL '1 minute' WHEN 'three' THEN INTERVAL '2 minutes' \
+             ELSE INTERVAL '0' END",
+        )
+        .execute(&pool.get().unwrap())
+        .unwrap();
+
+        let list = |query: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/api/articles/{}/comments{}", slugs[0], query))
+                .to_request()
+        };
+        let bodies = |body: &Value| {
+            body["comments"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|comment| comment["body"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+        let (status, body) = call(&app, list("")).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(vec!["one", "two", "three"], bodies(&body));
+        assert_eq!(3, body["commentsCount"]);
+
+        let (_, body) = call(&app, list("?order=newest&limit=2")).await;
+        assert_eq!(vec!["three", "two"], bodies(&body));
+        let (_, body) = call(&app, list("?order=newest&limit=2&offset=2")).await;
+        assert_eq!(vec!["one"], bodies(&body));
+        assert_eq!(3, body["commentsCount"]);
+
+        let (status, _) = call(&app, list("?order=sideways")).await;
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+        let req = test::TestRequest::get().uri("/api/articles/no-such-article/comments");
+        let (status, _) = call(&app, req.to_request()).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+    }
+
+    #[actix_web::test]
+    async fn replies_form_threads_that_survive_deleting_a_parent() {
+        let (mut state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let max_depth = 3;
+        let mut config = Config::default();
+        config.comments.max_depth = max_depth;
+        state.config = Arc::new(config);
+        let pool = state.pool.clone();
+        let app = init_app!(state);
+        let req = as_user(create_article("Threads", "published"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+
+        let req = as_user(create_comment(&slug, "root draft"), &bob);
+        let (_, body) = call(&app, req.to_request()).await;
+        let root = body["comment"]["id"].as_str().unwrap().to_owned();
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/articles/{}/comments/{}", slug, root))
+            .set_json(json!({ "comment": { "body": "root" } }));
+        let (status, _) = call(&app, as_user(req, &bob).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        let mut parent = root.clone();
+        for depth in 1..=max_depth {
+            let req = as_user(
+                reply_to(&slug, &parent, &format!("reply {}", depth)),
+                &alice,
+            );
+            let (status, body) = call(&app, req.to_request()).await;
+            assert_eq!(StatusCode::OK, status);
+            assert_eq!(parent.as_str(), body["comment"]["parentId"]);
+            parent = body["comment"]["id"].as_str().unwrap().to_owned();
+        }
+        let req = as_user(reply_to(&slug, &parent, "too deep"), &alice);
+        let (status, _) = call(&app, req.to_request()).await;
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+
+        // Deleting a comment with replies leaves a placeholder in its place.
+        let req =
+            test::TestRequest::delete().uri(&format!("/api/articles/{}/comments/{}", slug, root));
+        let (status, _) = call(&app, as_user(req, &bob).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        let req = as_user(reply_to(&slug, &root, "late"), &alice);
+        let (status, _) = call(&app, req.to_request()).await;
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+
+        let req =
+            test::TestRequest::get().uri(&format!("/api/articles/{}/comments?view=tree", slug));
+        let (status, body) = call(&app, req.to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(max_depth as i64, body["commentsCount"]);
+        let thread = &body["comments"][0];
+        assert_eq!(1, body["comments"].as_array().unwrap().len());
+        assert_eq!(true, thread["deleted"]);
+        assert_eq!("", thread["body"]);
+        assert_eq!(Value::Null, thread["author"]);
+        // Nor does its edit history keep the deleted text.
+        use crate::schema::comment_revisions;
+        use diesel::prelude::*;
+        let revisions = comment_revisions::table
+            .select(diesel::dsl::count_star())
+            .first::<i64>(&pool.get().unwrap())
+            .unwrap();
+        assert_eq!(0, revisions);
+        let mut depth = 0;
+        let mut comment = thread;
+        while let Some(reply) = comment["replies"].get(0) {
+            depth += 1;
+            assert_eq!(format!("reply {}", depth), reply["body"]);
+            comment = reply;
+        }
+        assert_eq!(max_depth, depth);
+
+        let req = test::TestRequest::get().uri(&format!("/api/articles/{}/comments", slug));
+        let (_, body) = call(&app, req.to_request()).await;
+        let flat = body["comments"].as_array().unwrap();
+        assert_eq!(max_depth as usize + 1, flat.len());
+        assert!(flat.iter().all(|comment| comment.get("replies").is_none()));
+    }
+
+    #[actix_web::test]
+    async fn authors_can_edit_their_comments_and_keep_the_history() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let pool = state.pool.clone();
+        let app = init_app!(state);
+        let req = as_user(create_article("Edits", "published"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+        let req = as_user(create_comment(&slug, "teh first"), &bob);
+        let (_, body) = call(&app, req.to_request()).await;
+        assert_eq!(false, body["comment"]["edited"]);
+        assert_eq!(Value::Null, body["comment"]["editedAt"]);
+        let id = body["comment"]["id"].as_str().unwrap().to_owned();
+        let edit = |text: &str| {
+            test::TestRequest::put()
+                .uri(&format!("/api/articles/{}/comments/{}", slug, id))
+                .set_json(json!({ "comment": { "body": text } }))
+        };
+
+        let (status, _) = call(&app, as_user(edit("hijacked"), &alice).to_request()).await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+        let (status, body) = call(&app, as_user(edit("the first"), &bob).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("the first", body["comment"]["body"]);
+        assert_eq!(true, body["comment"]["edited"]);
+        assert!(body["comment"]["editedAt"].is_string());
+        call(&app, as_user(edit("the first"), &bob).to_request()).await;
+
+        use crate::schema::comment_revisions;
+        use diesel::prelude::*;
+        let revisions = comment_revisions::table
+            .select(comment_revisions::body)
+            .load::<String>(&pool.get().unwrap())
+            .unwrap();
+        assert_eq!(vec!["teh first"], revisions);
+
+        // Past the configured edit window the comment is final.
+        diesel::sql_query("UPDATE comments SET created_at = created_at - INTERVAL '2 hours'")
+            .execute(&pool.get().unwrap())
+            .unwrap();
+        let (status, _) = call(&app, as_user(edit("the last"), &bob).to_request()).await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+    }
+
+    #[actix_web::test]
+    async fn article_authors_moderate_their_comments() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let app = init_app!(state);
+        let req = as_user(create_article("Moderated", "published"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+        let mut ids = vec![];
+        for text in ["rude", "spam"] {
+            let (_, body) = call(
+                &app,
+                as_user(create_comment(&slug, text), &bob).to_request(),
+            )
+            .await;
+            ids.push(body["comment"]["id"].as_str().unwrap().to_owned());
+        }
+        let comment =
+            |id: &str, rest: &str| format!("/api/articles/{}/comments/{}{}", slug, id, rest);
+
+        // Bob cannot moderate Alice's article, Alice can hide and delete Bob's comments.
+        let req = test::TestRequest::post().uri(&comment(&ids[0], "/hide"));
+        let (status, _) = call(&app, as_user(req, &bob).to_request()).await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+        let req = test::TestRequest::post().uri(&comment(&ids[0], "/hide"));
+        let (status, body) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(true, body["comment"]["hidden"]);
+        let req = test::TestRequest::delete().uri(&comment(&ids[1], ""));
+        let (status, _) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+
+        let list = || test::TestRequest::get().uri(&format!("/api/articles/{}/comments", slug));
+        let (_, body) = call(&app, as_user(list(), &bob).to_request()).await;
+        assert_eq!(0, body["commentsCount"]);
+        assert_eq!(1, body["comments"].as_array().unwrap().len());
+        assert_eq!("", body["comments"][0]["body"]);
+        assert_eq!(Value::Null, body["comments"][0]["author"]);
+        let (_, body) = call(&app, as_user(list(), &alice).to_request()).await;
+        assert_eq!("rude", body["comments"][0]["body"]);
+        assert_eq!(true, body["comments"][0]["hidden"]);
+
+        let req = test::TestRequest::delete().uri(&comment(&ids[0], "/hide"));
+        call(&app, as_user(req, &alice).to_request()).await;
+        let (_, body) = call(&app, as_user(list(), &bob).to_request()).await;
+        assert_eq!("rude", body["comments"][0]["body"]);
+        assert_eq!(1, body["commentsCount"]);
+    }
+
+    #[actix_web::test]
+    async fn article_authors_can_lock_comments_or_limit_them_to_followers() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let app = init_app!(state);
+        let req = as_user(create_article("Settings", "published"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+        let settings = |settings: Value| {
+            test::TestRequest::put()
+                .uri(&format!("/api/articles/{}/comments/settings", slug))
+                .set_json(json!({ "settings": settings }))
+        };
+
+        let req = settings(json!({ "locked": true }));
+        let (status, _) = call(&app, as_user(req, &bob).to_request()).await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+        let req = settings(json!({ "locked": true }));
+        let (status, body) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(true, body["article"]["commentsLocked"]);
+        let (status, _) = call(
+            &app,
+            as_user(create_comment(&slug, "hi"), &bob).to_request(),
+        )
+        .await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+        let (status, _) = call(
+            &app,
+            as_user(create_comment(&slug, "note"), &alice).to_request(),
+        )
+        .await;
+        assert_eq!(StatusCode::OK, status);
+
+        let req = settings(json!({ "locked": false, "followersOnly": true }));
+        let (_, body) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(false, body["article"]["commentsLocked"]);
+        assert_eq!(true, body["article"]["commentsFollowersOnly"]);
+        let (status, _) = call(
+            &app,
+            as_user(create_comment(&slug, "hi"), &bob).to_request(),
+        )
+        .await;
+        assert_eq!(StatusCode::FORBIDDEN, status);
+        let req = test::TestRequest::post().uri("/api/profiles/alice/follow");
+        call(&app, as_user(req, &bob).to_request()).await;
+        let (status, _) = call(
+            &app,
+            as_user(create_comment(&slug, "hi"), &bob).to_request(),
+        )
+        .await;
+        assert_eq!(StatusCode::OK, status);
+    }
+
+    #[actix_web::test]
+    async fn mentions_are_resolved_and_listed_for_the_mentioned_user() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let app = init_app!(state);
+        let article = create_article_with_body("Mentions", "Thanks @bob and @nobody!", "published");
+        let req = as_user(article, &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        assert_eq!(
+            json!([{ "username": "bob", "start": 7, "end": 11 }]),
+            body["article"]["mentions"]
+        );
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+        let req = as_user(create_article_with_body("Draft", "@bob", "draft"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        let draft_slug = body["article"]["slug"].as_str().unwrap().to_owned();
+
+        let req = as_user(create_comment(&slug, "@alice you're welcome"), &bob);
+        let (_, body) = call(&app, req.to_request()).await;
+        assert_eq!("alice", body["comment"]["mentions"][0]["username"]);
+        let comment_id = body["comment"]["id"].as_str().unwrap().to_owned();
+
+        let mentions = || test::TestRequest::get().uri("/api/user/mentions");
+        let (status, body) = call(&app, as_user(mentions(), &bob).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(1, body["mentionsCount"]);
+        assert_eq!("alice", body["mentions"][0]["author"]["username"]);
+        assert_eq!(slug.as_str(), body["mentions"][0]["article"]["slug"]);
+        assert_eq!(Value::Null, body["mentions"][0]["comment"]);
+        let (_, body) = call(&app, as_user(mentions(), &alice).to_request()).await;
+        assert_eq!(1, body["mentionsCount"]);
+        assert_eq!(comment_id.as_str(), body["mentions"][0]["comment"]["id"]);
+
+        // Editing the mention away removes it.
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/articles/{}/comments/{}", slug, comment_id))
+            .set_json(json!({ "comment": { "body": "you're welcome" } }));
+        let (_, body) = call(&app, as_user(req, &bob).to_request()).await;
+        assert_eq!(json!([]), body["comment"]["mentions"]);
+        let (_, body) = call(&app, as_user(mentions(), &alice).to_request()).await;
+        assert_eq!(0, body["mentionsCount"]);
+
+        // Bob hears about the draft only once it is published.
+        let notifications = || test::TestRequest::get().uri("/api/user/notifications");
+        let (_, body) = call(&app, as_user(notifications(), &bob).to_request()).await;
+        assert_eq!(1, body["notificationsCount"]);
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/articles/{}", draft_slug))
+            .set_json(json!({ "article": { "status": "published" } }));
+        call(&app, as_user(req, &alice).to_request()).await;
+        let (_, body) = call(&app, as_user(notifications(), &bob).to_request()).await;
+        assert_eq!(2, body["notificationsCount"]);
+        let (_, body) = call(&app, as_user(mentions(), &bob).to_request()).await;
+        assert_eq!(2, body["mentionsCount"]);
+    }
+
+    #[actix_web::test]
+    async fn notifications_are_collapsed_and_can_be_marked_read() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let carol = add_user(&state, "carol");
+        let app = init_app!(state);
+        let req = as_user(create_article("Notified", "published"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+
+        let favorite =
+            || test::TestRequest::post().uri(&format!("/api/articles/{}/favorite", slug));
+        call(&app, as_user(favorite(), &bob).to_request()).await;
+        call(&app, as_user(favorite(), &carol).to_request()).await;
+        let req = as_user(create_comment(&slug, "@alice nice"), &bob);
+        call(&app, req.to_request()).await;
+        let req = test::TestRequest::post().uri("/api/profiles/alice/follow");
+        call(&app, as_user(req, &bob).to_request()).await;
+        // Nobody hears about their own actions.
+        let req = as_user(create_comment(&slug, "thanks"), &alice);
+        call(&app, req.to_request()).await;
+
+        let notifications = || test::TestRequest::get().uri("/api/user/notifications");
+        let (status, body) = call(&app, as_user(notifications(), &alice).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(4, body["notificationsCount"]);
+        assert_eq!(4, body["unreadCount"]);
+        let list = body["notifications"].as_array().unwrap();
+        let group = |kind: &str| list.iter().find(|n| n["kind"] == kind).unwrap().clone();
+        let favorites = group("favorite");
+        assert_eq!(2, favorites["eventsCount"]);
+        assert_eq!(2, favorites["actorsCount"]);
+        assert_eq!(slug.as_str(), favorites["article"]["slug"]);
+        assert_eq!("@alice nice", group("mention")["comment"]["body"]);
+        assert_eq!("bob", group("follow")["actors"][0]["username"]);
+        assert_eq!(Value::Null, group("follow")["article"]);
+
+        let req = test::TestRequest::post().uri(&format!(
+            "/api/user/notifications/{}/read",
+            favorites["id"].as_str().unwrap()
+        ));
+        let (status, body) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(3, body["unreadCount"]);
+        // Someone else's notification can't be marked read.
+        let req = test::TestRequest::post().uri(&format!(
+            "/api/user/notifications/{}/read",
+            group("comment")["id"].as_str().unwrap()
+        ));
+        let (status, _) = call(&app, as_user(req, &bob).to_request()).await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+
+        let req = test::TestRequest::post().uri("/api/user/notifications/read");
+        let (_, body) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(0, body["unreadCount"]);
+        let (_, body) = call(&app, as_user(notifications(), &alice).to_request()).await;
+        assert_eq!(4, body["notificationsCount"]);
+        assert_eq!(false, body["notifications"][0]["unread"]);
+        let (_, body) = call(&app, as_user(notifications(), &bob).to_request()).await;
+        assert_eq!(0, body["notificationsCount"]);
+    }
+
+    #[actix_web::test]
+    async fn events_are_streamed_to_the_clients_they_concern() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let app = init_app!(state);
+        let req = as_user(create_article("Streamed", "published"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+        let req = test::TestRequest::post().uri("/api/profiles/alice/follow");
+        call(&app, as_user(req, &bob).to_request()).await;
+
+        let stream =
+            |uri: &str, token: &str| as_user(test::TestRequest::get().uri(uri), token).to_request();
+        let res = test::call_service(
+            &app,
+            stream(&format!("/api/stream?articles={}", slug), &alice),
+        )
+        .await;
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!(
+            "text/event-stream",
+            res.headers().get("content-type").unwrap()
+        );
+        let mut alice_events = res.into_body();
+        let res = test::call_service(&app, stream("/api/stream", &bob)).await;
+        let mut bob_events = res.into_body();
+        let (status, _) = call(
+            &app,
+            test::TestRequest::get().uri("/api/stream").to_request(),
+        )
+        .await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+
+        let req = test::TestRequest::post().uri(&format!("/api/articles/{}/favorite", slug));
+        call(&app, as_user(req, &bob).to_request()).await;
+        let req = as_user(create_comment(&slug, "first"), &bob);
+        let (_, body) = call(&app, req.to_request()).await;
+        assert_eq!(
+            ("notification".to_string(), json!({ "kind": "favorite" })),
+            next_event(&mut alice_events).await
+        );
+        assert_eq!(
+            (
+                "favorites".to_string(),
+                json!({ "article": slug, "favoritesCount": 1 })
+            ),
+            next_event(&mut alice_events).await
+        );
+        assert_eq!("notification", next_event(&mut alice_events).await.0);
+        assert_eq!(
+            (
+                "comment".to_string(),
+                json!({ "article": slug, "commentId": body["comment"]["id"] })
+            ),
+            next_event(&mut alice_events).await
+        );
+
+        // Bob isn't watching the article, but follows its author.
+        let req = as_user(create_article("Streamed again", "published"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        assert_eq!(
+            (
+                "feed".to_string(),
+                json!({ "article": body["article"]["slug"] })
+            ),
+            next_event(&mut bob_events).await
+        );
+    }
+
+    // A local HTTP server standing in for a webhook receiver. It records the event,
+    // signature and body of every request, and answers with `status`.
+    struct Receiver {
+        url: String,
+        requests: Arc<Mutex<Vec<(String, String, String)>>>,
+        status: Arc<AtomicU16>,
+        server: actix_web::dev::ServerHandle,
+    }
+
+    fn start_receiver() -> Receiver {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let status = Arc::new(AtomicU16::new(200));
+        let (recorded, answer) = (requests.clone(), status.clone());
+        let server = actix_web::HttpServer::new(move || {
+            let (recorded, answer) = (recorded.clone(), answer.clone());
+            App::new().default_service(web::to(move |req: actix_web::HttpRequest, body: String| {
+                let header = |name: &str| {
+                    req.headers()
+                        .get(name)
+                        .unwrap()
+                        .to_str()
+                        .unwrap()
+                        .to_owned()
+                };
+                let request = (
+                    header("X-Conduit-Event"),
+                    header("X-Conduit-Signature"),
+                    body,
+                );
+                recorded.lock().unwrap().push(request);
+                let status = StatusCode::from_u16(answer.load(Ordering::SeqCst)).unwrap();
+                async move { actix_web::HttpResponse::build(status).finish() }
+            }))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let url = format!("http://{}/hooks", server.addrs()[0]);
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+        Receiver {
+            url,
+            requests,
+            status,
+            server: handle,
+        }
+    }
+
+    #[actix_web::test]
+    async fn webhooks_are_signed_retried_and_logged() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let pool = state.pool.clone();
+        let webhooks_config = state.config.webhooks.clone();
+        let receiver = start_receiver();
+        let app = init_app!(state);
+        let register = |url: &str, events: Value| {
+            test::TestRequest::post()
+                .uri("/api/user/webhooks")
+                .set_json(json!({ "webhook": { "url": url, "events": events } }))
+        };
+        let req = register("ftp://example.com", json!(["article.created"]));
+        let (status, _) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+        let req = register(&receiver.url, json!(["article.exploded"]));
+        let (status, _) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+        let events = json!(["article.created", "comment.created", "favorite.created"]);
+        let req = register(&receiver.url, events);
+        let (status, body) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        let webhook_id = body["webhook"]["id"].as_str().unwrap().to_owned();
+        let secret = body["webhook"]["secret"].as_str().unwrap().to_owned();
+        let (_, body) = call(
+            &app,
+            as_user(test::TestRequest::get().uri("/api/user/webhooks"), &alice).to_request(),
+        )
+        .await;
+        assert_eq!(Value::Null, body["webhooks"][0]["secret"]);
+
+        let req = as_user(create_article("Hooked", "published"), &alice);
+        let (_, body) = call(&app, req.to_request()).await;
+        let slug = body["article"]["slug"].as_str().unwrap().to_owned();
+        let req = as_user(create_comment(&slug, "hi"), &bob);
+        call(&app, req.to_request()).await;
+        let req = test::TestRequest::post().uri(&format!("/api/articles/{}/favorite", slug));
+        call(&app, as_user(req, &bob).to_request()).await;
+
+        let log = format!("/api/user/webhooks/{}/deliveries", webhook_id);
+        let deliveries = || as_user(test::TestRequest::get().uri(&log), &alice).to_request();
+        let (_, body) = call(&app, deliveries()).await;
+        assert_eq!(3, body["deliveriesCount"]);
+        assert_eq!("pending", body["deliveries"][0]["status"]);
+        let (status, _) = call(
+            &app,
+            as_user(test::TestRequest::get().uri(&log), &bob).to_request(),
+        )
+        .await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+
+        let client = app::webhook::worker::loopback_client(&webhooks_config);
+        let deliver = || app::webhook::worker::deliver_due(&pool, &client);
+        assert_eq!(3, deliver().await.unwrap());
+        let requests = receiver.requests.lock().unwrap().clone();
+        let mut received = requests
+            .iter()
+            .map(|(event, _, _)| event.as_str())
+            .collect::<Vec<_>>();
+        received.sort();
+        assert_eq!(
+            vec!["article.created", "comment.created", "favorite.created"],
+            received
+        );
+        for (event, signature, body) in &requests {
+            let timestamp = signature[2..signature.find(',').unwrap()].parse().unwrap();
+            assert_eq!(
+                &app::webhook::service::signature(&secret, timestamp, body),
+                signature
+            );
+            let payload = serde_json::from_str::<Value>(body).unwrap();
+            assert_eq!(event.as_str(), payload["event"]);
+        }
+        let comment_payload = &requests
+            .iter()
+            .find(|(event, _, _)| event == "comment.created")
+            .unwrap()
+            .2;
+        assert_eq!(
+            "hi",
+            serde_json::from_str::<Value>(comment_payload).unwrap()["comment"]["body"]
+        );
+        // Deliveries queued in one transaction share a timestamp, so their order is arbitrary.
+        let undelivered = |body: &Value| {
+            body["deliveries"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|delivery| delivery["status"] != "delivered")
+                .cloned()
+        };
+        let (_, body) = call(&app, deliveries()).await;
+        assert_eq!(None, undelivered(&body));
+        assert_eq!(200, body["deliveries"][0]["responseStatus"]);
+
+        // Failures are retried later, then given up on.
+        receiver.status.store(500, Ordering::SeqCst);
+        call(
+            &app,
+            as_user(create_comment(&slug, "again"), &bob).to_request(),
+        )
+        .await;
+        assert_eq!(1, deliver().await.unwrap());
+        assert_eq!(0, deliver().await.unwrap());
+        let (_, body) = call(&app, deliveries()).await;
+        let failed = undelivered(&body).unwrap();
+        assert_eq!(
+            ("pending", 1, 500),
+            (
+                failed["status"].as_str().unwrap(),
+                failed["attempts"].as_i64().unwrap(),
+                failed["responseStatus"].as_i64().unwrap()
+            )
+        );
+        assert!(failed["nextAttemptAt"].is_string());
+        let conn = pool.get().unwrap();
+        diesel::sql_query(format!(
+            "UPDATE webhook_deliveries SET attempts = {}, next_attempt_at = next_attempt_at - INTERVAL '1 day' WHERE status = 'pending'",
+            webhooks_config.max_attempts - 1
+        ))
+        .execute(&conn)
+        .unwrap();
+        drop(conn);
+        assert_eq!(1, deliver().await.unwrap());
+        let (_, body) = call(&app, deliveries()).await;
+        let dead = undelivered(&body).unwrap();
+        assert_eq!("dead", dead["status"]);
+        assert_eq!(Value::Null, dead["nextAttemptAt"]);
+
+        // A dead delivery can be sent again by hand.
+        receiver.status.store(204, Ordering::SeqCst);
+        let delivery_id = dead["id"].as_str().unwrap();
+        let req = test::TestRequest::post().uri(&format!("{}/{}/retry", log, delivery_id));
+        let (_, body) = call(&app, as_user(req, &alice).to_request()).await;
+        assert_eq!(
+            ("pending", 0),
+            (
+                body["delivery"]["status"].as_str().unwrap(),
+                body["delivery"]["attempts"].as_i64().unwrap()
+            )
+        );
+        assert_eq!(1, deliver().await.unwrap());
+        let (_, body) = call(&app, deliveries()).await;
+        assert_eq!(4, body["deliveriesCount"]);
+        assert_eq!(None, undelivered(&body));
+        receiver.server.stop(false).await;
+    }
+
+    #[actix_web::test]
+    async fn webhooks_never_reach_private_addresses() {
+        let (state, alice, _) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let pool = state.pool.clone();
+        let webhooks_config = state.config.webhooks.clone();
+        let receiver = start_receiver();
+        let app = init_app!(state);
+        // The same receiver, by address and by a name that resolves to loopback.
+        let by_name = receiver.url.replace("127.0.0.1", "localhost");
+        for url in [&receiver.url, &by_name] {
+            let req = test::TestRequest::post()
+                .uri("/api/user/webhooks")
+                .set_json(json!({ "webhook": { "url": url, "events": ["article.created"] } }));
+            let (status, _) = call(&app, as_user(req, &alice).to_request()).await;
+            assert_eq!(StatusCode::OK, status);
+        }
+        let req = as_user(create_article("Probing", "published"), &alice);
+        call(&app, req.to_request()).await;
+
+        let client = app::webhook::worker::client(&webhooks_config);
+        assert_eq!(
+            2,
+            app::webhook::worker::deliver_due(&pool, &client)
+                .await
+                .unwrap()
+        );
+        assert!(receiver.requests.lock().unwrap().is_empty());
+        let (_, body) = call(
+            &app,
+            as_user(test::TestRequest::get().uri("/api/user/webhooks"), &alice).to_request(),
+        )
+        .await;
+        for webhook in body["webhooks"].as_array().unwrap() {
+            let log = format!(
+                "/api/user/webhooks/{}/deliveries",
+                webhook["id"].as_str().unwrap()
+            );
+            let req = as_user(test::TestRequest::get().uri(&log), &alice);
+            let (_, body) = call(&app, req.to_request()).await;
+            let delivery = &body["deliveries"][0];
+            assert_eq!(Value::Null, delivery["responseStatus"]);
+            let error = delivery["lastError"].as_str().unwrap();
+            assert!(
+                error.contains("not a public address") || error.contains("has no public address"),
+                "{}",
+                error
+            );
+        }
+        receiver.server.stop(false).await;
+    }
+
+    #[actix_web::test]
+    async fn digests_send_new_articles_from_followed_authors_once() {
+        let (state, alice, bob) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let carol = add_user(&state, "carol");
+        let pool = state.pool.clone();
+        let app = init_app!(state);
+        let digest = |frequency: Value| {
+            test::TestRequest::put()
+                .uri("/api/user/digest")
+                .set_json(json!({ "digest": { "frequency": frequency } }))
+        };
+        let (status, _) = call(&app, as_user(digest(json!("hourly")), &bob).to_request()).await;
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+        let (_, body) = call(&app, as_user(digest(json!("daily")), &bob).to_request()).await;
+        assert_eq!(
+            json!({ "frequency": "daily", "lastSentAt": null }),
+            body["digest"]
+        );
+        let req = test::TestRequest::post().uri("/api/profiles/alice/follow");
+        call(&app, as_user(req, &bob).to_request()).await;
+        let req = as_user(create_article("Old news", "published"), &alice);
+        call(&app, req.to_request()).await;
+
+        let dir = std::env::temp_dir().join(format!("conduit-digests-{}", uuid::Uuid::new_v4()));
+        let mailer = crate::utils::mailer::FileMailer::new(&dir);
+        let send = |now: chrono::NaiveDateTime| {
+            let conn = pool.get().unwrap();
+            app::digest::service::send_due_digests(&conn, &mailer, "http://conduit.test", now)
+                .unwrap()
+        };
+        let now = chrono::Utc::now().naive_utc();
+        // Not due until a day after subscribing.
+        assert_eq!(0, send(now));
+        let conn = pool.get().unwrap();
+        diesel::sql_query(
+            "UPDATE digest_subscriptions SET created_at = created_at - INTERVAL '2 days'",
+        )
+        .execute(&conn)
+        .unwrap();
+        drop(conn);
+        assert_eq!(1, send(now));
+        let sent = mailer.sent();
+        assert_eq!("bob@example.com", sent[0].to);
+        assert_eq!("New on Conduit: Old news", sent[0].subject);
+        assert!(sent[0]
+            .text
+            .contains("http://conduit.test/article/old-news"));
+        assert!(sent[0]
+            .html
+            .contains("<a href=\"http://conduit.test/article/old-news\">"));
+        assert_eq!(0, send(now));
+
+        let req = as_user(create_article("Fresh <news>", "published"), &alice);
+        call(&app, req.to_request()).await;
+        // Published late, like a scheduled article the scheduler got to after the last
+        // digest went out.
+        let req = as_user(create_article("Late news", "published"), &alice);
+        call(&app, req.to_request()).await;
+        diesel::sql_query(
+            "UPDATE articles SET published_at = published_at - INTERVAL '1 hour' WHERE slug = 'late-news'",
+        )
+        .execute(&pool.get().unwrap())
+        .unwrap();
+        // Followed tags and bob's own articles are in his feed, but not in the digest of the
+        // people he follows.
+        let req = test::TestRequest::post().uri("/api/tags/rust/follow");
+        call(&app, as_user(req, &bob).to_request()).await;
+        for (title, token) in [("Tagged by carol", &carol), ("Written by bob", &bob)] {
+            let req = as_user(test::TestRequest::post(), token)
+                .uri("/api/articles")
+                .set_json(json!({ "article": {
+                    "title": title,
+                    "description": "d",
+                    "body": "b",
+                    "tagList": ["rust"],
+                }}));
+            let (status, _) = call(&app, req.to_request()).await;
+            assert_eq!(StatusCode::OK, status);
+        }
+        let tomorrow = now + chrono::Duration::days(1);
+        assert_eq!(1, send(tomorrow));
+        let sent = mailer.sent();
+        assert_eq!(2, sent.len());
+        assert_eq!("2 new articles from people you follow", sent[1].subject);
+        assert!(sent[1].html.contains("Fresh &lt;news&gt;"));
+        assert!(sent[1].text.contains("Late news"));
+        assert!(!sent[1].text.contains("Old news"));
+        assert!(!sent[1].text.contains("Tagged by carol"));
+        assert!(!sent[1].text.contains("Written by bob"));
+        let (_, body) = call(
+            &app,
+            as_user(test::TestRequest::get().uri("/api/user/digest"), &bob).to_request(),
+        )
+        .await;
+        assert_ne!(Value::Null, body["digest"]["lastSentAt"]);
+
+        // Nothing new: no email, but the window still moves on.
+        assert_eq!(0, send(tomorrow + chrono::Duration::days(1)));
+        let (_, body) = call(&app, as_user(digest(Value::Null), &bob).to_request()).await;
+        assert_eq!(Value::Null, body["digest"]["frequency"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn signing_in_needs_the_right_password() {
+        let (state, _, _) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let app = init_app!(state);
+        let req = test::TestRequest::post()
+            .uri("/api/users")
+            .set_json(json!({ "user": {
+                "username": "erin",
+                "email": "erin@example.com",
+                "password": "password",
+            }}));
+        call(&app, req.to_request()).await;
+        let login = |password: &str| {
+            test::TestRequest::post()
+                .uri("/api/users/login")
+                .set_json(json!({ "user": { "email": "erin@example.com", "password": password } }))
+                .to_request()
+        };
+        let (status, body) = call(&app, login("wrong password")).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert_eq!(Value::Null, body["user"]);
+        let (status, body) = call(&app, login("password")).await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!("erin", body["user"]["username"]);
+    }
+
+    #[actix_web::test]
+    async fn disabled_accounts_cannot_sign_in_or_use_their_tokens() {
+        let (state, alice, _) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let pool = state.pool.clone();
+        let webhooks_config = state.config.webhooks.clone();
+        let receiver = start_receiver();
+        let app = init_app!(state);
+        let user = json!({ "user": {
+            "username": "dave",
+            "email": "dave@example.com",
+            "password": "password",
+        }});
+        let req = test::TestRequest::post().uri("/api/users").set_json(&user);
+        let (_, body) = call(&app, req.to_request()).await;
+        let token = body["user"]["token"].as_str().unwrap().to_owned();
+        let me = || test::TestRequest::get().uri("/api/user");
+        let login = || {
+            test::TestRequest::post()
+                .uri("/api/users/login")
+                .set_json(&user)
+        };
+
+        // Dave is subscribed to digests, has a webhook with a delivery waiting, and a
+        // published article that makes him a suggestion for Alice.
+        let req = test::TestRequest::put()
+            .uri("/api/user/digest")
+            .set_json(json!({ "digest": { "frequency": "daily" } }));
+        call(&app, as_user(req, &token).to_request()).await;
+        let req = test::TestRequest::post()
+            .uri("/api/user/webhooks")
+            .set_json(json!({ "webhook": { "url": receiver.url, "events": ["article.created"] } }));
+        call(&app, as_user(req, &token).to_request()).await;
+        call(
+            &app,
+            as_user(create_article("Dave's", "published"), &token).to_request(),
+        )
+        .await;
+        diesel::sql_query(
+            "UPDATE digest_subscriptions SET created_at = created_at - INTERVAL '2 days'",
+        )
+        .execute(&pool.get().unwrap())
+        .unwrap();
+        let tomorrow = chrono::Utc::now().naive_utc() + chrono::Duration::days(1);
+        let digest_due = || {
+            app::digest::model::DigestSubscription::fetch_due_user_ids(
+                &pool.get().unwrap(),
+                tomorrow,
+            )
+            .unwrap()
+        };
+        let suggested = || async {
+            let req = test::TestRequest::get().uri("/api/profiles/suggestions");
+            let (_, body) = call(&app, as_user(req, &alice).to_request()).await;
+            body["profiles"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|profile| profile["username"] == "dave")
+        };
+        let client = app::webhook::worker::loopback_client(&webhooks_config);
+
+        let dave = User::find_by_username(&pool.get().unwrap(), "dave").unwrap();
+        let now = chrono::Utc::now().naive_utc();
+        User::set_disabled_at(&pool.get().unwrap(), dave.id, Some(now)).unwrap();
+        let (status, _) = call(&app, as_user(me(), &token).to_request()).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        let (status, _) = call(&app, login().to_request()).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, status);
+        assert!(!digest_due().contains(&dave.id));
+        assert_eq!(
+            0,
+            app::webhook::worker::deliver_due(&pool, &client)
+                .await
+                .unwrap()
+        );
+        assert!(!suggested().await);
+
+        User::set_disabled_at(&pool.get().unwrap(), dave.id, None).unwrap();
+        let (status, _) = call(&app, as_user(me(), &token).to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        let (status, _) = call(&app, login().to_request()).await;
+        assert_eq!(StatusCode::OK, status);
+        assert!(digest_due().contains(&dave.id));
+        assert_eq!(
+            1,
+            app::webhook::worker::deliver_due(&pool, &client)
+                .await
+                .unwrap()
+        );
+        assert!(suggested().await);
+        receiver.server.stop(false).await;
+    }
+}
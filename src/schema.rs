@@ -1,3 +1,18 @@
+table! {
+    article_revisions (id) {
+        id -> Uuid,
+        article_id -> Uuid,
+        author_id -> Uuid,
+        title -> Text,
+        description -> Text,
+        body -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        body_html -> Nullable<Text>,
+        toc -> Nullable<Jsonb>,
+    }
+}
+
 table! {
     articles (id) {
         id -> Uuid,
@@ -8,6 +23,20 @@ table! {
         body -> Text,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        status -> Text,
+        published_at -> Nullable<Timestamp>,
+        comments_locked -> Bool,
+        comments_followers_only -> Bool,
+        slug_base -> Text,
+    }
+}
+
+table! {
+    comment_revisions (id) {
+        id -> Uuid,
+        comment_id -> Uuid,
+        body -> Text,
+        created_at -> Timestamp,
     }
 }
 
@@ -17,7 +46,31 @@ table! {
         article_id -> Uuid,
         author_id -> Uuid,
         body -> Text,
-        create_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        parent_id -> Nullable<Uuid>,
+        root_id -> Nullable<Uuid>,
+        depth -> Int4,
+        deleted_at -> Nullable<Timestamp>,
+        edited_at -> Nullable<Timestamp>,
+        hidden_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    digest_articles (user_id, article_id) {
+        user_id -> Uuid,
+        article_id -> Uuid,
+        sent_at -> Timestamp,
+    }
+}
+
+table! {
+    digest_subscriptions (user_id) {
+        user_id -> Uuid,
+        frequency -> Text,
+        last_sent_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
         updated_at -> Timestamp,
     }
 }
@@ -41,6 +94,49 @@ table! {
     }
 }
 
+table! {
+    mentions (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        author_id -> Uuid,
+        article_id -> Uuid,
+        comment_id -> Nullable<Uuid>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    notifications (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        actor_id -> Uuid,
+        kind -> Text,
+        article_id -> Nullable<Uuid>,
+        comment_id -> Nullable<Uuid>,
+        group_key -> Text,
+        read_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    slug_history (slug) {
+        slug -> Text,
+        article_id -> Uuid,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    tag_follows (user_id, tag_name) {
+        user_id -> Uuid,
+        tag_name -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     tags (id) {
         id -> Uuid,
@@ -61,21 +157,77 @@ table! {
         image -> Nullable<Text>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        role -> Text,
+        disabled_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    webhook_deliveries (id) {
+        id -> Uuid,
+        webhook_id -> Uuid,
+        event -> Text,
+        payload -> Text,
+        status -> Text,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        last_attempt_at -> Nullable<Timestamp>,
+        response_status -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        delivered_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    webhooks (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        url -> Text,
+        secret -> Text,
+        events -> Array<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
+joinable!(article_revisions -> articles (article_id));
+joinable!(article_revisions -> users (author_id));
 joinable!(articles -> users (author_id));
+joinable!(comment_revisions -> comments (comment_id));
 joinable!(comments -> articles (article_id));
 joinable!(comments -> users (author_id));
+joinable!(digest_articles -> articles (article_id));
+joinable!(digest_articles -> users (user_id));
+joinable!(digest_subscriptions -> users (user_id));
 joinable!(favorites -> articles (article_id));
 joinable!(favorites -> users (user_id));
+joinable!(mentions -> articles (article_id));
+joinable!(mentions -> comments (comment_id));
+joinable!(mentions -> users (author_id));
+joinable!(notifications -> articles (article_id));
+joinable!(notifications -> comments (comment_id));
+joinable!(slug_history -> articles (article_id));
+joinable!(tag_follows -> users (user_id));
 joinable!(tags -> articles (article_id));
+joinable!(webhook_deliveries -> webhooks (webhook_id));
+joinable!(webhooks -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
+    article_revisions,
     articles,
+    comment_revisions,
     comments,
+    digest_articles,
+    digest_subscriptions,
     favorites,
     follows,
+    mentions,
+    notifications,
+    slug_history,
+    tag_follows,
     tags,
     users,
+    webhook_deliveries,
+    webhooks,
 );
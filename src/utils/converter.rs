@@ -4,6 +4,18 @@ pub fn to_kebab(text: &str) -> String {
     text.to_case(Case::Kebab)
 }
 
+// Transliterates to ASCII first so "Crème Brûlée" and "Привет" still produce readable
+// slugs, then drops punctuation before kebab-casing.
+pub fn to_slug(text: &str) -> String {
+    let ascii = deunicode::deunicode(text);
+    let words = ascii
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    to_kebab(&words)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -12,4 +24,13 @@ mod tests {
     fn str_to_kebab() {
         assert_eq!("this-is-blog-title", to_kebab("this is blog title"));
     }
+
+    #[test]
+    fn str_to_slug() {
+        assert_eq!("hello-world", to_slug("Hello, World!"));
+        assert_eq!("creme-brulee-recipe", to_slug("Crème Brûlée -- recipe"));
+        assert_eq!("privet-mir", to_slug("Привет мир"));
+        assert_eq!("top-10-rust-tips", to_slug("Top 10 Rust tips"));
+        assert_eq!("", to_slug("!!!"));
+    }
 }
@@ -0,0 +1,58 @@
+use crate::error::AppError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::NaiveDateTime;
+use serde_json::json;
+use uuid::Uuid;
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.6f";
+
+// Keyset position in a list ordered by publication time and then `id`. Clients only ever see
+// the encoded form, so the layout can change without breaking them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cursor {
+    pub published_at: NaiveDateTime,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let raw = format!("{}|{}", self.published_at.format(TIMESTAMP_FORMAT), self.id);
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub fn decode(encoded: &str) -> Result<Self, AppError> {
+        let invalid = || {
+            AppError::UnprocessableEntity(json!({
+                "error": "cursor is invalid"
+            }))
+        };
+        let raw = URL_SAFE_NO_PAD.decode(encoded).map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+        let (published_at, id) = raw.split_once('|').ok_or_else(invalid)?;
+        Ok(Self {
+            published_at: NaiveDateTime::parse_from_str(published_at, TIMESTAMP_FORMAT)
+                .map_err(|_| invalid())?,
+            id: Uuid::parse_str(id).map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_round_trips() {
+        let cursor = Cursor {
+            published_at: NaiveDateTime::parse_from_str(
+                "2024-01-07T09:00:00.123456",
+                TIMESTAMP_FORMAT,
+            )
+            .unwrap(),
+            id: Uuid::new_v4(),
+        };
+        assert_eq!(cursor, Cursor::decode(&cursor.encode()).unwrap());
+        assert!(Cursor::decode("not a cursor").is_err());
+    }
+}
@@ -1,18 +1,285 @@
-use crate::constants::env_key;
+use crate::config::DatabaseConfig;
+use crate::error::AppError;
 use diesel::pg::PgConnection;
 use diesel::r2d2::{ConnectionManager, Pool, PoolError};
-use dotenv::dotenv;
-use std::env;
+use diesel::Connection;
+use std::cell::RefCell;
 
 pub type DbPool = Pool<ConnectionManager<PgConnection>>;
 
-fn init_pool(database_url: &str) -> Result<DbPool, PoolError> {
-    let manager = ConnectionManager::<PgConnection>::new(database_url);
-    Pool::builder().build(manager)
+pub fn establish_connection(config: &DatabaseConfig) -> Result<DbPool, PoolError> {
+    let manager = ConnectionManager::<PgConnection>::new(&config.url);
+    Pool::builder()
+        .max_size(config.pool_max_size)
+        .min_idle(config.pool_min_idle)
+        .connection_timeout(config.connect_timeout())
+        .build(manager)
 }
 
-pub fn establish_connection() -> DbPool {
-    dotenv().ok();
-    let database_url = env::var(env_key::DATABASE_URL).expect("DATABASE_URL must be set");
-    init_pool(&database_url).expect("Failed to create pool")
+type AfterCommit = Box<dyn FnOnce()>;
+
+thread_local! {
+    // Callbacks waiting on each unit of work open on this thread, innermost last.
+    static AFTER_COMMIT: RefCell<Vec<Vec<AfterCommit>>> = const { RefCell::new(vec![]) };
+}
+
+// Drops the callbacks of a unit of work that panicked, so that the next unit on this thread
+// does not mistake itself for a nested one.
+struct AfterCommitFrame {
+    depth: usize,
+}
+
+impl Drop for AfterCommitFrame {
+    fn drop(&mut self) {
+        AFTER_COMMIT.with(|frames| frames.borrow_mut().truncate(self.depth));
+    }
+}
+
+// Runs `work` as one unit of work: everything it writes is committed together, or rolled
+// back together as soon as it returns an error. Units started inside another unit become
+// savepoints, so services can call each other freely.
+pub fn unit_of_work<T, F>(conn: &PgConnection, work: F) -> Result<T, AppError>
+where
+    F: FnOnce() -> Result<T, AppError>,
+{
+    let frame = AFTER_COMMIT.with(|frames| {
+        let mut frames = frames.borrow_mut();
+        frames.push(vec![]);
+        AfterCommitFrame {
+            depth: frames.len() - 1,
+        }
+    });
+    let result = conn.transaction(work);
+    let committed = AFTER_COMMIT.with(|frames| {
+        let mut frames = frames.borrow_mut();
+        let callbacks = frames.pop().unwrap_or_default();
+        match frames.last_mut() {
+            _ if result.is_err() => vec![],
+            // A savepoint: the callbacks wait for the enclosing unit to commit.
+            Some(enclosing) => {
+                enclosing.extend(callbacks);
+                vec![]
+            }
+            None => callbacks,
+        }
+    });
+    drop(frame);
+    for callback in committed {
+        callback();
+    }
+    result
+}
+
+// Runs `callback` once the enclosing unit of work has committed, or drops it if the unit
+// rolls back. Outside of a unit of work every statement commits on its own, so it runs
+// right away.
+pub fn after_commit<F>(callback: F)
+where
+    F: FnOnce() + 'static,
+{
+    let callback = AFTER_COMMIT.with(|frames| match frames.borrow_mut().last_mut() {
+        Some(frame) => {
+            frame.push(Box::new(callback));
+            None
+        }
+        None => Some(callback),
+    });
+    if let Some(callback) = callback {
+        callback();
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static ARMED_FAIL_POINT: std::cell::Cell<Option<&'static str>> = const { std::cell::Cell::new(None) };
+}
+
+// Named step inside a unit of work where tests can inject a failure. Call sites are marked
+// `#[cfg(test)]` as well, so none of this is compiled into the server.
+#[cfg(test)]
+pub fn fail_point(name: &'static str) -> Result<(), AppError> {
+    if ARMED_FAIL_POINT.with(|armed| armed.get() == Some(name)) {
+        return Err(AppError::InternalServerError);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod testing {
+    use super::{DbPool, ARMED_FAIL_POINT};
+    use crate::app::user::model::User;
+    use crate::constants::env_key;
+    use crate::schema::users;
+    use diesel::connection::SimpleConnection;
+    use diesel::pg::PgConnection;
+    use diesel::prelude::*;
+    use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool};
+
+    // Connection for tests that need Postgres. Everything runs in a test transaction that
+    // is never committed. Returns None (and the test is skipped) when DATABASE_URL is unset.
+    pub fn connection() -> Option<PgConnection> {
+        let database_url = std::env::var(env_key::DATABASE_URL).ok()?;
+        let conn =
+            PgConnection::establish(&database_url).expect("could not connect to DATABASE_URL");
+        conn.begin_test_transaction()
+            .expect("could not begin test transaction");
+        Some(conn)
+    }
+
+    #[derive(Debug)]
+    struct TestTransaction;
+
+    impl CustomizeConnection<PgConnection, diesel::r2d2::Error> for TestTransaction {
+        fn on_acquire(&self, conn: &mut PgConnection) -> Result<(), diesel::r2d2::Error> {
+            conn.begin_test_transaction()
+                .map_err(diesel::r2d2::Error::QueryError)
+        }
+    }
+
+    // Pool for tests that go through the HTTP layer. It holds a single connection inside a
+    // test transaction, so requests see each other's writes and nothing is ever committed.
+    // Returns None (and the test is skipped) when DATABASE_URL is unset.
+    pub fn pool() -> Option<DbPool> {
+        let database_url = std::env::var(env_key::DATABASE_URL).ok()?;
+        let pool = Pool::builder()
+            .max_size(1)
+            .connection_customizer(Box::new(TestTransaction))
+            .build(ConnectionManager::<PgConnection>::new(database_url))
+            .expect("could not connect to DATABASE_URL");
+        Some(pool)
+    }
+
+    pub fn insert_user(conn: &PgConnection, username: &str) -> User {
+        diesel::insert_into(users::table)
+            .values((
+                users::username.eq(username),
+                users::email.eq(format!("{}@example.com", username)),
+                users::password.eq("not-a-real-hash"),
+            ))
+            .get_result::<User>(conn)
+            .expect("could not insert user")
+    }
+
+    #[derive(QueryableByName)]
+    struct TableScans {
+        #[sql_type = "diesel::sql_types::BigInt"]
+        scans: i64,
+    }
+
+    // How many times Postgres has scanned `table` so far in the current transaction. Tests
+    // use it to check that a page of results costs a fixed number of queries rather than one
+    // per row. Call `predictable_plans` first so every table a query reads is scanned
+    // exactly once, whatever the amount of data.
+    pub fn table_scans(conn: &PgConnection, table: &str) -> i64 {
+        diesel::sql_query(
+            "SELECT (COALESCE(SUM(seq_scan), 0) + COALESCE(SUM(idx_scan), 0))::BIGINT AS scans \
+             FROM pg_stat_xact_user_tables WHERE relname = $1",
+        )
+        .bind::<diesel::sql_types::Text, _>(table)
+        .get_result::<TableScans>(conn)
+        .expect("could not read table statistics")
+        .scans
+    }
+
+    // Forces sequential scans and hash joins for the rest of the test transaction. Index
+    // scans are counted once per array element or per outer row of a nested loop, which
+    // would make `table_scans` depend on the data instead of the number of queries.
+    pub fn predictable_plans(conn: &PgConnection) {
+        conn.batch_execute(
+            "SET LOCAL enable_indexscan = off; \
+             SET LOCAL enable_bitmapscan = off; \
+             SET LOCAL enable_indexonlyscan = off; \
+             SET LOCAL enable_nestloop = off;",
+        )
+        .expect("could not change planner settings");
+    }
+
+    // Makes `fail_point(name)` fail on this thread until the returned guard is dropped.
+    pub fn arm_fail_point(name: &'static str) -> FailPointGuard {
+        ARMED_FAIL_POINT.with(|armed| armed.set(Some(name)));
+        FailPointGuard
+    }
+
+    pub struct FailPointGuard;
+
+    impl Drop for FailPointGuard {
+        fn drop(&mut self) {
+            ARMED_FAIL_POINT.with(|armed| armed.set(None));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::users;
+    use diesel::prelude::*;
+
+    #[test]
+    fn unit_of_work_rolls_back_on_error() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let result = unit_of_work(&conn, || {
+            testing::insert_user(&conn, "uow_rollback");
+            Err::<(), _>(AppError::InternalServerError)
+        });
+        assert!(result.is_err());
+        let count = users::table
+            .filter(users::username.eq("uow_rollback"))
+            .count()
+            .get_result::<i64>(&conn)
+            .unwrap();
+        assert_eq!(0, count);
+    }
+
+    #[test]
+    fn after_commit_waits_for_the_outermost_unit_and_skips_rollbacks() {
+        use std::rc::Rc;
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let ran = Rc::new(RefCell::new(vec![]));
+        let record = |name: &'static str| {
+            let ran = ran.clone();
+            move || ran.borrow_mut().push(name)
+        };
+        unit_of_work(&conn, || {
+            after_commit(record("outer"));
+            unit_of_work(&conn, || {
+                after_commit(record("inner"));
+                Ok(())
+            })?;
+            let _ = unit_of_work(&conn, || {
+                after_commit(record("rolled back"));
+                Err::<(), _>(AppError::InternalServerError)
+            });
+            assert!(ran.borrow().is_empty());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(vec!["outer", "inner"], *ran.borrow());
+
+        let _ = unit_of_work(&conn, || {
+            after_commit(record("failed"));
+            Err::<(), _>(AppError::InternalServerError)
+        });
+        after_commit(record("no unit"));
+        assert_eq!(vec!["outer", "inner", "no unit"], *ran.borrow());
+    }
+
+    #[test]
+    fn unit_of_work_commits_on_success() {
+        let conn = match testing::connection() {
+            Some(conn) => conn,
+            None => return,
+        };
+        let user = unit_of_work(&conn, || Ok(testing::insert_user(&conn, "uow_commit"))).unwrap();
+        assert!(users::table
+            .find(user.id)
+            .first::<crate::app::user::model::User>(&conn)
+            .is_ok());
+    }
 }
@@ -0,0 +1,183 @@
+use crate::utils::converter;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TocEntry {
+    pub level: u8,
+    pub id: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rendered {
+    pub html: String,
+    pub toc: Vec<TocEntry>,
+}
+
+// Prepended to every id in rendered HTML, so that author-chosen heading and footnote names
+// cannot clobber globals or other elements of the page the HTML is embedded in.
+pub const ID_PREFIX: &str = "user-content-";
+
+// Renders CommonMark with the GFM extensions (tables, strikethrough, task lists, footnotes)
+// into sanitized HTML. Every heading gets a unique anchor id, which the table of contents
+// links to.
+pub fn render(source: &str) -> Rendered {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_GFM;
+    let mut events = Parser::new_ext(source, options).collect::<Vec<_>>();
+
+    let mut toc = vec![];
+    let mut used_ids = HashMap::new();
+    let mut index = 0;
+    while index < events.len() {
+        if let Event::Start(Tag::Heading { level, .. }) = &events[index] {
+            let level = *level as u8;
+            let mut text = String::new();
+            let mut end = index + 1;
+            while end < events.len() && !matches!(events[end], Event::End(TagEnd::Heading(_))) {
+                if let Event::Text(part) | Event::Code(part) = &events[end] {
+                    text.push_str(part);
+                }
+                end += 1;
+            }
+            let anchor = unique_anchor(&text, &mut used_ids);
+            if let Event::Start(Tag::Heading { id, .. }) = &mut events[index] {
+                *id = Some(CowStr::from(anchor.clone()));
+            }
+            toc.push(TocEntry {
+                level,
+                id: format!("{}{}", ID_PREFIX, anchor),
+                text,
+            });
+            index = end;
+        }
+        index += 1;
+    }
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, events.into_iter());
+    Rendered {
+        html: sanitize(&unsafe_html),
+        toc,
+    }
+}
+
+// Ids are prefixed with `ID_PREFIX`, and in-page links are rewritten to match, which keeps
+// footnote references and links to headings working.
+fn sanitize(unsafe_html: &str) -> String {
+    ammonia::Builder::default()
+        .add_tags(&["input"])
+        .add_tag_attributes("input", &["type", "checked", "disabled"])
+        .add_tag_attributes("h1", &["id"])
+        .add_tag_attributes("h2", &["id"])
+        .add_tag_attributes("h3", &["id"])
+        .add_tag_attributes("h4", &["id"])
+        .add_tag_attributes("h5", &["id"])
+        .add_tag_attributes("h6", &["id"])
+        .add_tag_attributes("div", &["id"])
+        .add_allowed_classes("div", &["footnote-definition"])
+        .add_allowed_classes("sup", &["footnote-reference", "footnote-definition-label"])
+        .id_prefix(Some(ID_PREFIX))
+        .attribute_filter(|_, attribute, value| match value.strip_prefix('#') {
+            Some(fragment) if attribute == "href" && !fragment.starts_with(ID_PREFIX) => {
+                Some(format!("#{}{}", ID_PREFIX, fragment).into())
+            }
+            _ => Some(value.into()),
+        })
+        .clean(unsafe_html)
+        .to_string()
+}
+
+// Search snippets come straight from the stored Markdown source, so only the `<mark>` tags
+// added by `ts_headline` are kept.
+pub fn sanitize_highlight(unsafe_html: &str) -> String {
+    ammonia::Builder::empty()
+        .add_tags(&["mark"])
+        .clean(unsafe_html)
+        .to_string()
+}
+
+fn unique_anchor(text: &str, used_ids: &mut HashMap<String, usize>) -> String {
+    let base = match converter::to_slug(text) {
+        anchor if anchor.is_empty() => "section".to_string(),
+        anchor => anchor,
+    };
+    let seen = used_ids.entry(base.clone()).or_insert(0);
+    let anchor = if *seen == 0 {
+        base
+    } else {
+        format!("{}-{}", base, seen)
+    };
+    *seen += 1;
+    anchor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_strips_unsafe_html() {
+        let rendered = render("Hello <script>alert(1)</script> [x](javascript:alert(1))");
+        assert!(!rendered.html.contains("<script"));
+        assert!(!rendered.html.contains("javascript:"));
+        assert!(rendered.html.contains("Hello"));
+    }
+
+    #[test]
+    fn render_builds_toc_with_unique_anchors() {
+        let rendered = render("# Intro\n\ntext\n\n## Setup `cargo`\n\n## Setup cargo\n");
+        assert_eq!(
+            vec![
+                TocEntry {
+                    level: 1,
+                    id: "user-content-intro".to_string(),
+                    text: "Intro".to_string()
+                },
+                TocEntry {
+                    level: 2,
+                    id: "user-content-setup-cargo".to_string(),
+                    text: "Setup cargo".to_string()
+                },
+                TocEntry {
+                    level: 2,
+                    id: "user-content-setup-cargo-1".to_string(),
+                    text: "Setup cargo".to_string()
+                },
+            ],
+            rendered.toc
+        );
+        assert!(rendered
+            .html
+            .contains("<h1 id=\"user-content-intro\">Intro</h1>"));
+    }
+
+    #[test]
+    fn render_prefixes_ids_and_keeps_footnote_links_working() {
+        let rendered = render("# cookie\n\nSee [above](#cookie).[^note]\n\n[^note]: Details.\n");
+        assert!(rendered.html.contains("<h1 id=\"user-content-cookie\">"));
+        assert!(rendered.html.contains("href=\"#user-content-cookie\""));
+        assert!(rendered.html.contains("href=\"#user-content-note\""));
+        assert!(rendered.html.contains("id=\"user-content-note\""));
+    }
+
+    #[test]
+    fn render_supports_gfm_tables_and_task_lists() {
+        let rendered = render("| a | b |\n|---|---|\n| 1 | 2 |\n\n- [x] done\n");
+        assert!(rendered.html.contains("<table>"));
+        assert!(rendered.html.contains("checked"));
+    }
+
+    #[test]
+    fn sanitize_highlight_keeps_only_marks() {
+        assert_eq!(
+            "a <mark>rust</mark> &lt; b",
+            sanitize_highlight("a <mark>rust</mark> <em>&lt;</em> b<script>x</script>")
+        );
+    }
+}
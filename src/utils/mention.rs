@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+// Where `@username` appears in a text. Offsets count UTF-16 code units, the way browsers
+// index strings, so clients can slice the text directly. `end` is exclusive and the range
+// includes the `@`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MentionRange {
+    pub username: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+fn is_username_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+// Every `@username` in `text`, whether or not such a user exists. An `@` right after a
+// word character is not a mention, so email addresses are left alone. Trailing dots and
+// dashes are treated as punctuation: "thanks @bob." mentions "bob".
+pub fn parse(text: &str) -> Vec<MentionRange> {
+    let mut ranges = vec![];
+    let mut chars = text.chars().peekable();
+    let mut offset = 0;
+    let mut previous: Option<char> = None;
+    while let Some(c) = chars.next() {
+        let start = offset;
+        offset += c.len_utf16();
+        let is_mention_start = c == '@'
+            && !previous.is_some_and(|previous| is_username_char(previous) || previous == '@');
+        previous = Some(c);
+        if !is_mention_start {
+            continue;
+        }
+        let mut username = String::new();
+        while let Some(&next) = chars.peek() {
+            if !is_username_char(next) {
+                break;
+            }
+            username.push(next);
+            offset += next.len_utf16();
+            previous = Some(next);
+            chars.next();
+        }
+        let trimmed = username.trim_end_matches(['.', '-']);
+        if trimmed.is_empty() {
+            continue;
+        }
+        ranges.push(MentionRange {
+            username: trimmed.to_string(),
+            start,
+            end: start + 1 + trimmed.encode_utf16().count(),
+        });
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usernames(text: &str) -> Vec<String> {
+        parse(text)
+            .into_iter()
+            .map(|range| range.username)
+            .collect()
+    }
+
+    #[test]
+    fn parse_mentions() {
+        assert_eq!(vec!["bob", "carol_1"], usernames("hi @bob and @carol_1!"));
+        assert_eq!(vec!["bob"], usernames("thanks @bob."));
+        assert_eq!(vec!["jane.doe"], usernames("(@jane.doe)"));
+        assert!(usernames("mail me at bob@example.com").is_empty());
+        assert!(usernames("@ @@bob @.").is_empty());
+    }
+
+    #[test]
+    fn mention_ranges_count_utf16_code_units() {
+        let text = "🎉 @bob";
+        let range = &parse(text)[0];
+        assert_eq!((3, 7), (range.start, range.end));
+        let utf16 = text.encode_utf16().collect::<Vec<_>>();
+        assert_eq!(
+            "@bob",
+            String::from_utf16(&utf16[range.start..range.end]).unwrap()
+        );
+    }
+}
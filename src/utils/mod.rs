@@ -1,6 +1,10 @@
 pub mod converter;
+pub mod cursor;
 pub mod date;
 pub mod db;
 pub mod hasher;
+pub mod mailer;
+pub mod markdown;
+pub mod mention;
 pub mod token;
 pub mod uuid;
\ No newline at end of file
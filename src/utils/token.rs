@@ -3,7 +3,6 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 static KEY: [u8; 16] = *include_bytes!("../../secret.key");
-static ONE_DAY: i64 = 60 * 60 * 24; // in seconds
 
 pub fn decode(token: &str) -> jsonwebtoken::errors::Result<TokenData<Claims>> {
     jsonwebtoken::decode::<Claims>(
@@ -13,8 +12,8 @@ pub fn decode(token: &str) -> jsonwebtoken::errors::Result<TokenData<Claims>> {
     )
 }
 
-pub fn generate(user_id: Uuid, now: i64) -> Result<String, Error> {
-    let claims = Claims::new(user_id, now);
+pub fn generate(user_id: Uuid, now: i64, ttl: i64) -> Result<String, Error> {
+    let claims = Claims::new(user_id, now, ttl);
     jsonwebtoken::encode(&Header::default(), &claims, &EncodingKey::from_secret(&KEY))
 }
 
@@ -31,10 +30,11 @@ pub struct Claims {
 }
 
 impl Claims {
-    pub fn new(user_id: Uuid, now: i64) -> Self {
+    // `ttl` is how many seconds the token stays valid.
+    pub fn new(user_id: Uuid, now: i64, ttl: i64) -> Self {
         Claims {
             iat: now,
-            exp: now + ONE_DAY,
+            exp: now + ttl,
             user_id,
         }
     }
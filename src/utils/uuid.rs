@@ -2,6 +2,6 @@ use crate::error::AppError;
 use uuid::Uuid;
 
 pub fn parse(maybe_uuid: &str) -> Result<Uuid, AppError> {
-    let uuid = Uuid::parse_str(&maybe_uuid)?;
+    let uuid = Uuid::parse_str(maybe_uuid)?;
     Ok(uuid)
 }
\ No newline at end of file